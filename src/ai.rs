@@ -1,86 +1,195 @@
 use bevy::prelude::*;
+use crate::classes::TankClass;
+use serde::{Deserialize, Serialize};
 
-/// Нейронная сеть для управления танком
-#[allow(dead_code)]
+/// Размеры слоёв сети, задающие раскладку генома
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkSpec {
+    pub inputs: usize,
+    pub hidden: usize,
+    pub outputs: usize,
+}
+
+impl NetworkSpec {
+    /// Архитектура, которую использует текущая версия симулятора
+    pub const CURRENT: NetworkSpec = NetworkSpec {
+        inputs: 13,
+        hidden: 8,
+        outputs: 4,
+    };
+
+    /// Архитектуры прошлых версий, от старых к новым: 8 входов — до классов танков,
+    /// 11 — до магазина и нагрева ствола. Входы только дописываются в конец, поэтому
+    /// старый геном переносится в новую раскладку вставкой нулевых весов новых входов
+    /// (см. widen_genome)
+    pub const LEGACY: [NetworkSpec; 2] = [
+        NetworkSpec {
+            inputs: 8,
+            hidden: 8,
+            outputs: 4,
+        },
+        NetworkSpec {
+            inputs: 11,
+            hidden: 8,
+            outputs: 4,
+        },
+    ];
+
+    /// Длина генома: веса вход→скрытый, затем скрытый→выход
+    pub fn genome_len(&self) -> usize {
+        self.inputs * self.hidden + self.hidden * self.outputs
+    }
+
+    /// Геном этой раскладки в раскладке to с теми же слоями, но большим числом входов.
+    /// Веса новых входов нулевые, так что сеть ведёт себя в точности как раньше
+    pub fn widen_genome(&self, genome: &[f32], to: NetworkSpec) -> Vec<f32> {
+        debug_assert!(self.hidden == to.hidden && self.outputs == to.outputs && self.inputs <= to.inputs);
+        let split = self.inputs * self.hidden;
+        let mut widened = genome[..split].to_vec();
+        widened.resize(to.inputs * to.hidden, 0.0);
+        widened.extend_from_slice(&genome[split..]);
+        widened
+    }
+
+    /// Геном любой прошлой архитектуры (узнаётся по длине) в раскладке CURRENT;
+    /// None — длина не соответствует ни одной известной архитектуре
+    pub fn upgrade_genome(genome: &[f32]) -> Option<Vec<f32>> {
+        if genome.len() == Self::CURRENT.genome_len() {
+            return Some(genome.to_vec());
+        }
+        Self::LEGACY
+            .iter()
+            .find(|spec| spec.genome_len() == genome.len())
+            .map(|spec| spec.widen_genome(genome, Self::CURRENT))
+    }
+}
+
+/// Вектор входов сети текущей архитектуры
+pub type Observation = [f32; NetworkSpec::CURRENT.inputs];
+
+/// Имена входов сети в порядке get_inputs (используются при экспорте в ONNX)
+pub const INPUT_NAMES: [&str; NetworkSpec::CURRENT.inputs] = [
+    "health",
+    "enemy_distance",
+    "enemy_direction_x",
+    "enemy_direction_z",
+    "enemy_angle_sin",
+    "enemy_angle_cos",
+    "enemy_health",
+    "heading",
+    "class_light",
+    "class_medium",
+    "class_heavy",
+    "ammo",
+    "heat",
+];
+
+/// Значения входов, которых не было в прошлых версиях, у танка тех времён
+/// (средний класс, полный магазин, холодный ствол); ими дополняются старые
+/// наблюдения (см. upgrade_observation)
+pub const INPUT_DEFAULTS: Observation = [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0];
+
+/// Имена выходов сети (см. TankAction::from_network)
+pub const OUTPUT_NAMES: [&str; 4] = ["throttle", "turn", "fire", "weapon"];
+
+/// Размер скрытого слоя текущей архитектуры (буферы прямого прохода живут на стеке)
+const HIDDEN: usize = NetworkSpec::CURRENT.hidden;
+
+/// Нейронная сеть для управления танком. Собирается один раз при появлении танка
+/// (см. spawn_tank) и хранит веса одним непрерывным массивом в раскладке генома:
+/// матрица вход→скрытый построчно (inputs × hidden), затем скрытый→выход (hidden × outputs).
+#[derive(Component, Clone, Debug, PartialEq)]
 pub struct NeuralNetwork {
-    weights_input_hidden: Vec<Vec<f32>>,
-    weights_hidden_output: Vec<Vec<f32>>,
+    weights: Vec<f32>,
+}
+
+/// Значения нейронов танка на последнем такте: их пишет ai_control_system
+/// из того же прямого прохода, которым танк управляется
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct NetworkActivity {
+    pub inputs: Observation,
+    pub hidden: [f32; HIDDEN],
+    pub outputs: [f32; 4],
 }
 
-#[allow(dead_code)]
 impl NeuralNetwork {
     pub fn from_genome(genome: &[f32]) -> Self {
-        let input_size = 8;
-        let hidden_size = 8;
-        let output_size = 4;
-        
-        let mut idx = 0;
-        let mut weights_input_hidden = vec![vec![0.0; hidden_size]; input_size];
-        let mut weights_hidden_output = vec![vec![0.0; output_size]; hidden_size];
-        
-        // Заполняем веса из генома
-        for i in 0..input_size {
-            for j in 0..hidden_size {
-                weights_input_hidden[i][j] = genome[idx];
-                idx += 1;
-            }
-        }
-        
-        for i in 0..hidden_size {
-            for j in 0..output_size {
-                weights_hidden_output[i][j] = genome[idx];
-                idx += 1;
-            }
-        }
-        
-        Self {
-            weights_input_hidden,
-            weights_hidden_output,
-        }
+        let len = NetworkSpec::CURRENT.genome_len();
+        let mut weights = vec![0.0; len];
+        let copied = genome.len().min(len);
+        weights[..copied].copy_from_slice(&genome[..copied]);
+        Self { weights }
     }
-    
-    pub fn forward(&self, inputs: &[f32; 8]) -> [f32; 4] {
-        let hidden_size = self.weights_input_hidden[0].len();
-        let mut hidden = vec![0.0; hidden_size];
-        
-        // Входной слой -> скрытый слой
-        for i in 0..hidden_size {
-            let mut sum = 0.0;
-            for j in 0..inputs.len() {
-                sum += inputs[j] * self.weights_input_hidden[j][i];
+
+    pub fn forward(&self, inputs: &Observation) -> [f32; 4] {
+        let mut activity = [NetworkActivity { inputs: *inputs, ..default() }];
+        Self::forward_batch(&[self], &mut activity);
+        activity[0].outputs
+    }
+
+    /// Прямой проход сразу для всех танков: входы складываются в матрицу batch × inputs,
+    /// и каждый слой считается целиком по всей пачке (пакетное умножение, у каждой
+    /// строки свои веса). Скрытый слой и выходы остаются в batch для визуализации.
+    pub fn forward_batch(networks: &[&NeuralNetwork], batch: &mut [NetworkActivity]) {
+        let NetworkSpec { inputs: input_size, hidden: hidden_size, outputs: output_size } = NetworkSpec::CURRENT;
+        let w2_offset = input_size * hidden_size;
+        debug_assert_eq!(networks.len(), batch.len());
+
+        // Входной слой -> скрытый слой: h = σ(x · W1)
+        for (network, row) in networks.iter().zip(batch.iter_mut()) {
+            row.hidden = [0.0; HIDDEN];
+            for (weights, &value) in network.weights[..w2_offset].chunks_exact(hidden_size).zip(&row.inputs) {
+                for (sum, &weight) in row.hidden.iter_mut().zip(weights) {
+                    *sum += value * weight;
+                }
             }
-            hidden[i] = Self::activation(sum);
+            row.hidden.iter_mut().for_each(|sum| *sum = Self::activation(*sum));
         }
-        
-        // Скрытый слой -> выходной слой
-        let mut outputs = [0.0; 4];
-        for i in 0..4 {
-            let mut sum = 0.0;
-            for j in 0..hidden_size {
-                sum += hidden[j] * self.weights_hidden_output[j][i];
+
+        // Скрытый слой -> выходной слой: o = σ(h · W2)
+        for (network, row) in networks.iter().zip(batch.iter_mut()) {
+            row.outputs = [0.0; 4];
+            for (weights, &value) in network.weights[w2_offset..].chunks_exact(output_size).zip(&row.hidden) {
+                for (sum, &weight) in row.outputs.iter_mut().zip(weights) {
+                    *sum += value * weight;
+                }
             }
-            outputs[i] = Self::activation(sum);
+            row.outputs.iter_mut().for_each(|sum| *sum = Self::activation(*sum));
         }
-        
-        outputs
     }
-    
+
+    /// Вес связи входа i со скрытым нейроном j
+    pub fn input_hidden_weight(&self, i: usize, j: usize) -> f32 {
+        self.weights[i * NetworkSpec::CURRENT.hidden + j]
+    }
+
+    /// Вес связи скрытого нейрона j с выходом k
+    pub fn hidden_output_weight(&self, j: usize, k: usize) -> f32 {
+        let NetworkSpec { inputs, hidden, outputs } = NetworkSpec::CURRENT;
+        self.weights[inputs * hidden + j * outputs + k]
+    }
+
     fn activation(x: f32) -> f32 {
         // Sigmoid activation (0 to 1)
         1.0 / (1.0 + (-x).exp())
     }
     
-    /// Получает входные данные для нейронной сети из окружения
+    /// Получает входные данные для нейронной сети из окружения.
+    /// Здоровье своё и врага — доля от максимума класса; патроны и нагрев — выбранного оружия
+    #[allow(clippy::too_many_arguments)]
     pub fn get_inputs(
         tank_pos: Vec3,
         tank_rotation: f32,
         tank_health: f32,
+        tank_class: TankClass,
+        ammo: f32,
+        heat: f32,
         nearest_enemy_pos: Option<Vec3>,
         nearest_enemy_health: Option<f32>,
-    ) -> [f32; 8] {
-        let mut inputs = [0.0; 8];
+    ) -> Observation {
+        let mut inputs = [0.0; NetworkSpec::CURRENT.inputs];
         
-        inputs[0] = tank_health / 100.0; // Нормализованное здоровье
+        inputs[0] = tank_health; // Нормализованное здоровье
         
         if let Some(enemy_pos) = nearest_enemy_pos {
             let direction = enemy_pos - tank_pos;
@@ -97,13 +206,28 @@ impl NeuralNetwork {
         }
         
         if let Some(enemy_health) = nearest_enemy_health {
-            inputs[6] = enemy_health / 100.0;
+            inputs[6] = enemy_health;
         }
         
         inputs[7] = tank_rotation / std::f32::consts::PI; // Нормализованный угол
+        inputs[8..11].copy_from_slice(&tank_class.one_hot());
+        inputs[11] = ammo; // Доля патронов в магазине, 0 — перезарядка
+        inputs[12] = heat; // Нагрев ствола, 1 — перегрев
         
         inputs
     }
+
+    /// Наблюдение прошлой версии (узнаётся по длине) с недостающими входами из INPUT_DEFAULTS
+    pub fn upgrade_observation(values: &[f32]) -> Option<Observation> {
+        let known = values.len() == NetworkSpec::CURRENT.inputs
+            || NetworkSpec::LEGACY.iter().any(|spec| spec.inputs == values.len());
+        if !known {
+            return None;
+        }
+        let mut inputs = INPUT_DEFAULTS;
+        inputs[..values.len()].copy_from_slice(values);
+        Some(inputs)
+    }
 }
 
 #[cfg(test)]
@@ -112,11 +236,71 @@ mod tests {
     
     #[test]
     fn test_neural_network() {
-        let genome = vec![0.5; 96]; // 8*8 + 8*4
+        let genome = vec![0.5; NetworkSpec::CURRENT.genome_len()];
         let nn = NeuralNetwork::from_genome(&genome);
-        let inputs = [0.0, 0.5, 0.3, -0.2, 0.1, 0.9, 0.7, -0.1];
+        let inputs = [0.0, 0.5, 0.3, -0.2, 0.1, 0.9, 0.7, -0.1, 0.0, 1.0, 0.0, 1.0, 0.0];
         let outputs = nn.forward(&inputs);
         
         assert_eq!(outputs.len(), 4);
     }
+
+    #[test]
+    fn batch_matches_single_forward() {
+        // Сверяем с прямым счётом по раскладке генома: W1[i][j] = genome[i*hidden + j]
+        let NetworkSpec { inputs: n_in, hidden: n_hidden, outputs: n_out } = NetworkSpec::CURRENT;
+        let w2 = n_in * n_hidden;
+        let genomes: Vec<Vec<f32>> = (0..3)
+            .map(|n| (0..NetworkSpec::CURRENT.genome_len()).map(|k| ((k * 7 + n * 13) % 17) as f32 / 17.0 - 0.5).collect())
+            .collect();
+        let inputs = [
+            [0.0, 0.5, 0.3, -0.2, 0.1, 0.9, 0.7, -0.1, 1.0, 0.0, 0.0, 0.6, 0.2],
+            [1.0, 0.2, -0.6, 0.8, -0.9, 0.4, 0.0, 0.5, 0.0, 0.0, 1.0, 0.0, 1.0],
+            [0.3; NetworkSpec::CURRENT.inputs],
+        ];
+        let networks: Vec<NeuralNetwork> = genomes.iter().map(|g| NeuralNetwork::from_genome(g)).collect();
+        let refs: Vec<&NeuralNetwork> = networks.iter().collect();
+        let mut activity: Vec<NetworkActivity> = inputs.iter().map(|&x| NetworkActivity { inputs: x, ..default() }).collect();
+        NeuralNetwork::forward_batch(&refs, &mut activity);
+        let batch: Vec<[f32; 4]> = activity.iter().map(|a| a.outputs).collect();
+
+        let sigmoid = |x: f32| 1.0 / (1.0 + (-x).exp());
+        for ((genome, x), out) in genomes.iter().zip(&inputs).zip(&batch) {
+            let hidden: Vec<f32> = (0..n_hidden).map(|j| sigmoid((0..n_in).map(|i| x[i] * genome[i * n_hidden + j]).sum())).collect();
+            for k in 0..n_out {
+                let expected = sigmoid((0..n_hidden).map(|j| hidden[j] * genome[w2 + j * n_out + k]).sum());
+                assert!((out[k] - expected).abs() < 1e-6);
+            }
+        }
+        assert_eq!(networks[1].forward(&inputs[1]), batch[1]);
+        assert_eq!(networks[2].input_hidden_weight(3, 5), genomes[2][3 * n_hidden + 5]);
+        assert_eq!(networks[2].hidden_output_weight(6, 1), genomes[2][w2 + 6 * n_out + 1]);
+    }
+
+    #[test]
+    fn upgraded_genome_keeps_old_behaviour() {
+        // Сеть на 8 входов, посчитанная вручную, и она же после переноса в текущую раскладку
+        let old = NetworkSpec::LEGACY[0];
+        let genome: Vec<f32> = (0..old.genome_len()).map(|k| ((k * 5) % 11) as f32 / 11.0 - 0.5).collect();
+        let observation = [0.8, 0.4, 0.6, -0.8, 0.3, 0.95, 0.5, 0.25];
+
+        let sigmoid = |x: f32| 1.0 / (1.0 + (-x).exp());
+        let w2 = old.inputs * old.hidden;
+        let hidden: Vec<f32> = (0..old.hidden)
+            .map(|j| sigmoid((0..old.inputs).map(|i| observation[i] * genome[i * old.hidden + j]).sum()))
+            .collect();
+        let expected: Vec<f32> = (0..old.outputs)
+            .map(|k| sigmoid((0..old.hidden).map(|j| hidden[j] * genome[w2 + j * old.outputs + k]).sum()))
+            .collect();
+
+        let upgraded = NetworkSpec::upgrade_genome(&genome).unwrap();
+        assert_eq!(upgraded.len(), NetworkSpec::CURRENT.genome_len());
+        let inputs = NeuralNetwork::upgrade_observation(&observation).unwrap();
+        assert_eq!(inputs[8..11], TankClass::Medium.one_hot());
+        assert_eq!(inputs[11..13], [1.0, 0.0]);
+        let outputs = NeuralNetwork::from_genome(&upgraded).forward(&inputs);
+        for (out, expected) in outputs.iter().zip(&expected) {
+            assert!((out - expected).abs() < 1e-6);
+        }
+        assert_eq!(NetworkSpec::upgrade_genome(&[0.0; 7]), None);
+    }
 }
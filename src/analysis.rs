@@ -0,0 +1,400 @@
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+use bevy::time::Fixed;
+use crate::ai::{NetworkActivity, NeuralNetwork, Observation, INPUT_NAMES, OUTPUT_NAMES};
+use crate::checkpoint::write_atomic;
+use crate::classes::{ClassDef, TankClass};
+use crate::weapons::Weapons;
+use crate::components::{AIController, FireCooldown, Tank, TeamColor};
+use crate::controller::{apply_tank_actions, TankAction};
+use crate::systems::{ai_control_system, BASE_SIM_DT};
+use crate::Headless;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Сколько выходов сети анализируется: газ, поворот, выстрел (выбор оружия — нет)
+const ACTION_OUTPUTS: usize = 3;
+/// Длительность одного сценария, тактов (10 с)
+const SCENARIO_TICKS: u32 = 500;
+/// Для анализа чувствительности берётся каждый N-й такт сценариев
+const SAMPLE_EVERY: u32 = 5;
+/// Шаг для оценки производной выхода по входу
+const GRADIENT_STEP: f32 = 0.01;
+/// Выстрел считается прицельным, если враг в пределах этого угла от ствола
+const AIM_CONE: f32 = 0.26;
+
+/// Контролируемый сценарий: неподвижный враг на заданной дистанции и пеленге
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Scenario {
+    /// Класс самого танка (встроенные характеристики)
+    pub class: TankClass,
+    /// Здоровье самого танка, доля от максимума
+    pub health: f32,
+    pub distance: f32,
+    /// Пеленг врага относительно оси орудия (локальная +Z, от неё же считают входы
+    /// enemy_angle_sin/cos), градусы (положительный — слева)
+    pub bearing: f32,
+    /// Здоровье врага, доля от максимума
+    pub enemy_health: f32,
+}
+
+impl Scenario {
+    /// Сетка сценариев: 3 класса × 4 дистанции × 8 пеленгов × 2 уровня своего здоровья × 2 — здоровья врага
+    pub fn grid() -> Vec<Scenario> {
+        let mut scenarios = Vec::new();
+        for class in TankClass::ALL {
+            for distance in [10.0, 20.0, 35.0, 50.0] {
+                for bearing in (0..8).map(|i| i as f32 * 45.0 - 135.0) {
+                    for health in [1.0, 0.4] {
+                        for enemy_health in [1.0, 0.4] {
+                            scenarios.push(Scenario { class, health, distance, bearing, enemy_health });
+                        }
+                    }
+                }
+            }
+        }
+        scenarios
+    }
+}
+
+/// Итог одного сценария
+#[derive(Clone, Debug, Serialize)]
+pub struct ScenarioResult {
+    #[serde(flatten)]
+    pub scenario: Scenario,
+    pub final_distance: f32,
+    pub min_distance: f32,
+    pub shots: u32,
+    pub aimed_shots: u32,
+}
+
+/// Чувствительность выходов (газ, поворот, выстрел) к одному входу
+#[derive(Clone, Debug, Serialize)]
+pub struct InputSensitivity {
+    pub name: String,
+    /// Среднее и разброс входа на тактах сценариев
+    pub mean: f32,
+    pub std: f32,
+    /// Средний |Δ выхода| при замене входа его средним значением
+    pub ablation: [f32; ACTION_OUTPUTS],
+    /// Средняя |∂ выхода / ∂ входа|
+    pub gradient: [f32; ACTION_OUTPUTS],
+    /// Доля тактов, где замена входа меняет решение стрелять
+    pub fire_flips: f32,
+}
+
+impl InputSensitivity {
+    /// Общий вклад входа: сумма абляций по выходам
+    pub fn importance(&self) -> f32 {
+        self.ablation.iter().sum()
+    }
+}
+
+/// Поведение сети во всех сценариях
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct BehaviourStats {
+    /// Медианная дистанция до врага во второй половине сценариев — где танк «оседает»
+    pub preferred_range: f32,
+    /// Медианная дистанция в тактах, когда сеть хочет стрелять
+    pub firing_range: Option<f32>,
+    /// Средняя скорость поворота корпуса, рад/с
+    pub turn_rate: f32,
+    /// Доля тактов с командой на выстрел
+    pub fire_rate: f32,
+    pub shots_per_second: f32,
+    /// Доля выстрелов, когда враг был в конусе прицеливания
+    pub aimed_share: f32,
+    pub mean_throttle: f32,
+    /// Доля тактов, когда танк едет назад
+    pub reverse_share: f32,
+}
+
+/// Отчёт анализа генома
+#[derive(Clone, Debug, Serialize)]
+pub struct AnalysisReport {
+    pub agent: String,
+    pub samples: usize,
+    /// Входы в порядке убывания важности
+    pub inputs: Vec<InputSensitivity>,
+    pub behaviour: BehaviourStats,
+    pub scenarios: Vec<ScenarioResult>,
+}
+
+/// Такты прогона сценария, нужные для статистики
+struct Trace {
+    observations: Vec<Observation>,
+    distances: Vec<f32>,
+    actions: Vec<TankAction>,
+    /// Скорость поворота корпуса класса, рад/с
+    rotation_speed: f32,
+    result: ScenarioResult,
+}
+
+/// Прогоняет сеть в сценарии: в отдельном мире без рендера стоят сам танк с сетью и
+/// неподвижный враг, а каждый такт идут те же системы, что в бою, — ai_control_system
+/// и apply_tank_actions. Препятствий и ответного огня нет
+fn rollout(network: &NeuralNetwork, weapons: &Weapons, scenario: Scenario) -> Trace {
+    let class = ClassDef::builtin(scenario.class);
+    let enemy_class = ClassDef::builtin(TankClass::Medium);
+    let mut world = World::new();
+    world.insert_resource(Time::<Fixed>::default());
+    world.insert_resource(Headless(true));
+    world.insert_resource(weapons.clone());
+
+    let start = Transform::from_xyz(0.0, 0.5, 0.0);
+    let direction = Quat::from_rotation_y(scenario.bearing.to_radians()) * start.back();
+    let enemy_position = start.translation + direction * scenario.distance;
+    let tank = world
+        .spawn((
+            start,
+            Tank {
+                health: class.health * scenario.health,
+                ..Tank::of_class(&class, 0)
+            },
+            TeamColor(Color::WHITE),
+            FireCooldown::from_seconds(class.cooldown),
+            TankAction::default(),
+            network.clone(),
+            NetworkActivity::default(),
+            AIController::with_genome(Vec::new()),
+        ))
+        .id();
+    world.spawn((
+        Transform::from_translation(enemy_position),
+        Tank {
+            health: enemy_class.health * scenario.enemy_health,
+            ..Tank::of_class(&enemy_class, 1)
+        },
+        TeamColor(Color::WHITE),
+        FireCooldown::from_seconds(enemy_class.cooldown),
+        TankAction::default(),
+    ));
+
+    let mut trace = Trace {
+        observations: Vec::new(),
+        distances: Vec::new(),
+        actions: Vec::new(),
+        rotation_speed: class.rotation_speed,
+        result: ScenarioResult {
+            scenario,
+            final_distance: scenario.distance,
+            min_distance: scenario.distance,
+            shots: 0,
+            aimed_shots: 0,
+        },
+    };
+    for _ in 0..SCENARIO_TICKS {
+        world.resource_mut::<Time<Fixed>>().advance_by(Duration::from_secs_f64(BASE_SIM_DT));
+        world.run_system_once(ai_control_system);
+        trace.observations.push(world.get::<NetworkActivity>(tank).unwrap().inputs);
+        trace.actions.push(*world.get::<TankAction>(tank).unwrap());
+        trace.distances.push(world.get::<Transform>(tank).unwrap().translation.distance(enemy_position));
+
+        let shots_before = world.get::<AIController>(tank).unwrap().behavior.shots_fired;
+        world.run_system_once(apply_tank_actions);
+        let transform = *world.get::<Transform>(tank).unwrap();
+        if world.get::<AIController>(tank).unwrap().behavior.shots_fired > shots_before {
+            trace.result.shots += 1;
+            // Снаряд летит по оси орудия: корпус, повёрнутый на угол башни (см. WeaponDef::launch)
+            let turret_angle = world.get::<Tank>(tank).unwrap().turret_angle;
+            let gun = transform.rotation * Quat::from_rotation_y(turret_angle) * Vec3::Z;
+            if gun.angle_between(enemy_position - transform.translation) < AIM_CONE {
+                trace.result.aimed_shots += 1;
+            }
+        }
+
+        let distance = transform.translation.distance(enemy_position);
+        trace.result.min_distance = trace.result.min_distance.min(distance);
+        trace.result.final_distance = distance;
+    }
+    trace
+}
+
+/// Анализ генома: поведение в сетке сценариев и чувствительность к входам
+pub fn analyze(agent: &str, ai: &AIController, weapons: &Weapons) -> AnalysisReport {
+    let network = NeuralNetwork::from_genome(&ai.genome);
+    let traces: Vec<Trace> = Scenario::grid().into_iter().map(|s| rollout(&network, weapons, s)).collect();
+
+    let samples: Vec<Observation> = traces
+        .iter()
+        .flat_map(|t| t.observations.iter().step_by(SAMPLE_EVERY as usize).copied())
+        .collect();
+    let mut inputs: Vec<InputSensitivity> = (0..INPUT_NAMES.len()).map(|i| sensitivity(&network, &samples, i)).collect();
+    inputs.sort_by(|a, b| b.importance().total_cmp(&a.importance()));
+
+    AnalysisReport {
+        agent: agent.to_string(),
+        samples: samples.len(),
+        inputs,
+        behaviour: behaviour(&traces),
+        scenarios: traces.into_iter().map(|t| t.result).collect(),
+    }
+}
+
+/// Абляция (вход заменён средним) и производная по входу i на всех образцах
+fn sensitivity(network: &NeuralNetwork, samples: &[Observation], i: usize) -> InputSensitivity {
+    let n = samples.len().max(1) as f32;
+    let mean = samples.iter().map(|x| x[i]).sum::<f32>() / n;
+    let std = (samples.iter().map(|x| (x[i] - mean).powi(2)).sum::<f32>() / n).sqrt();
+
+    let mut ablation = [0.0; ACTION_OUTPUTS];
+    let mut gradient = [0.0; ACTION_OUTPUTS];
+    let mut fire_flips = 0;
+    for x in samples {
+        let base = network.forward(x);
+        let with = |value: f32| {
+            let mut changed = *x;
+            changed[i] = value;
+            network.forward(&changed)
+        };
+        let ablated = with(mean);
+        let (plus, minus) = (with(x[i] + GRADIENT_STEP), with(x[i] - GRADIENT_STEP));
+        for k in 0..ACTION_OUTPUTS {
+            ablation[k] += (ablated[k] - base[k]).abs() / n;
+            gradient[k] += ((plus[k] - minus[k]) / (2.0 * GRADIENT_STEP)).abs() / n;
+        }
+        if (ablated[2] > 0.5) != (base[2] > 0.5) {
+            fire_flips += 1;
+        }
+    }
+    InputSensitivity {
+        name: INPUT_NAMES[i].to_string(),
+        mean,
+        std,
+        ablation,
+        gradient,
+        fire_flips: fire_flips as f32 / n,
+    }
+}
+
+fn behaviour(traces: &[Trace]) -> BehaviourStats {
+    let ticks = traces.iter().map(|t| t.actions.len()).sum::<usize>().max(1) as f32;
+    let actions = || traces.iter().flat_map(|t| t.actions.iter());
+    let settled: Vec<f32> = traces.iter().flat_map(|t| t.distances[t.distances.len() / 2..].iter().copied()).collect();
+    let firing: Vec<f32> = traces
+        .iter()
+        .flat_map(|t| t.distances.iter().zip(&t.actions).filter(|(_, a)| a.fire).map(|(d, _)| *d))
+        .collect();
+    let shots: u32 = traces.iter().map(|t| t.result.shots).sum();
+    let aimed: u32 = traces.iter().map(|t| t.result.aimed_shots).sum();
+
+    BehaviourStats {
+        preferred_range: median(settled).unwrap_or(0.0),
+        firing_range: median(firing),
+        turn_rate: traces
+            .iter()
+            .map(|t| t.actions.iter().map(|a| a.turn.abs()).sum::<f32>() * t.rotation_speed)
+            .sum::<f32>()
+            / ticks,
+        fire_rate: actions().filter(|a| a.fire).count() as f32 / ticks,
+        shots_per_second: shots as f32 / (ticks * BASE_SIM_DT as f32),
+        aimed_share: if shots > 0 { aimed as f32 / shots as f32 } else { 0.0 },
+        mean_throttle: actions().map(|a| a.throttle).sum::<f32>() / ticks,
+        reverse_share: actions().filter(|a| a.throttle < -0.1).count() as f32 / ticks,
+    }
+}
+
+fn median(mut values: Vec<f32>) -> Option<f32> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f32::total_cmp);
+    Some(values[values.len() / 2])
+}
+
+impl AnalysisReport {
+    /// Таблица для консоли
+    pub fn summary(&self) -> String {
+        let b = &self.behaviour;
+        let mut text = format!(
+            "Анализ «{}»: сценариев {}, образцов {}\n\
+             Предпочитаемая дистанция {:.1}, стреляет с {}\n\
+             Поворот {:.2} рад/с, газ {:+.2} (назад {:.0}% тактов)\n\
+             Команда огня {:.0}% тактов, {:.2} выстр./с, прицельных {:.0}%\n\n\
+             {:<18} {:>8} {:>8} {:>8} {:>8}\n",
+            self.agent,
+            self.scenarios.len(),
+            self.samples,
+            b.preferred_range,
+            b.firing_range.map_or("—".to_string(), |r| format!("{:.1}", r)),
+            b.turn_rate,
+            b.mean_throttle,
+            b.reverse_share * 100.0,
+            b.fire_rate * 100.0,
+            b.shots_per_second,
+            b.aimed_share * 100.0,
+            "вход",
+            OUTPUT_NAMES[0],
+            OUTPUT_NAMES[1],
+            OUTPUT_NAMES[2],
+            "огонь±",
+        );
+        for input in &self.inputs {
+            text += &format!(
+                "{:<18} {:>8.3} {:>8.3} {:>8.3} {:>7.0}%\n",
+                input.name,
+                input.ablation[0],
+                input.ablation[1],
+                input.ablation[2],
+                input.fire_flips * 100.0
+            );
+        }
+        text
+    }
+
+    /// Пишет <префикс>.json
+    pub fn write(&self, prefix: &Path) -> std::io::Result<PathBuf> {
+        let path = prefix.with_extension("json");
+        write_atomic(&path, &serde_json::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::NetworkSpec;
+
+    #[test]
+    fn ablation_finds_the_only_used_input() {
+        // Поворот зависит только от синуса угла на врага: вход 4 -> скрытый 0 -> выход 1
+        let NetworkSpec { inputs, hidden, .. } = NetworkSpec::CURRENT;
+        let mut genome = vec![0.0; NetworkSpec::CURRENT.genome_len()];
+        genome[4 * hidden] = 4.0;
+        genome[inputs * hidden + 1] = 4.0;
+        let report = analyze("test", &AIController::with_genome(genome), &Weapons::default());
+
+        assert_eq!(report.inputs[0].name, "enemy_angle_sin");
+        assert!(report.inputs[0].ablation[1] > 0.01);
+        assert!(report.inputs[1..].iter().all(|input| input.importance() < 1e-6));
+        assert_eq!(report.scenarios.len(), Scenario::grid().len());
+        // Газ и выстрел на нуле весов стоят на 0.5: танк только поворачивает и не стреляет
+        assert!(report.behaviour.turn_rate > 0.0);
+        assert_eq!(report.behaviour.mean_throttle, 0.0);
+        assert_eq!(report.behaviour.fire_rate, 0.0);
+    }
+
+    #[test]
+    fn bearing_and_aim_follow_the_gun_axis() {
+        // Сеть всегда стреляет и стоит на месте: выход 2 смещён через все скрытые нейроны
+        let NetworkSpec { inputs, hidden, outputs } = NetworkSpec::CURRENT;
+        let mut genome = vec![0.0; NetworkSpec::CURRENT.genome_len()];
+        for j in 0..hidden {
+            genome[inputs * hidden + j * outputs + 2] = 4.0;
+        }
+        let network = NeuralNetwork::from_genome(&genome);
+        let scenario = |bearing| Scenario { class: TankClass::Medium, health: 1.0, distance: 20.0, bearing, enemy_health: 1.0 };
+
+        // Нулевой пеленг — враг прямо по стволу: входы угла (sin, cos) = (0, 1), все выстрелы прицельные
+        let ahead = rollout(&network, &Weapons::default(), scenario(0.0));
+        assert!(ahead.observations[0][4].abs() < 1e-5 && (ahead.observations[0][5] - 1.0).abs() < 1e-5);
+        assert!(ahead.result.shots > 0);
+        assert_eq!(ahead.result.aimed_shots, ahead.result.shots);
+
+        // Враг за кормой: стрельба мимо
+        let behind = rollout(&network, &Weapons::default(), scenario(180.0));
+        assert!((behind.observations[0][5] + 1.0).abs() < 1e-5);
+        assert_eq!(behind.result.aimed_shots, 0);
+    }
+}
@@ -1,11 +1,23 @@
 use bevy::prelude::*;
 use bevy::prelude::shape;
+use crate::ai::{NetworkActivity, NeuralNetwork};
 use crate::components::*;
 use crate::genetics::Population;
+use crate::bots::{self, Benchmark, SparringBots};
+use crate::champion::FixedOpponents;
+use crate::classes::{ClassDef, ClassPicker, TankClasses};
+use crate::controller::TankAction;
+use crate::curriculum;
+use crate::history::HistoryDb;
+use crate::metrics::{BattleSummary, MetricsLog};
+use crate::rating;
+use crate::server::{self, ExternalOpponent};
+use crate::tournament::Tournament;
 use crate::systems::{BASE_SIM_DT, ProgressLog};
 use std::time::{Duration, Instant};
 use crate::Headless;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 #[derive(Resource, Default)]
 #[allow(dead_code)]
@@ -14,6 +26,12 @@ pub struct BattleState {
     pub max_ticks: u32,
     pub real_time: f32,
     pub teams: Vec<TeamStats>,
+    /// Команды и контроллеры танков, уничтоженных в этом бою
+    pub fallen: Vec<(u32, AIController)>,
+    /// Потери команд в этом бою
+    pub deaths: [u32; 2],
+    /// Суммарное здоровье команд раз в секунду боя: (время, [синие, красные])
+    pub strength: Vec<(f32, [f32; 2])>,
 }
 
 #[allow(dead_code)]
@@ -33,6 +51,95 @@ impl Default for TeamStats {
     }
 }
 
+/// Размер команды в бою между поколениями
+pub const MATCH_TEAM_SIZE: usize = 5;
+
+/// Какой бой сейчас идёт
+#[derive(Resource, Default, Clone, Copy, Debug, PartialEq)]
+pub enum BattleKind {
+    /// Обычный бой популяции, результат идёт в эволюцию
+    #[default]
+    Training,
+    /// Чемпион поколения A (синие) против чемпиона поколения B (красные)
+    GenerationMatch { generation_a: u32, generation_b: u32 },
+    /// Матч турнира между участниками с указанными индексами
+    Tournament { entrant_a: usize, entrant_b: usize },
+    /// Матч бенчмарка: агент (синие) против ботов стратегии с указанным индексом
+    Benchmark { strategy: usize },
+    /// Эпизод внешнего агента (режим --serve); карта и позиции задаются зерном
+    External { seed: u64, opponent: ExternalOpponent, opponents: usize },
+}
+
+impl BattleKind {
+    /// Состояние, в которое переходим после боя
+    pub fn after_battle(&self) -> crate::GameState {
+        match self {
+            BattleKind::Tournament { .. } | BattleKind::Benchmark { .. } => crate::GameState::Tournament,
+            // Следующий эпизод начинает клиент
+            BattleKind::External { .. } => crate::GameState::Setup,
+            _ => crate::GameState::Evolution,
+        }
+    }
+}
+
+/// Очередь запрошенных боёв между поколениями
+#[derive(Resource, Default)]
+pub struct MatchQueue {
+    pub pending: std::collections::VecDeque<(u32, u32)>,
+    /// Завершить программу, когда очередь опустеет (запуск из командной строки)
+    pub exit_when_done: bool,
+}
+
+impl MatchQueue {
+    /// Все бои из командной строки сыграны или пропущены — пора завершать программу
+    pub fn finished(&self) -> bool {
+        self.exit_when_done && self.pending.is_empty()
+    }
+}
+
+/// Итог боя между поколениями
+#[derive(Clone, Debug)]
+pub struct MatchResult {
+    pub generation_a: u32,
+    pub generation_b: u32,
+    pub alive: [u32; 2],
+    pub health: [f32; 2],
+    pub duration: f32,
+    /// Поколение-победитель, None — ничья
+    pub winner: Option<u32>,
+}
+
+impl MatchResult {
+    pub fn summary(&self) -> String {
+        let outcome = match self.winner {
+            Some(generation) => format!("победа поколения {}", generation),
+            None => "ничья".to_string(),
+        };
+        format!(
+            "Поколение {} vs {}: {} (выжило {}:{}, здоровье {:.0}:{:.0}, {:.1}s)",
+            self.generation_a,
+            self.generation_b,
+            outcome,
+            self.alive[0],
+            self.alive[1],
+            self.health[0],
+            self.health[1],
+            self.duration
+        )
+    }
+}
+
+/// Результаты сыгранных боёв между поколениями
+#[derive(Resource, Default)]
+pub struct MatchHistory {
+    pub results: Vec<MatchResult>,
+}
+
+/// Предикат для run_if: идёт обычный тренировочный бой
+pub fn is_training_battle(kind: Res<BattleKind>) -> bool {
+    *kind == BattleKind::Training
+}
+
 /// Начало битвы
 pub fn start_battle(
     mut battle_state: ResMut<BattleState>,
@@ -40,20 +147,115 @@ pub fn start_battle(
     battle_state.tick_count = 0;
     battle_state.real_time = 0.0;
     battle_state.max_ticks = (120.0 / BASE_SIM_DT) as u32; // 120 секунд симуляции
+    battle_state.fallen.clear();
+    battle_state.deaths = [0; 2];
+    battle_state.strength.clear();
     info!("Битва началась!");
 }
 
 /// Завершение битвы
+#[allow(clippy::too_many_arguments)]
 pub fn end_battle(
     mut population: ResMut<Population>,
-    query: Query<(Entity, &AIController)>,
+    mut battle_state: ResMut<BattleState>,
+    battle_kind: Res<BattleKind>,
+    mut match_history: ResMut<MatchHistory>,
+    tournament: Option<ResMut<Tournament>>,
+    benchmark: Option<ResMut<Benchmark>>,
+    metrics: Res<MetricsLog>,
+    history: Res<HistoryDb>,
+    query: Query<(Entity, &Tank, &AIController), Without<ExhibitionTank>>,
+    tank_query: Query<&Tank>,
     mut next_state: ResMut<NextState<crate::GameState>>,
     mut progress: ResMut<ProgressLog>,
 ) {
-    // Обновляем фитнес всех танков
-    for (entity, ai) in query.iter() {
-        population.calculate_fitness(entity, ai);
+    next_state.set(battle_kind.after_battle());
+    // Эпизоды внешнего агента ведёт клиент: в метрики и базу истории они не попадают
+    if let BattleKind::External { .. } = *battle_kind {
+        return;
     }
+    let (alive, health) = surviving_teams(&tank_query);
+    let score_a = rating::team_score(alive, health);
+
+    let summary = BattleSummary {
+        generation: population.generation,
+        kind: match *battle_kind {
+            BattleKind::Training => "training",
+            BattleKind::GenerationMatch { .. } => "generation_match",
+            BattleKind::Tournament { .. } => "tournament",
+            BattleKind::Benchmark { .. } => "benchmark",
+            BattleKind::External { .. } => "external",
+        }
+        .to_string(),
+        ticks: battle_state.tick_count,
+        duration: battle_state.real_time,
+        alive,
+        health,
+        deaths: battle_state.deaths,
+        score_a,
+    };
+    if let Err(e) = metrics.record_battle(&summary) {
+        error!("Не удалось записать метрики боя: {}", e);
+    }
+    if let Err(e) = history.record_battle(&summary) {
+        error!("Не удалось записать бой в базу истории: {}", e);
+    }
+
+    if let BattleKind::Tournament { entrant_a, entrant_b } = *battle_kind {
+        if let Some(mut tournament) = tournament {
+            println!(
+                "Матч турнира: {} vs {} — выжило {}:{}, здоровье {:.0}:{:.0}",
+                tournament.entrants[entrant_a].name,
+                tournament.entrants[entrant_b].name,
+                alive[0],
+                alive[1],
+                health[0],
+                health[1]
+            );
+            tournament.record(entrant_a, entrant_b, score_a);
+        }
+        return;
+    }
+
+    if let BattleKind::Benchmark { strategy } = *battle_kind {
+        if let Some(mut benchmark) = benchmark {
+            println!(
+                "Бенчмарк: {} vs {} — выжило {}:{}, здоровье {:.0}:{:.0}",
+                benchmark.agent_name,
+                benchmark.records[strategy].strategy.name(),
+                alive[0],
+                alive[1],
+                health[0],
+                health[1]
+            );
+            benchmark.record(strategy, score_a);
+        }
+        return;
+    }
+
+    if let BattleKind::GenerationMatch { generation_a, generation_b } = *battle_kind {
+        population.record_match_outcome(generation_a, generation_b, score_a);
+        let result = MatchResult {
+            generation_a,
+            generation_b,
+            alive,
+            health,
+            duration: battle_state.real_time,
+            winner: match score_a {
+                s if s > 0.5 => Some(generation_a),
+                s if s < 0.5 => Some(generation_b),
+                _ => None,
+            },
+        };
+        println!("Бой поколений завершён. {}", result.summary());
+        match_history.results.push(result);
+        return;
+    }
+
+    let survivors: Vec<(u32, &AIController)> = query.iter().map(|(_, tank, ai)| (tank.team, ai)).collect();
+    let fallen = std::mem::take(&mut battle_state.fallen);
+    score_training_battle(&mut population, &survivors, &fallen, score_a);
+    population.evaluation_battle += 1;
 
     // Выводим итоги боя в консоль: лучшее за матч и глобальный максимум
     let best_current = population
@@ -76,92 +278,290 @@ pub fn end_battle(
             best_max
         );
     }
-    next_state.set(crate::GameState::Evolution);
+}
+
+/// Фитнес и рейтинг Эло всех танков популяции после тренировочного боя. Павших
+/// (их контроллеры собирает collision_system) считаем наравне с выжившими: иначе убийства
+/// и поведение погибшего танка не попали бы ни в фитнес, ни в архив элит
+fn score_training_battle(
+    population: &mut Population,
+    survivors: &[(u32, &AIController)],
+    fallen: &[(u32, AIController)],
+    score_a: f32,
+) {
+    // Все участники команды получают в рейтинге результат своей команды
+    let mut teams: [Vec<&AIController>; 2] = [Vec::new(), Vec::new()];
+    for (team, ai) in survivors.iter().copied().chain(fallen.iter().map(|(team, ai)| (*team, ai))) {
+        population.calculate_fitness(Entity::PLACEHOLDER, ai);
+        teams[(team as usize).min(1)].push(ai);
+    }
+    population.record_battle_outcome([&teams[0], &teams[1]], score_a);
+}
+
+/// Число выживших и их суммарное здоровье по командам
+fn surviving_teams(tank_query: &Query<&Tank>) -> ([u32; 2], [f32; 2]) {
+    let mut alive = [0u32; 2];
+    let mut health = [0.0f32; 2];
+    for tank in tank_query.iter().filter(|t| t.health > 0.0) {
+        let team = (tank.team as usize).min(1);
+        alive[team] += 1;
+        health[team] += tank.health;
+    }
+    (alive, health)
+}
+
+/// Вместо эволюции после боя поколений: следующий бой или выход, если очередь CLI исчерпана.
+/// Если пропущены все оставшиеся бои очереди (нет чемпионов), выходят системы спавна
+pub fn finish_match(
+    mut battle_kind: ResMut<BattleKind>,
+    match_queue: Res<MatchQueue>,
+    mut next_state: ResMut<NextState<crate::GameState>>,
+    mut exit: EventWriter<bevy::app::AppExit>,
+) {
+    *battle_kind = BattleKind::Training;
+    if match_queue.finished() {
+        exit.send(bevy::app::AppExit);
+        return;
+    }
+    next_state.set(crate::GameState::Battle);
 }
 
 /// Создание танков из текущей популяции
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn spawn_tanks_from_population(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     population: Res<Population>,
-    tank_query: Query<Entity, With<Tank>>,
+    opponents: Res<FixedOpponents>,
+    mut match_queue: ResMut<MatchQueue>,
+    mut battle_kind: ResMut<BattleKind>,
+    tournament: Option<Res<Tournament>>,
+    benchmark: Option<Res<Benchmark>>,
+    sparring: Res<SparringBots>,
+    classes: Res<TankClasses>,
+    leftovers: Query<Entity, Or<(With<Tank>, With<Projectile>)>>,
     mut next_state: ResMut<NextState<crate::GameState>>,
+    mut exit: EventWriter<bevy::app::AppExit>,
 ) {
-    // Удаляем всех существующих танков
-    for entity in tank_query.iter() {
+    // Удаляем танков и снаряды прошлого боя: заложенная мина не должна дожить до следующего
+    for entity in leftovers.iter() {
         commands.entity(entity).despawn_recursive();
     }
+    let mut picker = classes.picker();
+
+    if let (BattleKind::Tournament { entrant_a, entrant_b }, Some(tournament)) = (*battle_kind, &tournament) {
+        spawn_tournament_match(&mut commands, Some(&mut meshes), Some(&mut materials), &mut picker, tournament, entrant_a, entrant_b);
+        next_state.set(crate::GameState::Battle);
+        return;
+    }
+    if let (BattleKind::Benchmark { strategy }, Some(benchmark)) = (*battle_kind, &benchmark) {
+        bots::spawn_benchmark_match(&mut commands, Some(&mut meshes), Some(&mut materials), &mut picker, benchmark, strategy);
+        next_state.set(crate::GameState::Battle);
+        return;
+    }
+    if let BattleKind::External { seed, opponent, opponents } = *battle_kind {
+        server::spawn_external_match(&mut commands, Some(&mut meshes), Some(&mut materials), &mut picker, &population, seed, opponent, opponents);
+        return;
+    }
+
+    *battle_kind = start_queued_match(&mut commands, Some(&mut meshes), Some(&mut materials), &mut picker, &population, &mut match_queue);
+    if *battle_kind == BattleKind::Training && match_queue.finished() {
+        exit.send(bevy::app::AppExit);
+        return;
+    }
+    if *battle_kind != BattleKind::Training {
+        next_state.set(crate::GameState::Battle);
+        return;
+    }
     
-    info!("Спавн танков для поколения {}", population.generation);
+    info!(
+        "Спавн танков для поколения {}, бой {} из {}",
+        population.generation,
+        population.evaluation_battle + 1,
+        population.battles_per_generation()
+    );
     
-    let mut rng = rand::thread_rng();
+    let mut rng = StdRng::seed_from_u64(population.battle_seed());
     
-    // Создаем 10 танков из лучших геномов популяции
-    for i in 0..10 {
+    // Создаем танки из геномов, назначенных в этот бой политикой оценки
+    for (index, team) in population.lineup() {
         let x = rng.gen_range(-40.0..40.0);
         let z = rng.gen_range(-40.0..40.0);
-        
-        // Используем геном из популяции (предполагаем, что они отсортированы)
-        let ai = population.genomes[i % population.genomes.len()].clone();
+        let ai = population.genomes[index].battle_copy();
         
         spawn_tank(
             &mut commands,
             &mut meshes,
             &mut materials,
             Vec3::new(x, 0.5, z), // Центр танка на высоте 0.5 для контакта с землей
-            (i % 2) as u32, // Команда 0 или 1
+            team,
+            picker.next(team),
             Some(ai),
         );
     }
+    curriculum::spawn_targets(&mut commands, Some(&mut meshes), Some(&mut materials), &mut picker, population.curriculum.stage, &mut rng);
+    // На ступенях учебной программы против мишеней спарринг-боты не выходят
+    if !population.curriculum.stage.uses_targets() {
+        bots::spawn_bots(&mut commands, Some(&mut meshes), Some(&mut materials), &mut picker, &sparring.strategies, 1, 0.0, &mut rng);
+    }
+
+    spawn_fixed_opponents(&mut commands, Some(&mut meshes), Some(&mut materials), &mut picker, &opponents, &mut rng);
     
     // Начинаем новый бой
     next_state.set(crate::GameState::Battle);
 }
 
 /// Создание танков без рендера (headless)
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn spawn_tanks_headless(
     mut commands: Commands,
     population: Res<Population>,
-    tank_query: Query<Entity, With<Tank>>,
+    opponents: Res<FixedOpponents>,
+    mut match_queue: ResMut<MatchQueue>,
+    mut battle_kind: ResMut<BattleKind>,
+    tournament: Option<Res<Tournament>>,
+    benchmark: Option<Res<Benchmark>>,
+    sparring: Res<SparringBots>,
+    classes: Res<TankClasses>,
+    leftovers: Query<Entity, Or<(With<Tank>, With<Projectile>)>>,
     mut next_state: ResMut<NextState<crate::GameState>>,
+    mut exit: EventWriter<bevy::app::AppExit>,
     headless: Res<Headless>,
 ) {
     if !headless.0 {
         return;
     }
 
-    for entity in tank_query.iter() {
+    for entity in leftovers.iter() {
         commands.entity(entity).despawn_recursive();
     }
+    let mut picker = classes.picker();
+
+    if let (BattleKind::Tournament { entrant_a, entrant_b }, Some(tournament)) = (*battle_kind, &tournament) {
+        spawn_tournament_match(&mut commands, None, None, &mut picker, tournament, entrant_a, entrant_b);
+        next_state.set(crate::GameState::Battle);
+        return;
+    }
+    if let (BattleKind::Benchmark { strategy }, Some(benchmark)) = (*battle_kind, &benchmark) {
+        bots::spawn_benchmark_match(&mut commands, None, None, &mut picker, benchmark, strategy);
+        next_state.set(crate::GameState::Battle);
+        return;
+    }
+    if let BattleKind::External { seed, opponent, opponents } = *battle_kind {
+        server::spawn_external_match(&mut commands, None, None, &mut picker, &population, seed, opponent, opponents);
+        return;
+    }
+
+    *battle_kind = start_queued_match(&mut commands, None, None, &mut picker, &population, &mut match_queue);
+    if *battle_kind == BattleKind::Training && match_queue.finished() {
+        exit.send(bevy::app::AppExit);
+        return;
+    }
+    if *battle_kind != BattleKind::Training {
+        next_state.set(crate::GameState::Battle);
+        return;
+    }
 
-    info!("[Headless] Спавн танков для поколения {}", population.generation);
+    info!(
+        "[Headless] Спавн танков для поколения {}, бой {} из {}",
+        population.generation,
+        population.evaluation_battle + 1,
+        population.battles_per_generation()
+    );
 
-    let mut rng = rand::thread_rng();
-    for i in 0..10 {
+    let mut rng = StdRng::seed_from_u64(population.battle_seed());
+    for (index, team) in population.lineup() {
         let x = rng.gen_range(-40.0..40.0);
         let z = rng.gen_range(-40.0..40.0);
-        let ai = population.genomes[i % population.genomes.len()].clone();
+        let ai = population.genomes[index].battle_copy();
 
-        let color = if i % 2 == 0 {
-            Color::rgb(0.2, 0.5, 0.8)
-        } else {
-            Color::rgb(0.8, 0.2, 0.2)
-        };
-
-        commands.spawn((
-            Transform::from_translation(Vec3::new(x, 0.5, z)),
-            GlobalTransform::default(),
-            Tank { team: (i % 2) as u32, ..default() },
-            TeamColor(color),
-            FireCooldown::default(),
-            ai,
-        ));
+        spawn_tank_headless(&mut commands, Vec3::new(x, 0.5, z), team, picker.next(team), Some(ai));
     }
+    curriculum::spawn_targets(&mut commands, None, None, &mut picker, population.curriculum.stage, &mut rng);
+    if !population.curriculum.stage.uses_targets() {
+        bots::spawn_bots(&mut commands, None, None, &mut picker, &sparring.strategies, 1, 0.0, &mut rng);
+    }
+
+    spawn_fixed_opponents(&mut commands, None, None, &mut picker, &opponents, &mut rng);
 
     next_state.set(crate::GameState::Battle);
 }
 
+/// Цвет команды
+pub fn team_color(team: u32) -> Color {
+    if team == 0 {
+        Color::rgb(0.2, 0.5, 0.8) // Синий
+    } else {
+        Color::rgb(0.8, 0.2, 0.2) // Красный
+    }
+}
+
+/// Создание танка без мешей и материалов (headless)
+pub fn spawn_tank_headless(
+    commands: &mut Commands,
+    position: Vec3,
+    team: u32,
+    class: &ClassDef,
+    ai_controller: Option<AIController>,
+) -> Entity {
+    let tank_entity = commands.spawn((
+        Transform::from_translation(position),
+        GlobalTransform::default(),
+        Tank::of_class(class, team),
+        TeamColor(team_color(team)),
+        FireCooldown::from_seconds(class.cooldown),
+        TankAction::default(),
+    )).id();
+    if let Some(ai) = ai_controller {
+        commands.entity(tank_entity).insert((NeuralNetwork::from_genome(&ai.genome), NetworkActivity::default(), ai));
+    }
+    tank_entity
+}
+
+/// Создание танка в любом режиме: с рендером, если переданы ассеты, иначе headless
+pub fn spawn_tank_any(
+    commands: &mut Commands,
+    meshes: Option<&mut ResMut<Assets<Mesh>>>,
+    materials: Option<&mut ResMut<Assets<StandardMaterial>>>,
+    position: Vec3,
+    team: u32,
+    class: &ClassDef,
+    ai_controller: Option<AIController>,
+) -> Entity {
+    match (meshes, materials) {
+        (Some(meshes), Some(materials)) => spawn_tank(commands, meshes, materials, position, team, class, ai_controller),
+        _ => spawn_tank_headless(commands, position, team, class, ai_controller),
+    }
+}
+
+/// Выпускает фиксированных противников-чемпионов за команду 1 вне эволюции.
+/// Позиции берутся из генератора боя, так что при том же зерне они повторяются
+fn spawn_fixed_opponents(
+    commands: &mut Commands,
+    mut meshes: Option<&mut ResMut<Assets<Mesh>>>,
+    mut materials: Option<&mut ResMut<Assets<StandardMaterial>>>,
+    picker: &mut ClassPicker,
+    opponents: &FixedOpponents,
+    rng: &mut impl Rng,
+) {
+    for champion in &opponents.champions {
+        let position = Vec3::new(rng.gen_range(-40.0..40.0), 0.5, rng.gen_range(-40.0..40.0));
+        let mut ai = champion.to_controller();
+        ai.fitness = 0.0;
+        let entity = spawn_tank_any(
+            commands,
+            meshes.as_deref_mut(),
+            materials.as_deref_mut(),
+            position,
+            1,
+            picker.next(1),
+            Some(ai),
+        );
+        commands.entity(entity).insert(ExhibitionTank);
+    }
+}
+
 /// Создание одного танка
 pub fn spawn_tank(
     commands: &mut Commands,
@@ -169,18 +569,16 @@ pub fn spawn_tank(
     materials: &mut ResMut<Assets<StandardMaterial>>,
     position: Vec3,
     team: u32,
+    class: &ClassDef,
     ai_controller: Option<AIController>,
 ) -> Entity {
-    let color = if team == 0 {
-        Color::rgb(0.2, 0.5, 0.8) // Синий
-    } else {
-        Color::rgb(0.8, 0.2, 0.2) // Красный
-    };
+    let color = team_color(team);
+    let [width, height, length] = class.hull;
     
-    // Корпус танка
+    // Корпус танка по размерам класса
     let tank_entity = commands.spawn((
         PbrBundle {
-            mesh: meshes.add(Mesh::from(shape::Box::new(2.0, 1.0, 3.0))),
+            mesh: meshes.add(Mesh::from(shape::Box::new(width, height, length))),
             material: materials.add(StandardMaterial {
                 base_color: color,
                 ..default()
@@ -188,19 +586,16 @@ pub fn spawn_tank(
             transform: Transform::from_translation(position),
             ..default()
         },
-        Tank {
-            team,
-            ..default()
-        },
+        Tank::of_class(class, team),
         TeamColor(color),
     )).id();
     
-    // Добавляем AI контроллер или метку игрока
+    // Добавляем AI контроллер и собранную по его геному сеть
     if let Some(ai) = ai_controller {
-        commands.entity(tank_entity).insert(ai);
+        commands.entity(tank_entity).insert((NeuralNetwork::from_genome(&ai.genome), NetworkActivity::default(), ai));
     }
-    // Перезарядка
-    commands.entity(tank_entity).insert(FireCooldown::default());
+    // Перезарядка и команды контроллера
+    commands.entity(tank_entity).insert((FireCooldown::from_seconds(class.cooldown), TankAction::default()));
     
     // Башня танка
     let turret_entity = commands.spawn((
@@ -210,7 +605,8 @@ pub fn spawn_tank(
                 base_color: color,
                 ..default()
             }),
-            transform: Transform::from_translation(Vec3::Y * 0.75),
+            // Башня стоит на крыше корпуса
+            transform: Transform::from_translation(Vec3::Y * (height / 2.0 + 0.25)),
             ..default()
         },
         TankTurret {
@@ -241,6 +637,7 @@ pub fn spawn_tank(
 /// Система проверки окончания боя
 pub fn check_battle_end(
     mut battle_state: ResMut<BattleState>,
+    battle_kind: Res<BattleKind>,
     tank_query: Query<&Tank>,
     mut next_state: ResMut<NextState<crate::GameState>>,
 ) {
@@ -254,6 +651,17 @@ pub fn check_battle_end(
     for tank in tank_query.iter() {
         teams_alive.insert(tank.team);
     }
+
+    // Раз в секунду симуляции запоминаем силу команд для графика
+    let ticks_per_second = (1.0 / BASE_SIM_DT) as u32;
+    if battle_state.tick_count % ticks_per_second == 1 {
+        let mut health = [0.0f32; 2];
+        for tank in tank_query.iter().filter(|t| t.health > 0.0) {
+            health[(tank.team as usize).min(1)] += tank.health;
+        }
+        let time = battle_state.real_time;
+        battle_state.strength.push((time, health));
+    }
     
     // Бой закончен если:
     // 1. Осталась только одна команда
@@ -262,20 +670,182 @@ pub fn check_battle_end(
     if teams_alive.len() <= 1 || 
        battle_state.tick_count >= battle_state.max_ticks ||
        total_tanks == 0 {
-        next_state.set(crate::GameState::Evolution);
+        next_state.set(battle_kind.after_battle());
     }
 }
 
-/// Система для устройства боя между разными поколениями
-#[allow(dead_code)]
+/// Берёт из очереди первый бой поколений, чемпионы которых есть в архиве, и расставляет танки.
+/// Возвращает вид начавшегося боя (Training, если очередь пуста).
+fn start_queued_match(
+    commands: &mut Commands,
+    mut meshes: Option<&mut ResMut<Assets<Mesh>>>,
+    mut materials: Option<&mut ResMut<Assets<StandardMaterial>>>,
+    picker: &mut ClassPicker,
+    population: &Population,
+    match_queue: &mut MatchQueue,
+) -> BattleKind {
+    while let Some((generation_a, generation_b)) = match_queue.pending.pop_front() {
+        if setup_generation_battle(
+            commands,
+            meshes.as_deref_mut(),
+            materials.as_deref_mut(),
+            picker,
+            population,
+            generation_a,
+            generation_b,
+        ) {
+            return BattleKind::GenerationMatch { generation_a, generation_b };
+        }
+    }
+    BattleKind::Training
+}
+
+/// Система для устройства боя между разными поколениями: чемпион поколения A
+/// играет за синих, чемпион поколения B — за красных, по MATCH_TEAM_SIZE танков с каждой стороны.
+/// Возвращает false, если чемпиона одного из поколений нет в архиве.
 pub fn setup_generation_battle(
-    _commands: &mut Commands,
-    _meshes: &mut ResMut<Assets<Mesh>>,
-    _materials: &mut ResMut<Assets<StandardMaterial>>,
-    _population: &Population,
-    _generation_a: u32,
-    _generation_b: u32,
+    commands: &mut Commands,
+    meshes: Option<&mut ResMut<Assets<Mesh>>>,
+    materials: Option<&mut ResMut<Assets<StandardMaterial>>>,
+    picker: &mut ClassPicker,
+    population: &Population,
+    generation_a: u32,
+    generation_b: u32,
+) -> bool {
+    let (Some(champion_a), Some(champion_b)) = (
+        population.champion_of(generation_a),
+        population.champion_of(generation_b),
+    ) else {
+        let available = match (population.champions.first(), population.champions.last()) {
+            (Some(first), Some(last)) => format!("есть поколения {}..={}", first.generation, last.generation),
+            _ => "архив чемпионов пуст".to_string(),
+        };
+        println!("Бой поколений {} vs {} пропущен: {}", generation_a, generation_b, available);
+        return false;
+    };
+
+    info!("Бой поколений: {} (синие) vs {} (красные)", generation_a, generation_b);
+    // Один и тот же бой поколений всегда расставляется одинаково
+    let mut rng = StdRng::seed_from_u64(((generation_a as u64) << 32) | generation_b as u64);
+    spawn_lineup(commands, meshes, materials, picker, [champion_a, champion_b], MATCH_TEAM_SIZE, &mut rng);
+    true
+}
+
+/// Расставляет матч турнира: участник A за синих, участник B за красных
+fn spawn_tournament_match(
+    commands: &mut Commands,
+    meshes: Option<&mut ResMut<Assets<Mesh>>>,
+    materials: Option<&mut ResMut<Assets<StandardMaterial>>>,
+    picker: &mut ClassPicker,
+    tournament: &Tournament,
+    entrant_a: usize,
+    entrant_b: usize,
 ) {
-    // Загружаем геномы разных поколений и устраиваем бой
-    // Это можно вызвать вручную через UI
+    let lineup = [
+        &tournament.entrants[entrant_a].controller,
+        &tournament.entrants[entrant_b].controller,
+    ];
+    // Расстановка зависит только от номера матча: турнир с теми же участниками повторяется
+    let mut rng = StdRng::seed_from_u64(tournament.matches.len() as u64);
+    spawn_lineup(commands, meshes, materials, picker, lineup, tournament.team_size, &mut rng);
+}
+
+/// Две команды копий заданных геномов, каждая на своей половине карты, вне эволюции
+fn spawn_lineup(
+    commands: &mut Commands,
+    mut meshes: Option<&mut ResMut<Assets<Mesh>>>,
+    mut materials: Option<&mut ResMut<Assets<StandardMaterial>>>,
+    picker: &mut ClassPicker,
+    lineup: [&AIController; 2],
+    team_size: usize,
+    rng: &mut impl Rng,
+) {
+    for (team, controller) in lineup.into_iter().enumerate() {
+        let side = if team == 0 { -1.0 } else { 1.0 };
+        for _ in 0..team_size {
+            let x = side * rng.gen_range(10.0..40.0);
+            let z = rng.gen_range(-40.0..40.0);
+            let entity = spawn_tank_any(
+                commands,
+                meshes.as_deref_mut(),
+                materials.as_deref_mut(),
+                Vec3::new(x, 0.5, z),
+                team as u32,
+                picker.next(team as u32),
+                Some(AIController::with_genome(controller.genome.clone())),
+            );
+            commands.entity(entity).insert(ExhibitionTank);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[test]
+    fn fallen_tanks_are_scored_and_battle_state_resets() {
+        let mut population = Population::new_fresh(4);
+        let survivor = AIController { survival_time: 120.0, ..population.genomes[0].battle_copy() };
+        let fallen = AIController { kills: 2, survival_time: 10.0, ..population.genomes[1].battle_copy() };
+
+        // Синие выжили, но проиграли бой: павший красный получает и фитнес, и рейтинг победителя
+        score_training_battle(&mut population, &[(0, &survivor)], &[(1, fallen.clone())], 0.0);
+        assert_eq!(population.genomes[1].kills, 2);
+        assert_eq!(population.genomes[1].fitness, 220.0);
+        assert!(population.genomes[1].rating.elo > population.genomes[0].rating.elo);
+
+        // start_battle одинаков в окне и в headless: состояние прошлого боя сбрасывается
+        let mut world = World::new();
+        world.insert_resource(BattleState {
+            tick_count: 500,
+            fallen: vec![(1, fallen)],
+            deaths: [1, 0],
+            ..default()
+        });
+        world.run_system_once(start_battle);
+        let state = world.resource::<BattleState>();
+        assert_eq!((state.tick_count, state.deaths), (0, [0, 0]));
+        assert!(state.fallen.is_empty() && state.max_ticks > 0);
+    }
+
+    fn headless_world(queue: MatchQueue) -> World {
+        let mut world = World::new();
+        world.insert_resource(Population::new_fresh(4));
+        world.insert_resource(FixedOpponents::default());
+        world.insert_resource(queue);
+        world.insert_resource(BattleKind::Training);
+        world.insert_resource(SparringBots::default());
+        world.insert_resource(TankClasses::default());
+        world.insert_resource(NextState::<crate::GameState>::default());
+        world.insert_resource(Headless(true));
+        world.init_resource::<Events<bevy::app::AppExit>>();
+        world
+    }
+
+    #[test]
+    fn skipped_generation_battles_still_exit() {
+        let mut world = headless_world(MatchQueue {
+            pending: [(3, 7)].into_iter().collect(),
+            exit_when_done: true,
+        });
+
+        // Чемпионов поколений 3 и 7 нет: бой пропущен, тренировочный бой не начинается
+        world.run_system_once(spawn_tanks_headless);
+        assert_eq!(world.resource::<Events<bevy::app::AppExit>>().len(), 1);
+        assert!(world.query::<&Tank>().iter(&world).next().is_none());
+    }
+
+    #[test]
+    fn mines_of_the_last_battle_are_cleared() {
+        let mut world = headless_world(MatchQueue::default());
+        let (_, mine) = crate::weapons::WeaponDef::builtin(crate::weapons::WeaponKind::Mine)
+            .launch(Entity::PLACEHOLDER, 0, &Transform::default(), 20.0);
+        world.spawn((Transform::default(), mine));
+
+        world.run_system_once(spawn_tanks_headless);
+        assert!(world.query::<&Projectile>().iter(&world).next().is_none());
+        assert!(world.query::<&Tank>().iter(&world).next().is_some());
+    }
 }
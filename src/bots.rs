@@ -0,0 +1,431 @@
+use bevy::prelude::*;
+use crate::battle::{spawn_tank_any, BattleKind};
+use crate::checkpoint::write_atomic;
+use crate::classes::ClassPicker;
+use crate::components::*;
+use crate::controller::{TankAction, TankSnapshot};
+use crate::GameState;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+use std::f32::consts::PI;
+use std::path::PathBuf;
+
+/// Граница, вдоль которой ездит «прилипала» к стенам
+const WALL_PATH: f32 = 42.0;
+/// Дистанция, которую держит «кайтер»
+const KITE_RANGE: f32 = 20.0;
+/// Дальше этого кемпер не стреляет
+const CAMPER_RANGE: f32 = 30.0;
+
+/// Рукописная стратегия бота
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum BotStrategy {
+    /// Наводится на ближайшего врага и едет прямо на него
+    AimAndCharge,
+    /// Держит дистанцию и стреляет издалека
+    KiteAtRange,
+    /// Стоит на месте и стреляет по всем в радиусе
+    Camper,
+    /// Объезжает карту вдоль стен, стреляет по тем, кто оказался на линии огня
+    WallHugger,
+}
+
+impl BotStrategy {
+    pub const ALL: [BotStrategy; 4] = [
+        BotStrategy::AimAndCharge,
+        BotStrategy::KiteAtRange,
+        BotStrategy::Camper,
+        BotStrategy::WallHugger,
+    ];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "charge" => Some(BotStrategy::AimAndCharge),
+            "kite" => Some(BotStrategy::KiteAtRange),
+            "camper" => Some(BotStrategy::Camper),
+            "wall" => Some(BotStrategy::WallHugger),
+            _ => None,
+        }
+    }
+
+    /// Разбирает список через запятую; "all" — все стратегии
+    pub fn parse_list(list: &str) -> Option<Vec<Self>> {
+        if list == "all" {
+            return Some(Self::ALL.to_vec());
+        }
+        list.split(',').map(|name| Self::parse(name.trim())).collect()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BotStrategy::AimAndCharge => "таран",
+            BotStrategy::KiteAtRange => "кайтер",
+            BotStrategy::Camper => "кемпер",
+            BotStrategy::WallHugger => "вдоль стен",
+        }
+    }
+}
+
+/// Бот под управлением рукописной стратегии вместо нейросети
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ScriptedBot {
+    pub strategy: BotStrategy,
+}
+
+/// Боты-спарринг-партнёры, выходящие за красных в каждый тренировочный бой
+#[derive(Resource, Default)]
+pub struct SparringBots {
+    pub strategies: Vec<BotStrategy>,
+}
+
+/// Выпускает ботов указанных стратегий за команду; side: -1 — левая половина карты, 1 — правая, 0 — вся карта
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_bots(
+    commands: &mut Commands,
+    mut meshes: Option<&mut ResMut<Assets<Mesh>>>,
+    mut materials: Option<&mut ResMut<Assets<StandardMaterial>>>,
+    picker: &mut ClassPicker,
+    strategies: &[BotStrategy],
+    team: u32,
+    side: f32,
+    rng: &mut impl Rng,
+) {
+    for &strategy in strategies {
+        let x = if side == 0.0 { rng.gen_range(-40.0..40.0) } else { side * rng.gen_range(10.0..40.0) };
+        let position = Vec3::new(x, 0.5, rng.gen_range(-40.0..40.0));
+        let entity = spawn_tank_any(
+            commands,
+            meshes.as_deref_mut(),
+            materials.as_deref_mut(),
+            position,
+            team,
+            picker.next(team),
+            None,
+        );
+        commands.entity(entity).insert(ScriptedBot { strategy });
+    }
+}
+
+/// Угол поворота вокруг Y, при котором локальная ось +Z (орудие) смотрит вдоль dir
+fn gun_yaw(dir: Vec3) -> f32 {
+    dir.x.atan2(dir.z)
+}
+
+/// Угол, при котором вперёд (локальная -Z, направление хода) смотрит вдоль dir
+fn drive_yaw(dir: Vec3) -> f32 {
+    (-dir.x).atan2(-dir.z)
+}
+
+/// Разница углов, приведённая к [-PI, PI]
+fn angle_error(desired: f32, current: f32) -> f32 {
+    (desired - current + PI).rem_euclid(2.0 * PI) - PI
+}
+
+/// Бот не крутит башню: целится корпусом
+fn act(throttle: f32, turn: f32, fire: bool) -> TankAction {
+    TankAction { throttle, turn, fire, ..default() }
+}
+
+/// Решение бота на такт
+fn decide(strategy: BotStrategy, position: Vec3, yaw: f32, enemy: Option<Vec3>) -> TankAction {
+    let aim = enemy.map(|enemy_pos| {
+        let to_enemy = enemy_pos - position;
+        (angle_error(gun_yaw(to_enemy), yaw), to_enemy.length())
+    });
+
+    match strategy {
+        BotStrategy::AimAndCharge => match aim {
+            // Орудие сзади по ходу, поэтому «в атаку» — задним ходом
+            Some((error, distance)) => {
+                let throttle = if error.abs() < 0.5 && distance > 6.0 { -1.0 } else { 0.0 };
+                act(throttle, error.signum(), error.abs() < 0.15)
+            }
+            None => TankAction::default(),
+        },
+        BotStrategy::KiteAtRange => match aim {
+            Some((error, distance)) => {
+                let throttle = if distance < KITE_RANGE - 3.0 {
+                    1.0
+                } else if distance > KITE_RANGE + 5.0 {
+                    -1.0
+                } else {
+                    0.0
+                };
+                act(throttle, error.signum(), error.abs() < 0.1)
+            }
+            None => TankAction::default(),
+        },
+        BotStrategy::Camper => match aim {
+            Some((error, distance)) => act(0.0, error.signum(), error.abs() < 0.1 && distance < CAMPER_RANGE),
+            None => TankAction::default(),
+        },
+        BotStrategy::WallHugger => {
+            // К ближайшей стене, а у стены — вдоль неё против часовой стрелки
+            let along_wall = if position.x.abs().max(position.z.abs()) < WALL_PATH - 4.0 {
+                if position.x.abs() > position.z.abs() {
+                    Vec3::new(position.x.signum(), 0.0, 0.0)
+                } else {
+                    Vec3::new(0.0, 0.0, position.z.signum())
+                }
+            } else if position.x >= WALL_PATH - 4.0 && position.z < WALL_PATH - 4.0 {
+                Vec3::Z
+            } else if position.z >= WALL_PATH - 4.0 && position.x > -(WALL_PATH - 4.0) {
+                Vec3::NEG_X
+            } else if position.x <= -(WALL_PATH - 4.0) && position.z > -(WALL_PATH - 4.0) {
+                Vec3::NEG_Z
+            } else {
+                Vec3::X
+            };
+            let error = angle_error(drive_yaw(along_wall), yaw);
+            let fire = aim.map(|(aim_error, distance)| aim_error.abs() < 0.2 && distance < CAMPER_RANGE);
+            act(1.0, error.clamp(-1.0, 1.0), fire.unwrap_or(false))
+        }
+    }
+}
+
+/// Управление ботами: действие по стратегии вместо нейросети
+pub fn scripted_bot_system(
+    mut bots: Query<(&Transform, &Tank, &ScriptedBot, &mut TankAction)>,
+    all_tanks: Query<(&Transform, &Tank)>,
+) {
+    let snapshots = TankSnapshot::collect(all_tanks.iter());
+    for (transform, tank, bot, mut action) in bots.iter_mut() {
+        let enemy = TankSnapshot::nearest_enemy(&snapshots, transform.translation, tank.team).map(|e| e.position);
+        let yaw = transform.rotation.to_euler(EulerRot::YXZ).0;
+        *action = decide(bot.strategy, transform.translation, yaw, enemy);
+    }
+}
+
+/// Итог агента против одной стратегии
+#[derive(Clone, Debug, Serialize)]
+pub struct BenchmarkRecord {
+    pub strategy: BotStrategy,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl BenchmarkRecord {
+    pub fn played(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Доля побед, ничья — половина победы
+    pub fn win_rate(&self) -> f32 {
+        if self.played() == 0 {
+            return 0.0;
+        }
+        (self.wins as f32 + 0.5 * self.draws as f32) / self.played() as f32
+    }
+}
+
+/// Набор матчей агента против каждой стратегии ботов
+#[derive(Resource)]
+pub struct Benchmark {
+    pub agent_name: String,
+    pub agent: AIController,
+    pub team_size: usize,
+    pub matches_per_strategy: u32,
+    pub records: Vec<BenchmarkRecord>,
+    pub output: PathBuf,
+    /// Зерно расстановки: один и тот же матч бенчмарка всегда стартует одинаково
+    pub seed: u64,
+    pub exit_when_done: bool,
+    pub finished: bool,
+}
+
+impl Benchmark {
+    pub fn new(agent_name: String, agent: AIController, team_size: usize, matches_per_strategy: u32, output: PathBuf) -> Self {
+        Self {
+            agent_name,
+            agent,
+            team_size: team_size.max(1),
+            matches_per_strategy: matches_per_strategy.max(1),
+            records: BotStrategy::ALL
+                .iter()
+                .map(|&strategy| BenchmarkRecord { strategy, wins: 0, draws: 0, losses: 0 })
+                .collect(),
+            output,
+            seed: 0,
+            exit_when_done: false,
+            finished: false,
+        }
+    }
+
+    /// Стратегия, против которой играть следующий матч
+    pub fn next_match(&self) -> Option<usize> {
+        self.records
+            .iter()
+            .position(|record| record.played() < self.matches_per_strategy)
+    }
+
+    /// Зерно очередного матча против стратегии: зависит только от номера стратегии и матча
+    pub fn match_seed(&self, strategy: usize) -> u64 {
+        self.seed ^ ((strategy as u64) << 32 | self.records[strategy].played() as u64)
+    }
+
+    pub fn record(&mut self, strategy: usize, score_a: f32) {
+        let record = &mut self.records[strategy];
+        match score_a {
+            s if s > 0.5 => record.wins += 1,
+            s if s < 0.5 => record.losses += 1,
+            _ => record.draws += 1,
+        }
+    }
+
+    /// Доля побед по всем стратегиям
+    pub fn overall_win_rate(&self) -> f32 {
+        let played: u32 = self.records.iter().map(|r| r.played()).sum();
+        if played == 0 {
+            return 0.0;
+        }
+        self.records.iter().map(|r| r.win_rate() * r.played() as f32).sum::<f32>() / played as f32
+    }
+
+    pub fn report_table(&self) -> String {
+        let mut table = format!(
+            "Бенчмарк «{}» ({}×{}):\n{:<12} {:>4} {:>4} {:>4} {:>7}\n",
+            self.agent_name, self.team_size, self.team_size, "стратегия", "поб", "нич", "пор", "победы"
+        );
+        for r in &self.records {
+            table += &format!(
+                "{:<12} {:>4} {:>4} {:>4} {:>6.0}%\n",
+                r.strategy.name(),
+                r.wins,
+                r.draws,
+                r.losses,
+                r.win_rate() * 100.0
+            );
+        }
+        table += &format!("Итого побед: {:.0}%", self.overall_win_rate() * 100.0);
+        table
+    }
+
+    pub fn write_report(&self) -> std::io::Result<PathBuf> {
+        #[derive(Serialize)]
+        struct RecordReport<'a> {
+            #[serde(flatten)]
+            record: &'a BenchmarkRecord,
+            win_rate: f32,
+        }
+        #[derive(Serialize)]
+        struct Report<'a> {
+            agent: &'a str,
+            team_size: usize,
+            matches_per_strategy: u32,
+            overall_win_rate: f32,
+            records: Vec<RecordReport<'a>>,
+        }
+        let report = Report {
+            agent: &self.agent_name,
+            team_size: self.team_size,
+            matches_per_strategy: self.matches_per_strategy,
+            overall_win_rate: self.overall_win_rate(),
+            records: self.records.iter().map(|record| RecordReport { record, win_rate: record.win_rate() }).collect(),
+        };
+        let path = self.output.with_extension("json");
+        write_atomic(&path, &serde_json::to_string_pretty(&report)?)?;
+        Ok(path)
+    }
+}
+
+/// Предикат для run_if: запущен бенчмарк
+pub fn benchmark_active(benchmark: Option<Res<Benchmark>>) -> bool {
+    benchmark.is_some()
+}
+
+/// Агент за синих слева, боты за красных справа
+pub fn spawn_benchmark_match(
+    commands: &mut Commands,
+    mut meshes: Option<&mut ResMut<Assets<Mesh>>>,
+    mut materials: Option<&mut ResMut<Assets<StandardMaterial>>>,
+    picker: &mut ClassPicker,
+    benchmark: &Benchmark,
+    strategy: usize,
+) {
+    let mut rng = StdRng::seed_from_u64(benchmark.match_seed(strategy));
+    for _ in 0..benchmark.team_size {
+        let position = Vec3::new(-rng.gen_range(10.0..40.0), 0.5, rng.gen_range(-40.0..40.0));
+        let entity = spawn_tank_any(
+            commands,
+            meshes.as_deref_mut(),
+            materials.as_deref_mut(),
+            position,
+            0,
+            picker.next(0),
+            Some(benchmark.agent.battle_copy()),
+        );
+        commands.entity(entity).insert(ExhibitionTank);
+    }
+    let strategies = vec![benchmark.records[strategy].strategy; benchmark.team_size];
+    spawn_bots(commands, meshes, materials, picker, &strategies, 1, 1.0, &mut rng);
+}
+
+/// Между матчами бенчмарка: следующий матч или итоговый отчёт
+pub fn run_benchmark(
+    mut benchmark: ResMut<Benchmark>,
+    mut battle_kind: ResMut<BattleKind>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut exit: EventWriter<bevy::app::AppExit>,
+) {
+    if let Some(strategy) = benchmark.next_match() {
+        *battle_kind = BattleKind::Benchmark { strategy };
+        next_state.set(GameState::Battle);
+        return;
+    }
+
+    *battle_kind = BattleKind::Training;
+    if !benchmark.finished {
+        benchmark.finished = true;
+        println!("{}", benchmark.report_table());
+        match benchmark.write_report() {
+            Ok(path) => println!("Отчёт бенчмарка сохранён в {}", path.display()),
+            Err(e) => error!("Не удалось сохранить отчёт бенчмарка: {}", e),
+        }
+    }
+    if benchmark.exit_when_done {
+        exit.send(bevy::app::AppExit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn charger_turns_gun_towards_enemy_and_closes_in() {
+        // Враг прямо по оси +Z — туда смотрит орудие при нулевом повороте
+        let action = decide(BotStrategy::AimAndCharge, Vec3::ZERO, 0.0, Some(Vec3::new(0.0, 0.0, 20.0)));
+        assert_eq!(action.throttle, -1.0);
+        assert!(action.fire);
+        assert!(action.turn.abs() <= 1.0);
+
+        // Враг сбоку: сначала разворот, без выстрела
+        let action = decide(BotStrategy::Camper, Vec3::ZERO, 0.0, Some(Vec3::new(20.0, 0.0, 0.0)));
+        assert!(action.turn > 0.0);
+        assert!(!action.fire);
+    }
+
+    #[test]
+    fn win_rates_count_draws_as_half() {
+        let mut benchmark = Benchmark::new("test".into(), AIController::new_random(), 1, 2, "bench".into());
+        for strategy in 0..BotStrategy::ALL.len() {
+            benchmark.record(strategy, 1.0);
+            benchmark.record(strategy, 0.5);
+        }
+        assert_eq!(benchmark.next_match(), None);
+        assert!((benchmark.overall_win_rate() - 0.75).abs() < 1e-6);
+        assert_eq!(BotStrategy::parse_list("kite,camper"), Some(vec![BotStrategy::KiteAtRange, BotStrategy::Camper]));
+    }
+
+    #[test]
+    fn benchmark_matches_have_fixed_seeds() {
+        let mut benchmark = Benchmark::new("test".into(), AIController::new_random(), 1, 2, "bench".into());
+        let first = benchmark.match_seed(0);
+        assert_eq!(first, benchmark.match_seed(0));
+        assert_ne!(first, benchmark.match_seed(1));
+        benchmark.record(0, 1.0);
+        assert_ne!(first, benchmark.match_seed(0));
+    }
+}
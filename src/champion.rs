@@ -0,0 +1,134 @@
+use bevy::prelude::*;
+use crate::ai::NetworkSpec;
+use crate::checkpoint::{write_atomic, SaveError};
+use crate::components::*;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Версия формата файла чемпиона
+pub const CHAMPION_FORMAT_VERSION: u32 = 1;
+
+/// Сведения о происхождении чемпиона
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChampionMetadata {
+    pub name: String,
+    /// Откуда экспортирован: сохранение, бой, архив элит
+    pub source: String,
+    /// Время экспорта, секунды UNIX
+    pub exported_at: u64,
+    /// Поколение популяции на момент экспорта
+    pub exported_generation: u32,
+}
+
+/// Самостоятельный файл одного агента: геном вместе с архитектурой сети и историей
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ChampionFile {
+    pub format_version: u32,
+    pub network: NetworkSpec,
+    pub genome: Vec<f32>,
+    pub fitness: f32,
+    pub kills: u32,
+    pub survival_time: f32,
+    pub fitness_history: Vec<f32>,
+    pub origin_generation: u32,
+    pub metadata: ChampionMetadata,
+}
+
+impl ChampionFile {
+    pub fn from_controller(ai: &AIController, name: &str, source: &str, generation: u32) -> Self {
+        let exported_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self {
+            format_version: CHAMPION_FORMAT_VERSION,
+            network: NetworkSpec::CURRENT,
+            genome: ai.genome.clone(),
+            fitness: ai.fitness,
+            kills: ai.kills,
+            survival_time: ai.survival_time,
+            fitness_history: ai.fitness_history.clone(),
+            origin_generation: ai.origin_generation,
+            metadata: ChampionMetadata {
+                name: name.to_string(),
+                source: source.to_string(),
+                exported_at,
+                exported_generation: generation,
+            },
+        }
+    }
+
+    /// Контроллер для популяции или боя; записанный фитнес сохраняется,
+    /// чтобы импортированный чемпион пережил ближайший отбор
+    pub fn to_controller(&self) -> AIController {
+        let mut ai = AIController::with_genome(self.genome.clone());
+        ai.fitness = self.fitness;
+        ai.fitness_history = self.fitness_history.clone();
+        ai.origin_generation = self.origin_generation;
+        ai
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SaveError> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| SaveError::Parse(path.to_path_buf(), e))?;
+        write_atomic(path, &json).map_err(|e| SaveError::Io(path.to_path_buf(), e))
+    }
+
+    /// Загружает чемпиона и проверяет, что его сеть совместима с текущей
+    /// (или переносится в неё из прошлой архитектуры)
+    pub fn load(path: &Path) -> Result<Self, SaveError> {
+        let json = std::fs::read_to_string(path).map_err(|e| SaveError::Io(path.to_path_buf(), e))?;
+        let mut champion: ChampionFile =
+            serde_json::from_str(&json).map_err(|e| SaveError::Parse(path.to_path_buf(), e))?;
+        if champion.format_version > CHAMPION_FORMAT_VERSION {
+            return Err(SaveError::UnsupportedVersion(path.to_path_buf(), champion.format_version));
+        }
+        // Чемпион прошлой архитектуры: сеть переносится с нулевыми весами новых входов
+        if NetworkSpec::LEGACY.contains(&champion.network) && champion.genome.len() == champion.network.genome_len() {
+            champion.genome = champion.network.widen_genome(&champion.genome, NetworkSpec::CURRENT);
+            champion.network = NetworkSpec::CURRENT;
+        }
+        if champion.network != NetworkSpec::CURRENT || champion.genome.len() != champion.network.genome_len() {
+            return Err(SaveError::IncompatibleNetwork(
+                path.to_path_buf(),
+                format!(
+                    "сеть {:?} с геномом из {} весов, ожидается {:?}",
+                    champion.network,
+                    champion.genome.len(),
+                    NetworkSpec::CURRENT
+                ),
+            ));
+        }
+        Ok(champion)
+    }
+}
+
+/// Чемпионы, которые выходят в каждый бой фиксированными противниками (команда 1)
+#[derive(Resource, Default)]
+pub struct FixedOpponents {
+    pub champions: Vec<ChampionFile>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn champion_round_trip() {
+        let mut ai = AIController::new_random();
+        ai.fitness = 321.0;
+        ai.fitness_history = vec![100.0, 250.0];
+        ai.origin_generation = 7;
+
+        let path = std::env::temp_dir().join(format!("smart_bot_champion_{}.json", std::process::id()));
+        ChampionFile::from_controller(&ai, "test", "unit", 12).save(&path).unwrap();
+        let loaded = ChampionFile::load(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        let restored = loaded.to_controller();
+        assert_eq!(restored.genome, ai.genome);
+        assert_eq!(restored.fitness_history, ai.fitness_history);
+        assert_eq!(restored.origin_generation, 7);
+        assert_eq!(loaded.metadata.exported_generation, 12);
+    }
+}
@@ -0,0 +1,329 @@
+use bevy::prelude::*;
+use crate::ai::NetworkSpec;
+use crate::genetics::Population;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Основной файл сохранения популяции
+pub const BEST_GENOMES_FILE: &str = "best_genomes.json";
+
+/// Текущая версия схемы сохранения.
+/// 1 — «голый» Population без поля version (до введения версий),
+/// 2 — обёртка { version, population },
+/// 3 — геномы под сеть с входами класса танка (см. NetworkSpec::LEGACY),
+/// 4 — геномы под сеть с входами магазина и нагрева ствола.
+pub const SAVE_VERSION: u32 = 4;
+
+/// Поля, в которых сохранения хранят векторы в раскладке генома: геномы особей
+/// и состояние OpenAI-ES (среднее, моменты Adam, шум пар)
+const GENOME_FIELDS: [&str; 5] = ["genome", "mean", "adam_m", "adam_v", "noise"];
+
+/// Ошибка чтения или записи сохранения
+#[derive(Debug)]
+pub enum SaveError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, serde_json::Error),
+    UnsupportedVersion(PathBuf, u32),
+    IncompatibleNetwork(PathBuf, String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(path, e) => write!(f, "{}: ошибка ввода-вывода: {}", path.display(), e),
+            SaveError::Parse(path, e) => write!(f, "{}: повреждённый JSON: {}", path.display(), e),
+            SaveError::UnsupportedVersion(path, v) => write!(
+                f,
+                "{}: версия сохранения {} новее поддерживаемой {}",
+                path.display(),
+                v,
+                SAVE_VERSION
+            ),
+            SaveError::IncompatibleNetwork(path, details) => {
+                write!(f, "{}: несовместимая нейросеть: {}", path.display(), details)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+/// Настройки чекпоинтов: каталог и сколько последних поколений хранить
+#[derive(Resource, Clone, Debug)]
+pub struct CheckpointConfig {
+    pub dir: PathBuf,
+    pub keep_last: usize,
+}
+
+impl Default for CheckpointConfig {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("checkpoints"),
+            keep_last: 10,
+        }
+    }
+}
+
+/// Чекпоинт на диске
+pub struct CheckpointInfo {
+    pub path: PathBuf,
+    pub generation: u32,
+}
+
+#[derive(Serialize)]
+struct SaveFileRef<'a> {
+    version: u32,
+    population: &'a Population,
+}
+
+#[derive(Deserialize)]
+struct SaveFile {
+    #[allow(dead_code)]
+    version: u32,
+    population: Population,
+}
+
+/// Атомарная запись: сначала во временный файл рядом, затем rename поверх целевого
+pub fn write_atomic(path: &Path, contents: &str) -> std::io::Result<()> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    {
+        let mut file = std::fs::File::create(&tmp_path)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+    }
+    std::fs::rename(&tmp_path, path)
+}
+
+/// Сохраняет популяцию в текущей версии схемы
+pub fn save_population(path: &Path, population: &Population) -> Result<(), SaveError> {
+    let save = SaveFileRef {
+        version: SAVE_VERSION,
+        population,
+    };
+    let json = serde_json::to_string_pretty(&save)
+        .map_err(|e| SaveError::Parse(path.to_path_buf(), e))?;
+    write_atomic(path, &json).map_err(|e| SaveError::Io(path.to_path_buf(), e))
+}
+
+/// Загружает популяцию любой известной версии, применяя миграции
+pub fn load_population(path: &Path) -> Result<Population, SaveError> {
+    let json = std::fs::read_to_string(path).map_err(|e| SaveError::Io(path.to_path_buf(), e))?;
+    let value: Value = serde_json::from_str(&json).map_err(|e| SaveError::Parse(path.to_path_buf(), e))?;
+    let value = migrate(path, value)?;
+    let save: SaveFile = serde_json::from_value(value).map_err(|e| SaveError::Parse(path.to_path_buf(), e))?;
+    Ok(save.population)
+}
+
+/// Версия сохранения: у старых файлов поля нет
+fn save_version(value: &Value) -> u32 {
+    value
+        .get("version")
+        .and_then(Value::as_u64)
+        .map(|v| v as u32)
+        .unwrap_or(1)
+}
+
+/// Последовательно поднимает сохранение до SAVE_VERSION
+fn migrate(path: &Path, mut value: Value) -> Result<Value, SaveError> {
+    loop {
+        let version = save_version(&value);
+        value = match version {
+            1 => serde_json::json!({ "version": 2, "population": value }),
+            2 | 3 => {
+                upgrade_genomes(&mut value);
+                value["version"] = (version + 1).into();
+                value
+            }
+            SAVE_VERSION => return Ok(value),
+            _ => return Err(SaveError::UnsupportedVersion(path.to_path_buf(), version)),
+        };
+    }
+}
+
+/// Переносит все векторы прошлых архитектур сети в документе (сохранение популяции,
+/// архив элит) в текущую раскладку генома, см. NetworkSpec::upgrade_genome
+pub fn upgrade_genomes(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (key, child) in fields.iter_mut() {
+                let upgraded = GENOME_FIELDS
+                    .contains(&key.as_str())
+                    .then(|| as_genome(child))
+                    .flatten()
+                    .and_then(|genome| NetworkSpec::upgrade_genome(&genome));
+                match upgraded {
+                    Some(genome) => *child = genome.into(),
+                    None => upgrade_genomes(child),
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(upgrade_genomes),
+        _ => {}
+    }
+}
+
+/// Массив чисел как геном
+fn as_genome(value: &Value) -> Option<Vec<f32>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|weight| weight.as_f64().map(|w| w as f32))
+        .collect()
+}
+
+/// Путь к чекпоинту поколения
+fn checkpoint_path(dir: &Path, generation: u32) -> PathBuf {
+    dir.join(format!("gen_{:06}.json", generation))
+}
+
+/// Сохраняет чекпоинт поколения и удаляет самые старые сверх keep_last
+pub fn save_checkpoint(config: &CheckpointConfig, population: &Population) -> Result<PathBuf, SaveError> {
+    std::fs::create_dir_all(&config.dir).map_err(|e| SaveError::Io(config.dir.clone(), e))?;
+    let path = checkpoint_path(&config.dir, population.generation);
+    save_population(&path, population)?;
+
+    let checkpoints = list_checkpoints(&config.dir).map_err(|e| SaveError::Io(config.dir.clone(), e))?;
+    let excess = checkpoints.len().saturating_sub(config.keep_last.max(1));
+    for old in &checkpoints[..excess] {
+        std::fs::remove_file(&old.path).map_err(|e| SaveError::Io(old.path.clone(), e))?;
+    }
+    Ok(path)
+}
+
+/// Чекпоинты в каталоге, от старых к новым
+pub fn list_checkpoints(dir: &Path) -> std::io::Result<Vec<CheckpointInfo>> {
+    let mut checkpoints = Vec::new();
+    if !dir.exists() {
+        return Ok(checkpoints);
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let generation = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix("gen_"))
+            .and_then(|name| name.strip_suffix(".json"))
+            .and_then(|number| number.parse::<u32>().ok());
+        if let Some(generation) = generation {
+            checkpoints.push(CheckpointInfo { path, generation });
+        }
+    }
+    checkpoints.sort_by_key(|c| c.generation);
+    Ok(checkpoints)
+}
+
+/// Печатает список чекпоинтов для --list-checkpoints
+pub fn print_checkpoints(config: &CheckpointConfig) -> Result<(), SaveError> {
+    let checkpoints = list_checkpoints(&config.dir).map_err(|e| SaveError::Io(config.dir.clone(), e))?;
+    if checkpoints.is_empty() {
+        println!("В каталоге {} нет чекпоинтов", config.dir.display());
+        return Ok(());
+    }
+    println!("{:>10}  {:>14}  путь", "поколение", "лучший фитнес");
+    for checkpoint in checkpoints {
+        match load_population(&checkpoint.path) {
+            Ok(population) => {
+                let best = population
+                    .best_genome
+                    .as_ref()
+                    .map(|g| format!("{:.1}", g.fitness))
+                    .unwrap_or_else(|| "-".to_string());
+                println!("{:>10}  {:>14}  {}", checkpoint.generation, best, checkpoint.path.display());
+            }
+            Err(e) => println!("{:>10}  {:>14}  {}", checkpoint.generation, "не читается", e),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("smart_bot_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn migrates_unversioned_save() {
+        let dir = temp_dir("migrate");
+        let path = dir.join(BEST_GENOMES_FILE);
+        let legacy = serde_json::to_string(&Population::new_fresh(4)).unwrap();
+        std::fs::write(&path, legacy).unwrap();
+
+        let population = load_population(&path).unwrap();
+        assert_eq!(population.genomes.len(), 4);
+
+        save_population(&path, &population).unwrap();
+        let saved: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(save_version(&saved), SAVE_VERSION);
+        assert!(!dir.join("best_genomes.json.tmp").exists());
+    }
+
+    #[test]
+    fn widens_genomes_of_old_network() {
+        let dir = temp_dir("widen");
+        let path = dir.join(BEST_GENOMES_FILE);
+        let old = NetworkSpec::LEGACY[0];
+        let legacy_genome: Vec<f32> = (0..old.genome_len()).map(|k| k as f32).collect();
+
+        let mut population = Population::new_fresh(3);
+        population.es = Some(crate::es::EsState::new(legacy_genome.clone()));
+        let mut value = serde_json::to_value(&population).unwrap();
+        for genome in value["genomes"].as_array_mut().unwrap() {
+            genome["genome"] = legacy_genome.clone().into();
+        }
+        let save = serde_json::json!({ "version": 2, "population": value });
+        std::fs::write(&path, save.to_string()).unwrap();
+
+        let population = load_population(&path).unwrap();
+        let widened = old.widen_genome(&legacy_genome, NetworkSpec::CURRENT);
+        assert!(population.genomes.iter().all(|ai| ai.genome == widened));
+        let es = population.es.unwrap();
+        assert_eq!(es.mean, widened);
+        assert_eq!(es.adam_m.len(), NetworkSpec::CURRENT.genome_len());
+        // Веса новых входов нулевые, остальные на прежних местах
+        let new_inputs = old.inputs * old.hidden..NetworkSpec::CURRENT.inputs * old.hidden;
+        assert!(widened[new_inputs].iter().all(|&weight| weight == 0.0));
+        assert_eq!(widened.last(), legacy_genome.last());
+    }
+
+    #[test]
+    fn refuses_corrupt_and_future_saves() {
+        let dir = temp_dir("corrupt");
+        let path = dir.join(BEST_GENOMES_FILE);
+        std::fs::write(&path, "{ not json").unwrap();
+        assert!(matches!(load_population(&path), Err(SaveError::Parse(..))));
+
+        std::fs::write(&path, r#"{ "version": 999, "population": {} }"#).unwrap();
+        assert!(matches!(load_population(&path), Err(SaveError::UnsupportedVersion(_, 999))));
+    }
+
+    #[test]
+    fn keeps_last_n_checkpoints() {
+        let config = CheckpointConfig {
+            dir: temp_dir("rotate"),
+            keep_last: 3,
+        };
+        let mut population = Population::new_fresh(2);
+        for generation in 0..5 {
+            population.generation = generation;
+            save_checkpoint(&config, &population).unwrap();
+        }
+        let generations: Vec<u32> = list_checkpoints(&config.dir)
+            .unwrap()
+            .iter()
+            .map(|c| c.generation)
+            .collect();
+        assert_eq!(generations, vec![2, 3, 4]);
+    }
+}
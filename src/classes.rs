@@ -0,0 +1,247 @@
+use bevy::prelude::*;
+use crate::weapons::WeaponKind;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Класс танка
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TankClass {
+    Light,
+    #[default]
+    Medium,
+    Heavy,
+}
+
+impl TankClass {
+    pub const ALL: [TankClass; 3] = [TankClass::Light, TankClass::Medium, TankClass::Heavy];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "light" => Some(TankClass::Light),
+            "medium" => Some(TankClass::Medium),
+            "heavy" => Some(TankClass::Heavy),
+            _ => None,
+        }
+    }
+
+    /// Разбирает список через запятую
+    pub fn parse_list(list: &str) -> Option<Vec<Self>> {
+        list.split(',').map(|name| Self::parse(name.trim())).collect()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TankClass::Light => "лёгкий",
+            TankClass::Medium => "средний",
+            TankClass::Heavy => "тяжёлый",
+        }
+    }
+
+    /// Входы сети: единица на месте класса (см. NeuralNetwork::get_inputs)
+    pub fn one_hot(&self) -> [f32; 3] {
+        let mut inputs = [0.0; 3];
+        inputs[*self as usize] = 1.0;
+        inputs
+    }
+}
+
+/// Характеристики класса
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ClassDef {
+    pub class: TankClass,
+    /// Корпус: ширина, высота, длина, м
+    pub hull: [f32; 3],
+    pub health: f32,
+    pub speed: f32,
+    pub rotation_speed: f32,
+    /// Урон одного снаряда
+    pub damage: f32,
+    /// Перезарядка орудия, с
+    pub cooldown: f32,
+    /// Доступное оружие; из нескольких выбирает выход сети «оружие»
+    #[serde(default = "cannon_only")]
+    pub weapons: Vec<WeaponKind>,
+}
+
+/// Корпус среднего танка; размеры остальных считаются относительно него
+const MEDIUM_HULL: [f32; 3] = [2.0, 1.0, 3.0];
+/// Радиус корпуса среднего танка: два средних сближаются центрами до 3 м, а снаряды
+/// попадают в него на дистанции hit_radius своего оружия
+pub const MEDIUM_RADIUS: f32 = 1.5;
+
+fn cannon_only() -> Vec<WeaponKind> {
+    vec![WeaponKind::Cannon]
+}
+
+impl ClassDef {
+    /// Встроенные характеристики; средний совпадает с танком до появления классов
+    pub fn builtin(class: TankClass) -> Self {
+        match class {
+            TankClass::Light => Self {
+                class,
+                hull: [1.6, 0.8, 2.4],
+                health: 60.0,
+                speed: 7.5,
+                rotation_speed: 2.8,
+                damage: 12.0,
+                cooldown: 0.45,
+                weapons: vec![WeaponKind::MachineGun, WeaponKind::Mine],
+            },
+            TankClass::Medium => Self {
+                class,
+                hull: MEDIUM_HULL,
+                health: 100.0,
+                speed: 5.0,
+                rotation_speed: 2.0,
+                damage: 20.0,
+                cooldown: 0.7,
+                weapons: cannon_only(),
+            },
+            TankClass::Heavy => Self {
+                class,
+                hull: [2.6, 1.3, 3.8],
+                health: 160.0,
+                speed: 3.5,
+                rotation_speed: 1.3,
+                damage: 35.0,
+                cooldown: 1.2,
+                weapons: vec![WeaponKind::Cannon, WeaponKind::Artillery],
+            },
+        }
+    }
+
+    /// Радиус корпуса для столкновений и попаданий: растёт с площадью основания
+    /// (ширина × длина), у среднего ровно MEDIUM_RADIUS
+    pub fn radius(&self) -> f32 {
+        let [width, _, length] = self.hull;
+        let [medium_width, _, medium_length] = MEDIUM_HULL;
+        MEDIUM_RADIUS * (width * length / (medium_width * medium_length)).sqrt()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let positive = self.hull.iter().all(|&size| size > 0.0)
+            && self.health > 0.0
+            && self.speed >= 0.0
+            && self.rotation_speed >= 0.0
+            && self.damage >= 0.0
+            && self.cooldown > 0.0;
+        if !positive {
+            return Err(format!("класс {:?}: размеры, здоровье и перезарядка должны быть больше нуля, остальное — не меньше", self.class));
+        }
+        if self.weapons.is_empty() {
+            return Err(format!("класс {:?}: нужно хотя бы одно оружие", self.class));
+        }
+        Ok(())
+    }
+}
+
+/// Классы танков и составы команд. Читается из JSON (--tank-classes): классы,
+/// которых в файле нет, берутся встроенные
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TankClasses {
+    #[serde(default)]
+    pub classes: Vec<ClassDef>,
+    /// Составы синих и красных: классы по местам в команде, по кругу;
+    /// пустой состав — все средние
+    #[serde(default)]
+    pub teams: [Vec<TankClass>; 2],
+}
+
+impl Default for TankClasses {
+    fn default() -> Self {
+        Self {
+            classes: TankClass::ALL.into_iter().map(ClassDef::builtin).collect(),
+            teams: [Vec::new(), Vec::new()],
+        }
+    }
+}
+
+impl TankClasses {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut loaded: TankClasses = serde_json::from_str(&json).map_err(|e| format!("{}: {}", path.display(), e))?;
+        for def in &loaded.classes {
+            def.validate().map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        for class in TankClass::ALL {
+            if !loaded.classes.iter().any(|def| def.class == class) {
+                loaded.classes.push(ClassDef::builtin(class));
+            }
+        }
+        Ok(loaded)
+    }
+
+    pub fn get(&self, class: TankClass) -> &ClassDef {
+        self.classes
+            .iter()
+            .find(|def| def.class == class)
+            .expect("все классы заполняются при загрузке")
+    }
+
+    /// Класс места slot в команде team
+    pub fn for_slot(&self, team: u32, slot: usize) -> &ClassDef {
+        let composition = &self.teams[(team as usize).min(1)];
+        let class = if composition.is_empty() {
+            TankClass::Medium
+        } else {
+            composition[slot % composition.len()]
+        };
+        self.get(class)
+    }
+
+    /// Раздача классов по местам на один бой
+    pub fn picker(&self) -> ClassPicker<'_> {
+        ClassPicker { classes: self, next: [0; 2] }
+    }
+
+    /// Составы для сводки при запуске
+    pub fn describe(&self) -> String {
+        let team = |composition: &[TankClass]| {
+            if composition.is_empty() {
+                TankClass::Medium.name().to_string()
+            } else {
+                composition.iter().map(TankClass::name).collect::<Vec<_>>().join(", ")
+            }
+        };
+        format!("синие: {}; красные: {}", team(&self.teams[0]), team(&self.teams[1]))
+    }
+}
+
+/// Выдаёт классы танкам одного боя в порядке появления: места считаются
+/// отдельно для каждой команды, сквозь всех её участников (геномы, боты, мишени)
+pub struct ClassPicker<'a> {
+    classes: &'a TankClasses,
+    next: [usize; 2],
+}
+
+impl<'a> ClassPicker<'a> {
+    pub fn next(&mut self, team: u32) -> &'a ClassDef {
+        let slot = &mut self.next[(team as usize).min(1)];
+        let def = self.classes.for_slot(team, *slot);
+        *slot += 1;
+        def
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compositions_cycle_per_team() {
+        let classes = TankClasses {
+            teams: [vec![TankClass::Light, TankClass::Heavy], Vec::new()],
+            ..default()
+        };
+        let mut picker = classes.picker();
+        let blue: Vec<TankClass> = (0..3).map(|_| picker.next(0).class).collect();
+        assert_eq!(blue, vec![TankClass::Light, TankClass::Heavy, TankClass::Light]);
+        assert_eq!(picker.next(1).class, TankClass::Medium);
+
+        let medium = classes.get(TankClass::Medium);
+        assert_eq!((medium.health, medium.speed, medium.damage, medium.cooldown), (100.0, 5.0, 20.0, 0.7));
+        assert_eq!(TankClass::Heavy.one_hot(), [0.0, 0.0, 1.0]);
+        assert_eq!(TankClass::parse_list("light, heavy"), Some(vec![TankClass::Light, TankClass::Heavy]));
+    }
+}
@@ -0,0 +1,306 @@
+use crate::bots::BotStrategy;
+use crate::classes::TankClass;
+use crate::curriculum::CurriculumStage;
+use crate::es::Trainer;
+use crate::genetics::{FitnessAggregate, SelectionMetric, StagnationResponse};
+use crate::history::HistoryQuery;
+use crate::tournament::TournamentFormat;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Справка по аргументам командной строки
+pub const USAGE: &str = "\
+Использование: smart_bot [параметры]
+
+  --fresh                    начать с новой случайной популяции
+  --headless                 запуск без окна и рендера
+  --resume <файл>            продолжить с указанного сохранения или чекпоинта
+  --list-checkpoints         показать чекпоинты и выйти
+  --checkpoint-dir <каталог> каталог чекпоинтов (по умолчанию checkpoints)
+  --keep-checkpoints <N>     сколько последних чекпоинтов хранить (по умолчанию 10)
+  --run-dir <каталог>        куда писать метрики поколений и боёв (по умолчанию каталог чекпоинтов)
+  --history-db <файл>        база истории SQLite (по умолчанию history.sqlite в каталоге запуска)
+  --query <best|trend|ancestors ID>
+                             запрос к базе истории: лучшие геномы по поколениям,
+                             динамика фитнеса или предки генома; печатает и выходит
+  --export-champion <файл>   сохранить лучший геном популяции как чемпиона и выйти
+  --export-onnx <файл>       сохранить сеть лучшего генома как модель ONNX и выйти
+  --onnx-from <файл>         экспортировать в ONNX этого чемпиона вместо лучшего генома
+  --analyze <файл>           анализ чемпиона или лучшего генома сохранения: чувствительность
+                             к входам и поведение в контрольных сценариях; пишет отчёт и выходит
+  --analyze-out <префикс>    куда записать отчёт анализа: <префикс>.json (по умолчанию analysis)
+  --import-champion <файл>   добавить чемпиона в популяцию (можно несколько раз)
+  --opponent <файл>          выпускать чемпиона фиксированным противником (можно несколько раз)
+  --gen-battle <A> <B>       бой чемпиона поколения A против чемпиона поколения B
+                             (можно несколько раз; в headless после боёв программа завершится)
+  --select-by <fitness|rating>
+                             отбор по фитнесу последнего боя или по рейтингу Эло
+  --curriculum <stationary|moving|bots|self-play>
+                             этап обучения (новая популяция начинает с stationary)
+  --evaluations <K>          сколько боёв проводит каждый геном за поколение (по умолчанию 1)
+  --aggregate <mean|median|worst>
+                             как сводить фитнес этих боёв (по умолчанию mean)
+  --stagnation <off|hypermutation|immigrants|restart>
+                             реакция на застой фитнеса (по умолчанию off)
+  --stagnation-window <N>    сколько поколений без улучшения считать застоем (по умолчанию 15)
+  --trainer <ga|es>          генетический алгоритм (по умолчанию) или OpenAI-ES
+  --es-sigma <X>             масштаб шума возмущений ES (по умолчанию 0.1)
+  --es-lr <X>                шаг Adam для ES (по умолчанию 0.03)
+  --demos <файл>             куда писать демонстрации игрока (по умолчанию demonstrations.jsonl)
+  --clone <файл>             обучить сеть на демонстрациях и добавить её в популяцию
+  --clone-epochs <N>         эпох обучения на демонстрациях (по умолчанию 50)
+  --tournament <round-robin|single|double>
+                             турнир чемпионов вместо эволюции
+  --entrant <файл>           участник турнира: файл чемпиона или сохранение популяции
+  --tournament-checkpoints   добавить в турнир лучших из всех чекпоинтов
+  --team-size <N>            танков в команде в матче турнира или бенчмарка (по умолчанию 1)
+  --tournament-out <префикс> куда записать таблицу: <префикс>.json и .csv (по умолчанию tournament)
+  --sparring <charge,kite,camper,wall|all>
+                             выпускать ботов этих стратегий за красных в тренировочных боях
+  --tank-classes <файл>      характеристики классов и составы команд (JSON)
+  --blue-classes <light,medium,heavy>
+                             состав синих: классы по местам в команде, по кругу
+                             (по умолчанию все средние)
+  --red-classes <light,medium,heavy>
+                             состав красных, так же
+  --weapons <файл>           характеристики оружия (JSON)
+  --benchmark <файл>         прогнать чемпиона или лучший геном сохранения против всех ботов
+  --benchmark-matches <N>    матчей против каждой стратегии (по умолчанию 10)
+  --benchmark-out <префикс>  куда записать отчёт: <префикс>.json (по умолчанию benchmark)
+  --serve <адрес>            сервер для внешнего агента (JSON по TCP, например 127.0.0.1:5555);
+                             всегда без окна, мир шагает только по запросам клиента
+  --bench <N>                замер скорости: N поколений новой популяции без окна,
+                             отчёт о тактах/с, боях/мин и времени ИИ, физики и эволюции
+  --bench-ticks <T>          остановить замер после T тактов (можно вместо --bench)
+  --bench-seed <S>           зерно популяции замера (по умолчанию 42)
+  --bench-out <файл>         куда записать отчёт замера (по умолчанию bench.json)
+  --help                     показать эту справку";
+
+/// Параметры командной строки
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    pub fresh: bool,
+    pub headless: bool,
+    pub resume: Option<PathBuf>,
+    pub list_checkpoints: bool,
+    pub checkpoint_dir: Option<PathBuf>,
+    pub keep_checkpoints: Option<usize>,
+    pub run_dir: Option<PathBuf>,
+    pub history_db: Option<PathBuf>,
+    pub query: Option<HistoryQuery>,
+    pub export_champion: Option<PathBuf>,
+    pub export_onnx: Option<PathBuf>,
+    pub onnx_from: Option<PathBuf>,
+    pub analyze: Option<PathBuf>,
+    pub analyze_out: Option<PathBuf>,
+    pub import_champions: Vec<PathBuf>,
+    pub opponents: Vec<PathBuf>,
+    pub generation_battles: Vec<(u32, u32)>,
+    pub selection: Option<SelectionMetric>,
+    pub curriculum: Option<CurriculumStage>,
+    pub evaluations: Option<u32>,
+    pub aggregate: Option<FitnessAggregate>,
+    pub stagnation: Option<StagnationResponse>,
+    pub stagnation_window: Option<u32>,
+    pub trainer: Option<Trainer>,
+    pub es_sigma: Option<f32>,
+    pub es_learning_rate: Option<f32>,
+    pub demos: Option<PathBuf>,
+    pub clone_from: Option<PathBuf>,
+    pub clone_epochs: Option<usize>,
+    pub tournament: Option<TournamentFormat>,
+    pub entrants: Vec<PathBuf>,
+    pub tournament_checkpoints: bool,
+    pub team_size: Option<usize>,
+    pub tournament_out: Option<PathBuf>,
+    pub sparring: Vec<BotStrategy>,
+    pub tank_classes: Option<PathBuf>,
+    pub blue_classes: Option<Vec<TankClass>>,
+    pub red_classes: Option<Vec<TankClass>>,
+    pub weapons: Option<PathBuf>,
+    pub benchmark: Option<PathBuf>,
+    pub benchmark_matches: Option<u32>,
+    pub benchmark_out: Option<PathBuf>,
+    pub serve: Option<String>,
+    pub bench_generations: Option<u32>,
+    pub bench_ticks: Option<u64>,
+    pub bench_seed: Option<u64>,
+    pub bench_out: Option<PathBuf>,
+    pub help: bool,
+}
+
+impl CliArgs {
+    /// Запрошен замер скорости
+    pub fn bench(&self) -> bool {
+        self.bench_generations.is_some() || self.bench_ticks.is_some()
+    }
+
+    pub fn parse() -> Result<Self, String> {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    pub fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut cli = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--fresh" => cli.fresh = true,
+                "--headless" => cli.headless = true,
+                "--resume" => cli.resume = Some(value(&mut args, &arg)?.into()),
+                "--list-checkpoints" => cli.list_checkpoints = true,
+                "--checkpoint-dir" => cli.checkpoint_dir = Some(value(&mut args, &arg)?.into()),
+                "--keep-checkpoints" => cli.keep_checkpoints = Some(number(&mut args, &arg)?),
+                "--run-dir" => cli.run_dir = Some(value(&mut args, &arg)?.into()),
+                "--history-db" => cli.history_db = Some(value(&mut args, &arg)?.into()),
+                "--query" => {
+                    cli.query = Some(match value(&mut args, &arg)?.as_str() {
+                        "best" => HistoryQuery::Best,
+                        "trend" => HistoryQuery::Trend,
+                        "ancestors" => HistoryQuery::Ancestors(number(&mut args, &arg)?),
+                        other => return Err(format!("--query: неизвестный запрос {:?}", other)),
+                    });
+                }
+                "--export-champion" => cli.export_champion = Some(value(&mut args, &arg)?.into()),
+                "--export-onnx" => cli.export_onnx = Some(value(&mut args, &arg)?.into()),
+                "--onnx-from" => cli.onnx_from = Some(value(&mut args, &arg)?.into()),
+                "--analyze" => cli.analyze = Some(value(&mut args, &arg)?.into()),
+                "--analyze-out" => cli.analyze_out = Some(value(&mut args, &arg)?.into()),
+                "--import-champion" => cli.import_champions.push(value(&mut args, &arg)?.into()),
+                "--opponent" => cli.opponents.push(value(&mut args, &arg)?.into()),
+                "--gen-battle" => {
+                    let generation_a = number(&mut args, &arg)?;
+                    let generation_b = number(&mut args, &arg)?;
+                    cli.generation_battles.push((generation_a, generation_b));
+                }
+                "--select-by" => {
+                    cli.selection = Some(match value(&mut args, &arg)?.as_str() {
+                        "fitness" => SelectionMetric::Fitness,
+                        "rating" => SelectionMetric::Rating,
+                        other => return Err(format!("--select-by: неизвестный показатель {:?}", other)),
+                    });
+                }
+                "--curriculum" => {
+                    let stage = value(&mut args, &arg)?;
+                    cli.curriculum = Some(
+                        CurriculumStage::parse(&stage)
+                            .ok_or_else(|| format!("--curriculum: неизвестный этап {:?}", stage))?,
+                    );
+                }
+                "--evaluations" => cli.evaluations = Some(number(&mut args, &arg)?),
+                "--aggregate" => {
+                    cli.aggregate = Some(match value(&mut args, &arg)?.as_str() {
+                        "mean" => FitnessAggregate::Mean,
+                        "median" => FitnessAggregate::Median,
+                        "worst" => FitnessAggregate::Worst,
+                        other => return Err(format!("--aggregate: неизвестная свёртка {:?}", other)),
+                    });
+                }
+                "--stagnation" => {
+                    cli.stagnation = Some(match value(&mut args, &arg)?.as_str() {
+                        "off" => StagnationResponse::Off,
+                        "hypermutation" => StagnationResponse::Hypermutation,
+                        "immigrants" => StagnationResponse::RandomImmigrants,
+                        "restart" => StagnationResponse::PartialRestart,
+                        other => return Err(format!("--stagnation: неизвестная реакция {:?}", other)),
+                    });
+                }
+                "--stagnation-window" => cli.stagnation_window = Some(number(&mut args, &arg)?),
+                "--trainer" => {
+                    cli.trainer = Some(match value(&mut args, &arg)?.as_str() {
+                        "ga" => Trainer::Genetic,
+                        "es" => Trainer::Es,
+                        other => return Err(format!("--trainer: неизвестный метод {:?}", other)),
+                    });
+                }
+                "--es-sigma" => cli.es_sigma = Some(number(&mut args, &arg)?),
+                "--es-lr" => cli.es_learning_rate = Some(number(&mut args, &arg)?),
+                "--demos" => cli.demos = Some(value(&mut args, &arg)?.into()),
+                "--clone" => cli.clone_from = Some(value(&mut args, &arg)?.into()),
+                "--clone-epochs" => cli.clone_epochs = Some(number(&mut args, &arg)?),
+                "--tournament" => {
+                    let format = value(&mut args, &arg)?;
+                    cli.tournament = Some(
+                        TournamentFormat::parse(&format)
+                            .ok_or_else(|| format!("--tournament: неизвестный формат {:?}", format))?,
+                    );
+                }
+                "--entrant" => cli.entrants.push(value(&mut args, &arg)?.into()),
+                "--tournament-checkpoints" => cli.tournament_checkpoints = true,
+                "--team-size" => cli.team_size = Some(number(&mut args, &arg)?),
+                "--tournament-out" => cli.tournament_out = Some(value(&mut args, &arg)?.into()),
+                "--sparring" => {
+                    let list = value(&mut args, &arg)?;
+                    cli.sparring = BotStrategy::parse_list(&list)
+                        .ok_or_else(|| format!("--sparring: неизвестная стратегия в {:?}", list))?;
+                }
+                "--tank-classes" => cli.tank_classes = Some(value(&mut args, &arg)?.into()),
+                "--weapons" => cli.weapons = Some(value(&mut args, &arg)?.into()),
+                "--blue-classes" | "--red-classes" => {
+                    let list = value(&mut args, &arg)?;
+                    let classes = TankClass::parse_list(&list)
+                        .ok_or_else(|| format!("{}: неизвестный класс в {:?}", arg, list))?;
+                    if arg == "--blue-classes" {
+                        cli.blue_classes = Some(classes);
+                    } else {
+                        cli.red_classes = Some(classes);
+                    }
+                }
+                "--benchmark" => cli.benchmark = Some(value(&mut args, &arg)?.into()),
+                "--benchmark-matches" => cli.benchmark_matches = Some(number(&mut args, &arg)?),
+                "--benchmark-out" => cli.benchmark_out = Some(value(&mut args, &arg)?.into()),
+                "--serve" => cli.serve = Some(value(&mut args, &arg)?),
+                "--bench" => cli.bench_generations = Some(number(&mut args, &arg)?),
+                "--bench-ticks" => cli.bench_ticks = Some(number(&mut args, &arg)?),
+                "--bench-seed" => cli.bench_seed = Some(number(&mut args, &arg)?),
+                "--bench-out" => cli.bench_out = Some(value(&mut args, &arg)?.into()),
+                "--help" | "-h" => cli.help = true,
+                other => return Err(format!("Неизвестный аргумент: {}", other)),
+            }
+        }
+
+        if cli.tournament.is_none() && (!cli.entrants.is_empty() || cli.tournament_checkpoints) {
+            return Err("--entrant и --tournament-checkpoints требуют --tournament".to_string());
+        }
+        if cli.tournament.is_some() && cli.benchmark.is_some() {
+            return Err("--tournament и --benchmark нельзя использовать вместе".to_string());
+        }
+        if cli.serve.is_some() && (cli.tournament.is_some() || cli.benchmark.is_some() || !cli.generation_battles.is_empty()) {
+            return Err("--serve нельзя совмещать с --tournament, --benchmark и --gen-battle".to_string());
+        }
+        if cli.analyze_out.is_some() && cli.analyze.is_none() {
+            return Err("--analyze-out требует --analyze".to_string());
+        }
+        if cli.onnx_from.is_some() && cli.export_onnx.is_none() {
+            return Err("--onnx-from требует --export-onnx".to_string());
+        }
+        if !cli.bench() && (cli.bench_seed.is_some() || cli.bench_out.is_some()) {
+            return Err("--bench-seed и --bench-out требуют --bench или --bench-ticks".to_string());
+        }
+        if cli.bench()
+            && (cli.serve.is_some()
+                || cli.tournament.is_some()
+                || cli.benchmark.is_some()
+                || !cli.generation_battles.is_empty()
+                || cli.resume.is_some())
+        {
+            return Err("--bench нельзя совмещать с --serve, --tournament, --benchmark, --gen-battle и --resume".to_string());
+        }
+        if cli.fresh && cli.resume.is_some() {
+            return Err("--fresh и --resume нельзя использовать вместе".to_string());
+        }
+        Ok(cli)
+    }
+}
+
+/// Значение аргумента, идущее следующим
+fn value(args: &mut impl Iterator<Item = String>, name: &str) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("{} требует значение", name))
+}
+
+/// Числовое значение аргумента
+fn number<T: FromStr>(args: &mut impl Iterator<Item = String>, name: &str) -> Result<T, String> {
+    let raw = value(args, name)?;
+    raw.parse()
+        .map_err(|_| format!("{}: ожидалось число, получено {:?}", name, raw))
+}
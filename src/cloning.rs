@@ -0,0 +1,304 @@
+use bevy::prelude::*;
+use crate::ai::{NetworkSpec, NeuralNetwork, Observation};
+use crate::components::*;
+use crate::controller::{observe, TankAction, TankSnapshot};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// Файл демонстраций по умолчанию
+pub const DEMONSTRATIONS_FILE: &str = "demonstrations.jsonl";
+
+/// Один такт игры человека: что танк видел и что игрок сделал
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Demonstration {
+    #[serde(deserialize_with = "observation_any_version")]
+    pub observation: Observation,
+    /// Действие в единицах выходов сети: газ, поворот, выстрел и выбор оружия (0..1)
+    pub action: [f32; 4],
+    /// Поворот башни (Q/E) в тех же единицах 0..1. У сети такого выхода нет, и train
+    /// его не использует, но набор хранит действие игрока целиком
+    #[serde(default = "turret_idle")]
+    pub turret: f32,
+}
+
+fn turret_idle() -> f32 {
+    0.5
+}
+
+impl Demonstration {
+    pub fn new(observation: Observation, action: &TankAction) -> Self {
+        Self {
+            observation,
+            action: [
+                action.throttle.clamp(-1.0, 1.0) * 0.5 + 0.5,
+                action.turn.clamp(-1.0, 1.0) * 0.5 + 0.5,
+                if action.fire { 1.0 } else { 0.0 },
+                action.weapon.clamp(0.0, 1.0),
+            ],
+            turret: action.turret.clamp(-1.0, 1.0) * 0.5 + 0.5,
+        }
+    }
+}
+
+/// Наблюдение из набора любой версии: у старых записей нет входов, добавленных позже
+fn observation_any_version<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Observation, D::Error> {
+    let values = Vec::<f32>::deserialize(deserializer)?;
+    NeuralNetwork::upgrade_observation(&values)
+        .ok_or_else(|| serde::de::Error::invalid_length(values.len(), &"наблюдение известной версии сети"))
+}
+
+/// Запись демонстраций игрока
+#[derive(Resource)]
+pub struct DemonstrationRecorder {
+    pub path: PathBuf,
+    /// Ещё не сохранённые такты
+    pub pending: Vec<Demonstration>,
+    /// Сохранено за эту сессию
+    pub saved: usize,
+}
+
+impl DemonstrationRecorder {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            pending: Vec::new(),
+            saved: 0,
+        }
+    }
+
+    /// Дописывает накопленные такты в файл набора
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        for sample in &self.pending {
+            writeln!(file, "{}", serde_json::to_string(sample)?)?;
+        }
+        self.saved += self.pending.len();
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+/// Читает набор демонстраций (JSON Lines)
+pub fn load_demonstrations(path: &Path) -> std::io::Result<Vec<Demonstration>> {
+    BufReader::new(File::open(path)?)
+        .lines()
+        .filter(|line| !matches!(line, Ok(l) if l.trim().is_empty()))
+        .map(|line| {
+            let line = line?;
+            serde_json::from_str(&line).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+        })
+        .collect()
+}
+
+/// Танк стал показательным только на время управления игроком
+#[derive(Component)]
+pub struct ControlledExhibition;
+
+/// P — взять управление выбранным танком или вернуть его ИИ. Пока танком управляет
+/// человек, он выходит из оценки популяции: его бой не идёт в фитнес генома.
+#[allow(clippy::type_complexity)]
+pub fn take_control_system(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mut recorder: ResMut<DemonstrationRecorder>,
+    selected: Query<(Entity, Has<ExhibitionTank>), (With<Selected>, With<Tank>, Without<PlayerControlled>)>,
+    controlled: Query<(Entity, Has<ControlledExhibition>), With<PlayerControlled>>,
+) {
+    if !keys.just_pressed(KeyCode::P) {
+        return;
+    }
+    if !controlled.is_empty() {
+        for (entity, temporary) in controlled.iter() {
+            commands.entity(entity).remove::<PlayerControlled>();
+            // Показательным танк был только из-за игрока — возвращаем его в оценку
+            if temporary {
+                commands.entity(entity).remove::<(ExhibitionTank, ControlledExhibition)>();
+            }
+        }
+        match recorder.flush() {
+            Ok(()) => info!("Управление возвращено ИИ, записано тактов: {}", recorder.saved),
+            Err(e) => error!("Не удалось сохранить демонстрации: {}", e),
+        }
+        return;
+    }
+    if let Some((entity, exhibition)) = selected.iter().next() {
+        commands.entity(entity).insert(PlayerControlled);
+        if !exhibition {
+            commands.entity(entity).insert((ExhibitionTank, ControlledExhibition));
+        }
+        info!("Управление у игрока, демонстрации пишутся в {}", recorder.path.display());
+    }
+}
+
+/// Записывает наблюдение и действие танка игрока на каждом такте
+pub fn record_demonstrations(
+    mut recorder: ResMut<DemonstrationRecorder>,
+    players: Query<(&Transform, &Tank, &TankAction), With<PlayerControlled>>,
+    all_tanks: Query<(&Transform, &Tank)>,
+) {
+    if players.is_empty() {
+        return;
+    }
+    let snapshots = TankSnapshot::collect(all_tanks.iter());
+    for (transform, tank, action) in players.iter() {
+        recorder.pending.push(Demonstration::new(observe(transform, tank, &snapshots), action));
+    }
+}
+
+/// После боя сохраняет записанное (танк игрока мог погибнуть)
+pub fn flush_demonstrations(mut recorder: ResMut<DemonstrationRecorder>) {
+    if let Err(e) = recorder.flush() {
+        error!("Не удалось сохранить демонстрации: {}", e);
+    }
+}
+
+/// Обучение с учителем: сеть той же архитектуры подгоняется под действия игрока
+/// (среднеквадратичная ошибка по всем выходам, стохастический градиентный спуск)
+pub fn train(samples: &[Demonstration], epochs: usize, learning_rate: f32, rng: &mut impl Rng) -> Vec<f32> {
+    let NetworkSpec { inputs, hidden, outputs } = NetworkSpec::CURRENT;
+    let w2 = inputs * hidden;
+    let mut genome: Vec<f32> = (0..NetworkSpec::CURRENT.genome_len())
+        .map(|_| rng.gen_range(-0.5..0.5))
+        .collect();
+
+    let mut order: Vec<usize> = (0..samples.len()).collect();
+    let mut hidden_out = vec![0.0; hidden];
+    let mut hidden_delta = vec![0.0; hidden];
+    for _ in 0..epochs {
+        order.shuffle(rng);
+        for &index in &order {
+            let Demonstration { observation: x, action: target, .. } = &samples[index];
+
+            // Прямой проход в раскладке генома (см. NeuralNetwork::from_genome)
+            for (j, h) in hidden_out.iter_mut().enumerate() {
+                let sum: f32 = (0..inputs).map(|i| x[i] * genome[i * hidden + j]).sum();
+                *h = sigmoid(sum);
+            }
+            let mut output_delta = [0.0; 4];
+            for (k, delta) in output_delta.iter_mut().enumerate() {
+                let sum: f32 = (0..hidden).map(|j| hidden_out[j] * genome[w2 + j * outputs + k]).sum();
+                let o = sigmoid(sum);
+                *delta = (o - target[k]) * o * (1.0 - o);
+            }
+
+            // Обратный проход
+            for (j, delta) in hidden_delta.iter_mut().enumerate() {
+                let back: f32 = (0..outputs).map(|k| output_delta[k] * genome[w2 + j * outputs + k]).sum();
+                *delta = back * hidden_out[j] * (1.0 - hidden_out[j]);
+            }
+            for j in 0..hidden {
+                for (k, delta) in output_delta.iter().enumerate() {
+                    genome[w2 + j * outputs + k] -= learning_rate * hidden_out[j] * delta;
+                }
+                for i in 0..inputs {
+                    genome[i * hidden + j] -= learning_rate * x[i] * hidden_delta[j];
+                }
+            }
+        }
+    }
+    genome
+}
+
+/// Средняя квадратичная ошибка сети на демонстрациях по всем выходам
+pub fn loss(genome: &[f32], samples: &[Demonstration]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let network = NeuralNetwork::from_genome(genome);
+    let total: f32 = samples
+        .iter()
+        .map(|sample| {
+            let outputs = network.forward(&sample.observation);
+            outputs.iter().zip(&sample.action).map(|(o, target)| (o - target).powi(2)).sum::<f32>()
+        })
+        .sum();
+    total / (samples.len() * NetworkSpec::CURRENT.outputs) as f32
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::INPUT_DEFAULTS;
+    use rand::SeedableRng;
+
+    #[test]
+    fn fits_a_simple_policy() {
+        // «Игрок» едет вперёд и стреляет, когда враг прямо по курсу (входы 4 и 5 — sin и cos угла)
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        let samples: Vec<Demonstration> = (0..200)
+            .map(|_| {
+                let angle: f32 = rng.gen_range(-3.0..3.0);
+                let mut observation = INPUT_DEFAULTS;
+                observation[0] = 1.0;
+                observation[4] = angle.sin();
+                observation[5] = angle.cos();
+                let action = TankAction {
+                    throttle: 1.0,
+                    turn: angle.signum(),
+                    fire: angle.abs() < 0.3,
+                    ..default()
+                };
+                Demonstration::new(observation, &action)
+            })
+            .collect();
+
+        let untrained = loss(&train(&samples, 0, 0.5, &mut rng), &samples);
+        let trained = loss(&train(&samples, 200, 0.5, &mut rng), &samples);
+        assert!(trained < untrained * 0.5, "ошибка {} -> {}", untrained, trained);
+
+        // Запись до появления классов танков читается как наблюдение среднего танка
+        let legacy: Demonstration =
+            serde_json::from_str(r#"{"observation":[1,0.5,0,1,0,1,1,0],"action":[1,0.5,0,0.5]}"#).unwrap();
+        assert_eq!(legacy.observation[8..11], [0.0, 1.0, 0.0]);
+        assert_eq!(legacy.turret, 0.5);
+        let turning = Demonstration::new(INPUT_DEFAULTS, &TankAction { turret: -1.0, ..default() });
+        assert_eq!(turning.turret, 0.0);
+    }
+
+    #[test]
+    fn handing_control_back_returns_tank_to_evaluation() {
+        use bevy::ecs::system::RunSystemOnce;
+
+        let mut world = World::new();
+        world.insert_resource(Input::<KeyCode>::default());
+        world.insert_resource(DemonstrationRecorder::new(DEMONSTRATIONS_FILE.into()));
+        let tank = world.spawn((Tank::default(), Selected)).id();
+        let exhibition = world.spawn((Tank::default(), ExhibitionTank)).id();
+        let press_p = |world: &mut World| {
+            let mut keys = world.resource_mut::<Input<KeyCode>>();
+            keys.release(KeyCode::P);
+            keys.clear();
+            keys.press(KeyCode::P);
+        };
+
+        press_p(&mut world);
+        world.run_system_once(take_control_system);
+        assert!(world.get::<PlayerControlled>(tank).is_some());
+        assert!(world.get::<ExhibitionTank>(tank).is_some());
+
+        press_p(&mut world);
+        world.run_system_once(take_control_system);
+        assert!(world.get::<PlayerControlled>(tank).is_none());
+        assert!(world.get::<ExhibitionTank>(tank).is_none());
+
+        // Танк, который был показательным и до игрока, таким и остаётся
+        world.entity_mut(tank).remove::<Selected>();
+        world.entity_mut(exhibition).insert(Selected);
+        press_p(&mut world);
+        world.run_system_once(take_control_system);
+        press_p(&mut world);
+        world.run_system_once(take_control_system);
+        assert!(world.get::<ExhibitionTank>(exhibition).is_some());
+    }
+}
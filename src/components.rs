@@ -1,5 +1,9 @@
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
+use crate::ai::NetworkSpec;
+use crate::classes::{ClassDef, TankClass, MEDIUM_RADIUS};
+use crate::weapons::{Flight, Gun, WeaponKind};
+use crate::rating::Rating;
 
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
 pub enum GameState {
@@ -7,6 +11,8 @@ pub enum GameState {
     Setup,
     Battle,
     Evolution,
+    /// Между матчами турнира или бенчмарка
+    Tournament,
 }
 
 /// Компонент танка
@@ -19,6 +25,17 @@ pub struct Tank {
     pub rotation_speed: f32,
     pub generation: u32,
     pub team: u32,
+    /// Поворот башни относительно корпуса, рад
+    pub turret_angle: f32,
+    pub class: TankClass,
+    /// Урон снаряда орудия
+    pub damage: f32,
+    /// Оружие класса; какое стреляет, выбирает TankAction::weapon
+    pub weapons: Vec<WeaponKind>,
+    /// Магазины и нагрев стволов этого оружия
+    pub gun: Gun,
+    /// Радиус корпуса (см. ClassDef::radius): от него зависят столкновения и попадания
+    pub radius: f32,
 }
 
 impl Default for Tank {
@@ -30,6 +47,36 @@ impl Default for Tank {
             rotation_speed: 2.0,
             generation: 0,
             team: 0,
+            turret_angle: 0.0,
+            class: TankClass::Medium,
+            damage: 20.0,
+            weapons: vec![WeaponKind::Cannon],
+            gun: Gun::new(1),
+            radius: MEDIUM_RADIUS,
+        }
+    }
+}
+
+impl Tank {
+    /// Дистанция попадания снаряда оружия с hit_radius: крупный корпус поймать проще
+    pub fn hit_distance(&self, hit_radius: f32) -> f32 {
+        hit_radius * self.radius / MEDIUM_RADIUS
+    }
+
+    /// Танк с характеристиками класса
+    pub fn of_class(def: &ClassDef, team: u32) -> Self {
+        Self {
+            health: def.health,
+            max_health: def.health,
+            speed: def.speed,
+            rotation_speed: def.rotation_speed,
+            team,
+            class: def.class,
+            damage: def.damage,
+            weapons: def.weapons.clone(),
+            gun: Gun::new(def.weapons.len()),
+            radius: def.radius(),
+            ..default()
         }
     }
 }
@@ -38,22 +85,68 @@ impl Default for Tank {
 #[derive(Component)]
 pub struct Projectile {
     pub damage: f32,
-    pub speed: f32,
+    /// Скорость, м/с; у баллистических снарядов меняется под действием тяжести
+    pub velocity: Vec3,
     pub lifetime: Timer,
+    /// Пока не истёк, снаряд не реагирует на танки (мина успевает остаться позади)
+    pub armed: Timer,
     pub owner: Entity,
+    /// Команда владельца: свои танки не подрывают мину и не получают осколков
+    pub team: u32,
+    pub flight: Flight,
+    pub hit_radius: f32,
+    pub splash_radius: f32,
 }
 
-/// Перезарядка орудия танка
+/// Перезарядка орудия танка: после выстрела длится base × множитель оружия
 #[derive(Component)]
 pub struct FireCooldown {
     pub timer: Timer,
+    /// Перезарядка орудия класса, с
+    pub base: f32,
+}
+
+impl FireCooldown {
+    pub fn from_seconds(seconds: f32) -> Self {
+        let mut timer = Timer::from_seconds(seconds, TimerMode::Once);
+        timer.set_elapsed(timer.duration()); // сразу готов к выстрелу
+        Self { timer, base: seconds }
+    }
+
+    /// Выстрел из оружия с множителем перезарядки scale
+    pub fn restart(&mut self, scale: f32) {
+        self.timer.set_duration(std::time::Duration::from_secs_f32(self.base * scale));
+        self.timer.reset();
+    }
 }
 
 impl Default for FireCooldown {
     fn default() -> Self {
-        let mut timer = Timer::from_seconds(0.7, TimerMode::Once);
-        timer.set_elapsed(timer.duration()); // сразу готов к выстрелу
-        Self { timer }
+        Self::from_seconds(0.7)
+    }
+}
+
+/// Поведенческая статистика танка за бой (для архива MAP-Elites)
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct BehaviorStats {
+    pub shots_fired: u32,
+    pub hits: u32,
+    pub distance_travelled: f32,
+    /// Время, проведённое на дистанции боя с ближайшим врагом
+    pub engaged_time: f32,
+    /// Сколько танк проехал бы за то же время на полном газу своего класса
+    #[serde(default)]
+    pub max_distance: f32,
+}
+
+impl BehaviorStats {
+    /// Добавляет статистику ещё одного боя
+    pub fn accumulate(&mut self, other: &BehaviorStats) {
+        self.shots_fired += other.shots_fired;
+        self.hits += other.hits;
+        self.distance_travelled += other.distance_travelled;
+        self.engaged_time += other.engaged_time;
+        self.max_distance += other.max_distance;
     }
 }
 
@@ -64,28 +157,77 @@ pub struct AIController {
     pub fitness: f32,
     pub kills: u32,
     pub survival_time: f32,
+    #[serde(default)]
+    pub behavior: BehaviorStats,
+    /// Фитнес в прошлых поколениях, которые геном пережил как элита
+    #[serde(default)]
+    pub fitness_history: Vec<f32>,
+    /// Поколение, в котором геном появился
+    #[serde(default)]
+    pub origin_generation: u32,
+    /// Рейтинг Эло по всем сыгранным боям
+    #[serde(default)]
+    pub rating: Rating,
+    /// Уникальный номер генома в популяции (0 — ещё не присвоен)
+    #[serde(default)]
+    pub id: u64,
+    /// Номера родителей, от которых геном получен скрещиванием
+    #[serde(default)]
+    pub parents: Vec<u64>,
+    /// Фитнес каждого боя текущего поколения; fitness — их свёртка (см. EvaluationSettings)
+    #[serde(default)]
+    pub fitness_samples: Vec<f32>,
 }
 
 impl AIController {
-    pub fn new_random() -> Self {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
-        
-        // Простая нейронная сеть: 8 входов -> 8 скрытых -> 4 выхода
-        // Входы: расстояние до ближайшего врага, угол, здоровье свое/врага, позиция и т.д.
-        // Выходы: движение вперед/назад, поворот влево/вправо
-        let genome_size = (8 * 8) + (8 * 4); // веса связей
-        let genome: Vec<f32> = (0..genome_size)
-            .map(|_| rng.gen_range(-1.0..1.0))
-            .collect();
-        
+    /// Контроллер с заданным геномом и обнулённой статистикой
+    pub fn with_genome(genome: Vec<f32>) -> Self {
         Self {
             genome,
             fitness: 0.0,
             kills: 0,
             survival_time: 0.0,
+            behavior: BehaviorStats::default(),
+            fitness_history: Vec::new(),
+            origin_generation: 0,
+            rating: Rating::default(),
+            id: 0,
+            parents: Vec::new(),
+            fitness_samples: Vec::new(),
+        }
+    }
+
+    /// Копия для выхода в бой: статистика боя начинается с нуля
+    pub fn battle_copy(&self) -> Self {
+        Self {
+            fitness: 0.0,
+            kills: 0,
+            survival_time: 0.0,
+            behavior: BehaviorStats::default(),
+            fitness_samples: Vec::new(),
+            ..self.clone()
         }
     }
+
+    /// Сколько боёв геном провёл в текущем поколении (не меньше 1, чтобы делить)
+    pub fn battles_played(&self) -> f32 {
+        self.fitness_samples.len().max(1) as f32
+    }
+
+    pub fn new_random() -> Self {
+        Self::random_with(&mut rand::thread_rng())
+    }
+
+    /// Случайный геном из заданного генератора (воспроизводимые популяции)
+    pub fn random_with(rng: &mut impl rand::Rng) -> Self {
+        // Размеры сети (входы, скрытые, выходы) задаёт NetworkSpec::CURRENT, имена входов — INPUT_NAMES
+        let genome_size = NetworkSpec::CURRENT.genome_len(); // веса связей
+        let genome: Vec<f32> = (0..genome_size)
+            .map(|_| rng.gen_range(-1.0..1.0))
+            .collect();
+        
+        Self::with_genome(genome)
+    }
 }
 
 /// Компонент игрока
@@ -99,6 +241,10 @@ pub struct TankTurret {
     pub parent_tank: Entity,
 }
 
+/// Танк вне эволюции (например, элита из архива): его результат не идёт в популяцию
+#[derive(Component)]
+pub struct ExhibitionTank;
+
 /// Компонент выбора танка для просмотра от 3-го лица
 #[derive(Component)]
 pub struct Selected;
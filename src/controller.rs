@@ -0,0 +1,253 @@
+use bevy::prelude::*;
+use bevy::time::Fixed;
+use crate::ai::{NeuralNetwork, Observation};
+use crate::components::*;
+use crate::map::ObstacleComponent;
+use crate::systems::spawn_projectile;
+use crate::weapons::{WeaponKind, Weapons};
+use crate::Headless;
+
+/// Ближе этого к центру препятствия танк не проезжает
+pub const OBSTACLE_RADIUS: f32 = 6.0;
+
+/// Команды танку на текущий такт. Их заполняет контроллер (игрок, нейросеть, бот),
+/// а применяет общая система apply_tank_actions, поэтому новому виду управления
+/// достаточно записать действие.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct TankAction {
+    /// Газ: 1 — вперёд (локальная -Z), -1 — назад
+    pub throttle: f32,
+    /// Поворот корпуса: 1 — влево (против часовой стрелки), -1 — вправо
+    pub turn: f32,
+    /// Поворот башни относительно корпуса, в тех же единицах
+    pub turret: f32,
+    /// Выстрелить, как только орудие перезарядится
+    pub fire: bool,
+    /// Выбор оружия из набора класса: 0..1 делится поровну между видами (см. WeaponKind::select)
+    pub weapon: f32,
+}
+
+impl TankAction {
+    /// Действие из выходов сети: [0] — газ, [1] — поворот (0..1 -> -1..1), [2] — выстрел,
+    /// [3] — оружие
+    pub fn from_network(outputs: &[f32; 4]) -> Self {
+        Self {
+            throttle: (outputs[0] - 0.5) * 2.0,
+            turn: (outputs[1] - 0.5) * 2.0,
+            turret: 0.0,
+            fire: outputs[2] > 0.5,
+            weapon: outputs[3],
+        }
+    }
+
+    /// Значения приведены к допустимым диапазонам
+    fn clamped(&self) -> Self {
+        Self {
+            throttle: self.throttle.clamp(-1.0, 1.0),
+            turn: self.turn.clamp(-1.0, 1.0),
+            turret: self.turret.clamp(-1.0, 1.0),
+            fire: self.fire,
+            weapon: self.weapon.clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// Системы, в которых контроллеры заполняют TankAction; apply_tank_actions идёт после них
+#[derive(SystemSet, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ControllerSet;
+
+/// Системы, двигающие мир по уже выбранным действиям: танки, снаряды, попадания
+#[derive(SystemSet, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PhysicsSet;
+
+/// Сводка о танке для контроллеров: кто где и сколько у кого здоровья
+#[derive(Clone, Copy, Debug)]
+pub struct TankSnapshot {
+    pub position: Vec3,
+    pub team: u32,
+    pub health: f32,
+    pub max_health: f32,
+}
+
+impl TankSnapshot {
+    pub fn collect<'a>(tanks: impl Iterator<Item = (&'a Transform, &'a Tank)>) -> Vec<Self> {
+        tanks
+            .map(|(transform, tank)| Self {
+                position: transform.translation,
+                team: tank.team,
+                health: tank.health,
+                max_health: tank.max_health,
+            })
+            .collect()
+    }
+
+    /// Ближайший живой танк другой команды
+    pub fn nearest_enemy(snapshots: &[Self], position: Vec3, team: u32) -> Option<&Self> {
+        snapshots
+            .iter()
+            .filter(|other| other.team != team && other.health > 0.0)
+            .min_by(|a, b| a.position.distance(position).total_cmp(&b.position.distance(position)))
+    }
+}
+
+/// Вектор наблюдения танка, тот же, что получает нейросеть (см. NeuralNetwork::get_inputs)
+pub fn observe(transform: &Transform, tank: &Tank, snapshots: &[TankSnapshot]) -> Observation {
+    let nearest = TankSnapshot::nearest_enemy(snapshots, transform.translation, tank.team);
+    let gun = tank.gun.current();
+    NeuralNetwork::get_inputs(
+        transform.translation,
+        transform.rotation.to_euler(EulerRot::YXZ).0,
+        tank.health / tank.max_health,
+        tank.class,
+        gun.ammo,
+        gun.heat,
+        nearest.map(|enemy| enemy.position),
+        nearest.map(|enemy| enemy.health / enemy.max_health),
+    )
+}
+
+/// Применяет TankAction всех танков: движение с откатом при столкновении, поворот
+/// корпуса и башни, высота над землёй и стрельба выбранным оружием с перезарядкой,
+/// магазином и нагревом ствола
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn apply_tank_actions(
+    time: Res<Time<Fixed>>,
+    headless: Res<Headless>,
+    weapons: Res<Weapons>,
+    mut commands: Commands,
+    mut meshes: Option<ResMut<Assets<Mesh>>>,
+    mut materials: Option<ResMut<Assets<StandardMaterial>>>,
+    mut tanks: Query<(Entity, &mut Transform, &mut Tank, &TankAction, &mut FireCooldown, &TeamColor, Option<&mut AIController>)>,
+    mut turrets: Query<(&TankTurret, &mut Transform), Without<Tank>>,
+    obstacle_query: Query<&Transform, (With<ObstacleComponent>, Without<Tank>, Without<TankTurret>)>,
+) {
+    let dt = time.delta_seconds();
+    let mut positions: Vec<(Entity, Vec3, f32)> = tanks.iter().map(|(e, t, tank, ..)| (e, t.translation, tank.radius)).collect();
+
+    for (entity, mut transform, mut tank, action, mut cooldown, team_color, mut ai) in tanks.iter_mut() {
+        let action = action.clamped();
+        cooldown.timer.tick(time.delta());
+        let Tank { weapons: kinds, gun, .. } = &mut *tank;
+        for (state, kind) in gun.weapons.iter_mut().zip(kinds.iter()) {
+            state.tick(dt, weapons.get(*kind));
+        }
+        gun.selected = WeaponKind::select_index(kinds.len(), action.weapon);
+
+        let old_pos = transform.translation;
+        let forward = transform.forward();
+        let new_pos = old_pos + forward * action.throttle * tank.speed * dt;
+
+        // Откат при наезде на препятствие или на другой танк (ближе суммы радиусов
+        // корпусов); от танка, в который уже упёрлись, отъехать можно
+        let collided = obstacle_query
+            .iter()
+            .any(|obstacle| new_pos.distance(obstacle.translation) < OBSTACLE_RADIUS)
+            || positions.iter().any(|&(other, pos, radius)| {
+                other != entity
+                    && new_pos.distance(pos) < tank.radius + radius
+                    && new_pos.distance(pos) < old_pos.distance(pos)
+            });
+        if !collided {
+            transform.translation = new_pos;
+        }
+        transform.rotate_y(action.turn * tank.rotation_speed * dt);
+        // Фиксируем высоту танка на поверхности
+        transform.translation.y = 0.5;
+        if let Some(entry) = positions.iter_mut().find(|(other, ..)| *other == entity) {
+            entry.1 = transform.translation;
+        }
+
+        tank.turret_angle = (tank.turret_angle + action.turret * tank.rotation_speed * dt)
+            .rem_euclid(2.0 * std::f32::consts::PI);
+
+        if let Some(ai) = ai.as_mut() {
+            ai.behavior.distance_travelled += transform.translation.distance(old_pos);
+            ai.behavior.max_distance += tank.speed * dt;
+        }
+
+        let weapon = weapons.get(WeaponKind::select(&tank.weapons, action.weapon));
+        if action.fire && cooldown.timer.finished() && tank.gun.current().ready(weapon) {
+            let muzzle = transform.with_rotation(transform.rotation * Quat::from_rotation_y(tank.turret_angle));
+            let (shot, projectile) = weapon.launch(entity, tank.team, &muzzle, tank.damage);
+            spawn_projectile(&mut commands, meshes.as_mut(), materials.as_mut(), weapon, shot, projectile, team_color.0, headless.0);
+            cooldown.restart(weapon.cooldown_scale);
+            let selected = tank.gun.selected;
+            if let Some(state) = tank.gun.weapons.get_mut(selected) {
+                state.fire(weapon);
+            }
+            if let Some(ai) = ai.as_mut() {
+                ai.behavior.shots_fired += 1;
+            }
+        }
+    }
+
+    // Башни повёрнуты вслед за углом из Tank
+    for (turret, mut transform) in turrets.iter_mut() {
+        if let Ok((_, _, tank, ..)) = tanks.get(turret.parent_tank) {
+            transform.rotation = Quat::from_rotation_y(tank.turret_angle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn network_outputs_map_to_actions() {
+        let action = TankAction::from_network(&[1.0, 0.0, 0.6, 0.9]);
+        assert_eq!(action, TankAction { throttle: 1.0, turn: -1.0, turret: 0.0, fire: true, weapon: 0.9 });
+
+        let snapshots = [
+            TankSnapshot { position: Vec3::new(5.0, 0.0, 0.0), team: 0, health: 100.0, max_health: 100.0 },
+            TankSnapshot { position: Vec3::new(9.0, 0.0, 0.0), team: 1, health: 100.0, max_health: 100.0 },
+            TankSnapshot { position: Vec3::new(3.0, 0.0, 0.0), team: 1, health: 0.0, max_health: 100.0 },
+        ];
+        let enemy = TankSnapshot::nearest_enemy(&snapshots, Vec3::ZERO, 0).unwrap();
+        assert_eq!(enemy.position.x, 9.0);
+    }
+
+    #[test]
+    fn ai_tanks_see_and_block_each_other() {
+        use crate::ai::NetworkActivity;
+        use crate::classes::{TankClass, TankClasses};
+        use bevy::ecs::system::RunSystemOnce;
+
+        let mut world = World::new();
+        let mut time = Time::<Fixed>::default();
+        time.advance_by(std::time::Duration::from_millis(100));
+        world.insert_resource(time);
+        world.insert_resource(Headless(true));
+        world.insert_resource(Weapons::default());
+        let classes = TankClasses::default();
+        let class = classes.get(TankClass::Medium);
+        let spawn = |world: &mut World, position: Vec3, team: u32| {
+            let ai = AIController::new_random();
+            world
+                .spawn((
+                    Transform::from_translation(position),
+                    Tank::of_class(class, team),
+                    TeamColor(Color::WHITE),
+                    FireCooldown::from_seconds(class.cooldown),
+                    TankAction { throttle: 1.0, ..default() },
+                    NeuralNetwork::from_genome(&ai.genome),
+                    NetworkActivity::default(),
+                    ai,
+                ))
+                .id()
+        };
+        // Враг прямо по курсу (-Z) чуть дальше суммы радиусов двух средних корпусов
+        let blue = spawn(&mut world, Vec3::new(0.0, 0.5, 0.0), 0);
+        spawn(&mut world, Vec3::new(0.0, 0.5, -3.2), 1);
+
+        // Сеть получает на входы другой танк с нейросетью, а не пустое наблюдение
+        world.run_system_once(crate::systems::ai_control_system);
+        let inputs = world.get::<NetworkActivity>(blue).unwrap().inputs;
+        assert!((inputs[1] - 3.2 / 50.0).abs() < 1e-4);
+
+        // И не проезжает сквозь него
+        world.entity_mut(blue).insert(TankAction { throttle: 1.0, ..default() });
+        world.run_system_once(apply_tank_actions);
+        assert_eq!(world.get::<Transform>(blue).unwrap().translation, Vec3::new(0.0, 0.5, 0.0));
+    }
+}
@@ -0,0 +1,255 @@
+use bevy::prelude::*;
+use bevy::time::Fixed;
+use crate::battle::spawn_tank_any;
+use crate::classes::ClassPicker;
+use crate::components::*;
+use crate::controller::{TankAction, TankSnapshot};
+use crate::metrics::GenerationStats;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// Мишеней в одном бою на учебных этапах
+pub const TARGETS_PER_BATTLE: usize = 5;
+/// Сколько последних поколений подряд должны выполнить критерий перехода
+pub const PROMOTION_WINDOW: usize = 3;
+
+/// Этап обучения: от неподвижных мишеней к боям популяции друг с другом
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CurriculumStage {
+    StationaryTargets,
+    MovingTargets,
+    WeakBots,
+    /// Полноценные бои популяции друг с другом; старые сохранения сразу здесь
+    #[default]
+    SelfPlay,
+}
+
+impl CurriculumStage {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "stationary" => Some(CurriculumStage::StationaryTargets),
+            "moving" => Some(CurriculumStage::MovingTargets),
+            "bots" => Some(CurriculumStage::WeakBots),
+            "self-play" => Some(CurriculumStage::SelfPlay),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CurriculumStage::StationaryTargets => "неподвижные мишени",
+            CurriculumStage::MovingTargets => "движущиеся мишени",
+            CurriculumStage::WeakBots => "слабые боты",
+            CurriculumStage::SelfPlay => "бои популяции",
+        }
+    }
+
+    pub fn next(&self) -> Option<Self> {
+        match self {
+            CurriculumStage::StationaryTargets => Some(CurriculumStage::MovingTargets),
+            CurriculumStage::MovingTargets => Some(CurriculumStage::WeakBots),
+            CurriculumStage::WeakBots => Some(CurriculumStage::SelfPlay),
+            CurriculumStage::SelfPlay => None,
+        }
+    }
+
+    /// Какую долю мишеней боя популяция должна в среднем уничтожать для перехода
+    fn target_share(&self) -> f32 {
+        match self {
+            CurriculumStage::StationaryTargets => 0.6,
+            CurriculumStage::MovingTargets => 0.5,
+            CurriculumStage::WeakBots => 0.4,
+            CurriculumStage::SelfPlay => f32::INFINITY,
+        }
+    }
+
+    /// Убийств за бой в среднем на геном, нужных для перехода на следующий этап, если
+    /// против TARGETS_PER_BATTLE мишеней выходят tanks танков популяции: больше одной
+    /// мишени на танк не достанется, поэтому порог — доля от этого потолка
+    pub fn kill_threshold(&self, tanks: usize) -> f32 {
+        self.target_share() * TARGETS_PER_BATTLE as f32 / tanks.max(1) as f32
+    }
+
+    /// Против мишеней вся популяция играет за синих
+    pub fn uses_targets(&self) -> bool {
+        *self != CurriculumStage::SelfPlay
+    }
+}
+
+/// Текущий этап обучения, сохраняемый вместе с популяцией
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CurriculumState {
+    pub stage: CurriculumStage,
+    pub generations_in_stage: u32,
+}
+
+impl CurriculumState {
+    /// Начало обучения с первого этапа
+    pub fn first_stage() -> Self {
+        Self {
+            stage: CurriculumStage::StationaryTargets,
+            generations_in_stage: 0,
+        }
+    }
+
+    /// Учитывает прошедшее поколение; при выполнении критерия переходит на следующий этап
+    /// и возвращает его. Критерий — PROMOTION_WINDOW последних поколений этапа подряд
+    /// со средним числом убийств за бой не ниже порога этапа (tanks — танков популяции в бою).
+    pub fn update(&mut self, history: &[GenerationStats], tanks: usize) -> Option<CurriculumStage> {
+        self.generations_in_stage += 1;
+        let next = self.stage.next()?;
+        if (self.generations_in_stage as usize) < PROMOTION_WINDOW || history.len() < PROMOTION_WINDOW {
+            return None;
+        }
+        let threshold = self.stage.kill_threshold(tanks);
+        let recent = &history[history.len() - PROMOTION_WINDOW..];
+        if recent.iter().all(|s| s.mean_kills >= threshold) {
+            self.stage = next;
+            self.generations_in_stage = 0;
+            return Some(next);
+        }
+        None
+    }
+}
+
+/// Учебная мишень: танк без нейросети под управлением простого сценария
+#[derive(Component, Clone, Copy, Debug)]
+pub enum ScriptedTarget {
+    Stationary,
+    /// Ездит по кругу
+    Moving,
+    /// Медленно наводится на ближайшего врага и изредка стреляет
+    WeakBot,
+}
+
+/// Расставляет мишени текущего этапа за красных
+pub fn spawn_targets(
+    commands: &mut Commands,
+    mut meshes: Option<&mut ResMut<Assets<Mesh>>>,
+    mut materials: Option<&mut ResMut<Assets<StandardMaterial>>>,
+    picker: &mut ClassPicker,
+    stage: CurriculumStage,
+    rng: &mut impl Rng,
+) {
+    let target = match stage {
+        CurriculumStage::StationaryTargets => ScriptedTarget::Stationary,
+        CurriculumStage::MovingTargets => ScriptedTarget::Moving,
+        CurriculumStage::WeakBots => ScriptedTarget::WeakBot,
+        CurriculumStage::SelfPlay => return,
+    };
+    for _ in 0..TARGETS_PER_BATTLE {
+        let position = Vec3::new(rng.gen_range(-40.0..40.0), 0.5, rng.gen_range(-40.0..40.0));
+        let entity = spawn_tank_any(
+            commands,
+            meshes.as_deref_mut(),
+            materials.as_deref_mut(),
+            position,
+            1,
+            picker.next(1),
+            None,
+        );
+        commands
+            .entity(entity)
+            .insert((target, Transform::from_translation(position).with_rotation(Quat::from_rotation_y(rng.gen_range(-PI..PI)))));
+    }
+}
+
+/// Управление мишенями
+pub fn scripted_target_system(
+    time: Res<Time<Fixed>>,
+    mut targets: Query<(&Transform, &Tank, &ScriptedTarget, &mut TankAction)>,
+    all_tanks: Query<(&Transform, &Tank)>,
+) {
+    let dt = time.delta_seconds();
+    let snapshots = TankSnapshot::collect(all_tanks.iter());
+    let mut rng = rand::thread_rng();
+    for (transform, tank, target, mut action) in targets.iter_mut() {
+        *action = match target {
+            ScriptedTarget::Stationary => TankAction::default(),
+            // Половина скорости и медленный поворот: круг радиусом около 5 м
+            ScriptedTarget::Moving => TankAction {
+                throttle: 0.5,
+                turn: 0.25,
+                ..default()
+            },
+            ScriptedTarget::WeakBot => {
+                let Some(enemy) = TankSnapshot::nearest_enemy(&snapshots, transform.translation, tank.team) else {
+                    *action = TankAction::default();
+                    continue;
+                };
+                // Орудие смотрит по локальной оси +Z (см. spawn_projectile)
+                let to_enemy = enemy.position - transform.translation;
+                let desired = to_enemy.x.atan2(to_enemy.z);
+                let current = transform.rotation.to_euler(EulerRot::YXZ).0;
+                let error = (desired - current + PI).rem_euclid(2.0 * PI) - PI;
+                // Не быстрее половины скорости поворота и без перелёта через цель
+                let turn = (error / (tank.rotation_speed * dt).max(f32::EPSILON)).clamp(-0.5, 0.5);
+
+                // Стреляет, только если почти навёлся, и то не всегда
+                TankAction {
+                    turn,
+                    fire: error.abs() < 0.3 && rng.gen::<f32>() < 0.3,
+                    ..default()
+                }
+            }
+        };
+    }
+}
+
+/// Движущиеся мишени не уезжают дальше ±45 м от центра карты, иначе круг выносит их за край.
+/// Идёт после apply_tank_actions, которая двигает их наравне с остальными танками
+pub fn confine_moving_targets(mut targets: Query<(&mut Transform, &ScriptedTarget)>) {
+    for (mut transform, target) in targets.iter_mut() {
+        if matches!(target, ScriptedTarget::Moving) {
+            transform.translation.x = transform.translation.x.clamp(-45.0, 45.0);
+            transform.translation.z = transform.translation.z.clamp(-45.0, 45.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(mean_kills: f32) -> GenerationStats {
+        GenerationStats {
+            mean_kills,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn promotes_after_consistent_kills() {
+        let tanks = crate::genetics::BATTLE_SIZE;
+        let mut curriculum = CurriculumState::first_stage();
+        let mut history = vec![stats(0.1)];
+        assert_eq!(curriculum.update(&history, tanks), None);
+
+        // Одно удачное поколение не в счёт: нужно PROMOTION_WINDOW подряд
+        history.extend([stats(0.4), stats(0.4)]);
+        assert_eq!(curriculum.update(&history[..2], tanks), None);
+        assert_eq!(curriculum.update(&history, tanks), None);
+        // 10 танков уничтожают в среднем 3.5 мишени из 5 — этого достаточно
+        history.push(stats(0.35));
+        assert_eq!(curriculum.update(&history, tanks), Some(CurriculumStage::MovingTargets));
+        assert_eq!(curriculum.generations_in_stage, 0);
+        assert!(CurriculumStage::StationaryTargets.kill_threshold(tanks) < 0.5);
+
+        let mut final_stage = CurriculumState::default();
+        assert_eq!(final_stage.update(&history, tanks), None);
+        assert_eq!(final_stage.stage, CurriculumStage::SelfPlay);
+    }
+
+    #[test]
+    fn moving_targets_stay_on_the_map() {
+        use bevy::ecs::system::RunSystemOnce;
+
+        let mut world = World::new();
+        let moving = world.spawn((Transform::from_xyz(47.0, 0.5, -50.0), ScriptedTarget::Moving)).id();
+        let bot = world.spawn((Transform::from_xyz(47.0, 0.5, -50.0), ScriptedTarget::WeakBot)).id();
+        world.run_system_once(confine_moving_targets);
+        assert_eq!(world.get::<Transform>(moving).unwrap().translation, Vec3::new(45.0, 0.5, -45.0));
+        assert_eq!(world.get::<Transform>(bot).unwrap().translation, Vec3::new(47.0, 0.5, -50.0));
+    }
+}
@@ -0,0 +1,220 @@
+use bevy::prelude::*;
+use crate::components::*;
+use crate::checkpoint::{upgrade_genomes, write_atomic, SaveError};
+use crate::genetics::Population;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Файл архива элит (лежит рядом с best_genomes.json)
+pub const ELITES_FILE: &str = "elites.json";
+
+/// Число ячеек по каждому поведенческому измерению
+pub const BINS_PER_DIMENSION: usize = 5;
+
+/// Поведенческие измерения сетки MAP-Elites
+pub const DIMENSIONS: [&str; 3] = ["Агрессивность", "Мобильность", "Точность"];
+
+/// Лучший геном в своей ячейке поведенческой сетки
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Elite {
+    pub cell: [usize; 3],
+    pub descriptor: [f32; 3],
+    pub generation: u32,
+    pub controller: AIController,
+}
+
+/// Архив стратегий MAP-Elites: по одному лучшему геному на ячейку
+#[derive(Resource, Serialize, Deserialize)]
+pub struct EliteArchive {
+    pub bins: usize,
+    pub elites: Vec<Elite>,
+}
+
+impl Default for EliteArchive {
+    fn default() -> Self {
+        Self {
+            bins: BINS_PER_DIMENSION,
+            elites: Vec::new(),
+        }
+    }
+}
+
+impl EliteArchive {
+    /// Загружает архив из файла или создаёт пустой, если файла нет
+    pub fn load_or_default() -> Result<Self, SaveError> {
+        let path = Path::new(ELITES_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let json = std::fs::read_to_string(path).map_err(|e| SaveError::Io(path.to_path_buf(), e))?;
+        let mut value: serde_json::Value =
+            serde_json::from_str(&json).map_err(|e| SaveError::Parse(path.to_path_buf(), e))?;
+        // Элиты, найденные прошлыми версиями сети, переносятся в текущую раскладку
+        upgrade_genomes(&mut value);
+        let archive: EliteArchive =
+            serde_json::from_value(value).map_err(|e| SaveError::Parse(path.to_path_buf(), e))?;
+        println!("Загружен архив элит: {} ячеек заполнено", archive.elites.len());
+        Ok(archive)
+    }
+
+    /// Поведенческий дескриптор в диапазоне 0..1 по каждому измерению:
+    /// доля времени на дистанции боя, пройденный путь относительно максимального для класса
+    /// танка, доля попаданий. None, если танк не успел ничего сделать в бою.
+    pub fn descriptor(ai: &AIController) -> Option<[f32; 3]> {
+        if ai.survival_time <= 0.0 {
+            return None;
+        }
+        // В старых сохранениях максимального пути нет: считаем по среднему танку
+        let max_distance = if ai.behavior.max_distance > 0.0 {
+            ai.behavior.max_distance
+        } else {
+            Tank::default().speed * ai.survival_time
+        };
+        let aggressiveness = ai.behavior.engaged_time / ai.survival_time;
+        let mobility = ai.behavior.distance_travelled / max_distance;
+        let accuracy = if ai.behavior.shots_fired > 0 {
+            ai.behavior.hits as f32 / ai.behavior.shots_fired as f32
+        } else {
+            0.0
+        };
+        Some([
+            aggressiveness.clamp(0.0, 1.0),
+            mobility.clamp(0.0, 1.0),
+            accuracy.clamp(0.0, 1.0),
+        ])
+    }
+
+    /// Ячейка сетки для дескриптора
+    pub fn cell(&self, descriptor: [f32; 3]) -> [usize; 3] {
+        descriptor.map(|d| ((d * self.bins as f32) as usize).min(self.bins - 1))
+    }
+
+    /// Добавляет геном, если его ячейка пуста или он лучше текущей элиты.
+    /// Возвращает true, если архив изменился.
+    pub fn insert(&mut self, ai: &AIController, generation: u32) -> bool {
+        let Some(descriptor) = Self::descriptor(ai) else {
+            return false;
+        };
+        let cell = self.cell(descriptor);
+        let elite = Elite {
+            cell,
+            descriptor,
+            generation,
+            controller: ai.clone(),
+        };
+
+        match self.elites.iter_mut().find(|e| e.cell == cell) {
+            Some(existing) if existing.controller.fitness >= ai.fitness => false,
+            Some(existing) => {
+                *existing = elite;
+                true
+            }
+            None => {
+                self.elites.push(elite);
+                true
+            }
+        }
+    }
+
+    /// Добавляет в архив всю оценённую популяцию
+    pub fn insert_population(&mut self, population: &Population) -> usize {
+        population
+            .genomes
+            .iter()
+            .filter(|ai| self.insert(ai, population.generation))
+            .count()
+    }
+
+    /// Доля заполненных ячеек
+    pub fn coverage(&self) -> f32 {
+        self.elites.len() as f32 / self.bins.pow(DIMENSIONS.len() as u32) as f32
+    }
+
+    /// Атомарное сохранение архива в файл
+    pub fn save(&self) -> Result<(), SaveError> {
+        let path = Path::new(ELITES_FILE);
+        let json = serde_json::to_string_pretty(self).map_err(|e| SaveError::Parse(path.to_path_buf(), e))?;
+        write_atomic(path, &json).map_err(|e| SaveError::Io(path.to_path_buf(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::NetworkSpec;
+
+    fn controller(fitness: f32, engaged: f32, distance: f32, shots: u32, hits: u32) -> AIController {
+        let mut ai = AIController::with_genome(vec![0.0; NetworkSpec::CURRENT.genome_len()]);
+        ai.fitness = fitness;
+        ai.survival_time = 100.0;
+        ai.behavior = BehaviorStats {
+            shots_fired: shots,
+            hits,
+            distance_travelled: distance,
+            engaged_time: engaged,
+            ..default()
+        };
+        ai
+    }
+
+    #[test]
+    fn keeps_best_genome_per_cell() {
+        let mut archive = EliteArchive::default();
+        assert!(archive.insert(&controller(10.0, 50.0, 100.0, 10, 5), 0));
+        assert!(!archive.insert(&controller(5.0, 50.0, 100.0, 10, 5), 1));
+        assert!(archive.insert(&controller(20.0, 50.0, 100.0, 10, 5), 2));
+        assert_eq!(archive.elites.len(), 1);
+        assert_eq!(archive.elites[0].generation, 2);
+
+        assert!(archive.insert(&controller(1.0, 0.0, 0.0, 0, 0), 3));
+        assert_eq!(archive.elites.len(), 2);
+    }
+
+    #[test]
+    fn descriptor_is_normalized() {
+        let ai = controller(0.0, 200.0, 1.0e6, 4, 10);
+        let descriptor = EliteArchive::descriptor(&ai).unwrap();
+        assert!(descriptor.iter().all(|d| (0.0..=1.0).contains(d)));
+        assert_eq!(EliteArchive::default().cell(descriptor), [4, 4, 4]);
+    }
+
+    #[test]
+    fn mobility_is_relative_to_the_class_speed() {
+        use crate::classes::{ClassDef, TankClass};
+        use crate::controller::{apply_tank_actions, TankAction};
+        use crate::weapons::Weapons;
+        use bevy::ecs::system::RunSystemOnce;
+        use bevy::time::Fixed;
+
+        let mut world = World::new();
+        let mut time = Time::<Fixed>::default();
+        time.advance_by(std::time::Duration::from_millis(20));
+        world.insert_resource(time);
+        world.insert_resource(crate::Headless(true));
+        world.insert_resource(Weapons::default());
+        // Лёгкий и тяжёлый танк едут на полном газу по открытому полю
+        let tanks = [(TankClass::Light, -50.0), (TankClass::Heavy, 50.0)].map(|(class, x)| {
+            let def = ClassDef::builtin(class);
+            world
+                .spawn((
+                    Transform::from_xyz(x, 0.5, 0.0),
+                    Tank::of_class(&def, 0),
+                    TeamColor(Color::WHITE),
+                    FireCooldown::from_seconds(def.cooldown),
+                    TankAction { throttle: 1.0, ..default() },
+                    AIController::with_genome(Vec::new()),
+                ))
+                .id()
+        });
+        for _ in 0..50 {
+            world.run_system_once(apply_tank_actions);
+        }
+
+        for tank in tanks {
+            let mut ai = world.get::<AIController>(tank).unwrap().clone();
+            ai.survival_time = 1.0;
+            let mobility = EliteArchive::descriptor(&ai).unwrap()[1];
+            assert!((mobility - 1.0).abs() < 1e-3, "мобильность {}", mobility);
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Чем обучать популяцию
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Trainer {
+    /// Генетический алгоритм: отбор, скрещивание, мутация
+    #[default]
+    Genetic,
+    /// OpenAI-ES: один вектор весов и оценка градиента по парам возмущений
+    Es,
+}
+
+/// Параметры стратегии эволюции
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EsSettings {
+    /// Масштаб гауссова шума возмущений
+    pub sigma: f32,
+    pub learning_rate: f32,
+    /// L2-штраф, тянущий веса к нулю
+    pub weight_decay: f32,
+    pub beta1: f32,
+    pub beta2: f32,
+}
+
+impl Default for EsSettings {
+    fn default() -> Self {
+        Self {
+            sigma: 0.1,
+            learning_rate: 0.03,
+            weight_decay: 0.005,
+            beta1: 0.9,
+            beta2: 0.999,
+        }
+    }
+}
+
+/// Пара противоположных возмущений среднего: геномы θ + σε и θ − σε
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NoisePair {
+    pub noise: Vec<f32>,
+    /// Номера геномов популяции с плюсом и минусом
+    pub ids: (u64, u64),
+}
+
+/// Состояние ES, сохраняемое вместе с популяцией
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct EsState {
+    /// Текущий вектор весов θ
+    pub mean: Vec<f32>,
+    /// Возмущения, которые оцениваются в текущем поколении
+    pub pairs: Vec<NoisePair>,
+    /// Номер генома популяции с самим θ: он воюет наравне с возмущениями, но в градиент
+    /// не входит, а его фитнес — оценка того, что обучено
+    #[serde(default)]
+    pub mean_id: u64,
+    /// Моменты Adam
+    pub adam_m: Vec<f32>,
+    pub adam_v: Vec<f32>,
+    pub step: u32,
+}
+
+impl EsState {
+    pub fn new(mean: Vec<f32>) -> Self {
+        let len = mean.len();
+        Self {
+            mean,
+            pairs: Vec::new(),
+            mean_id: 0,
+            adam_m: vec![0.0; len],
+            adam_v: vec![0.0; len],
+            step: 0,
+        }
+    }
+
+    /// Новые пары возмущений; возвращает геномы θ + σε, θ − σε для каждой пары подряд
+    pub fn sample(&mut self, pairs: usize, sigma: f32, rng: &mut impl Rng) -> Vec<Vec<f32>> {
+        self.pairs = (0..pairs)
+            .map(|_| NoisePair {
+                noise: (0..self.mean.len()).map(|_| gaussian(rng)).collect(),
+                ids: (0, 0),
+            })
+            .collect();
+        self.pairs
+            .iter()
+            .flat_map(|pair| {
+                let plus = self.mean.iter().zip(&pair.noise).map(|(m, e)| m + sigma * e).collect();
+                let minus = self.mean.iter().zip(&pair.noise).map(|(m, e)| m - sigma * e).collect();
+                [plus, minus]
+            })
+            .collect()
+    }
+
+    /// Оценка градиента фитнеса по θ: центрированные ранги вместо сырого фитнеса,
+    /// чтобы масштаб наград и выбросы не влияли на шаг
+    pub fn gradient(&self, fitness: &[(f32, f32)], sigma: f32) -> Vec<f32> {
+        let flat: Vec<f32> = fitness.iter().flat_map(|&(plus, minus)| [plus, minus]).collect();
+        let ranks = centered_ranks(&flat);
+        let mut gradient = vec![0.0; self.mean.len()];
+        for (i, pair) in self.pairs.iter().enumerate() {
+            let weight = ranks[2 * i] - ranks[2 * i + 1];
+            for (g, e) in gradient.iter_mut().zip(&pair.noise) {
+                *g += weight * e;
+            }
+        }
+        let scale = 1.0 / (flat.len().max(1) as f32 * sigma);
+        gradient.iter_mut().for_each(|g| *g *= scale);
+        gradient
+    }
+
+    /// Шаг Adam вверх по градиенту (мы максимизируем фитнес)
+    pub fn adam_step(&mut self, gradient: &[f32], settings: &EsSettings) {
+        self.step += 1;
+        let t = self.step as i32;
+        let correction1 = 1.0 - settings.beta1.powi(t);
+        let correction2 = 1.0 - settings.beta2.powi(t);
+        let moments = self.adam_m.iter_mut().zip(self.adam_v.iter_mut());
+        for ((theta, &gradient), (m, v)) in self.mean.iter_mut().zip(gradient).zip(moments) {
+            let g = gradient - settings.weight_decay * *theta;
+            *m = settings.beta1 * *m + (1.0 - settings.beta1) * g;
+            *v = settings.beta2 * *v + (1.0 - settings.beta2) * g * g;
+            let m_hat = *m / correction1;
+            let v_hat = *v / correction2;
+            *theta += settings.learning_rate * m_hat / (v_hat.sqrt() + 1e-8);
+        }
+    }
+}
+
+/// Ранги, приведённые к [-0.5, 0.5]
+pub fn centered_ranks(values: &[f32]) -> Vec<f32> {
+    let n = values.len();
+    if n < 2 {
+        return vec![0.0; n];
+    }
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+    let mut ranks = vec![0.0; n];
+    for (rank, &index) in order.iter().enumerate() {
+        ranks[index] = rank as f32 / (n - 1) as f32 - 0.5;
+    }
+    ranks
+}
+
+/// Стандартное нормальное число (преобразование Бокса — Мюллера)
+fn gaussian(rng: &mut impl Rng) -> f32 {
+    let u1: f32 = rng.gen_range(f32::EPSILON..1.0);
+    let u2: f32 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn climbs_a_quadratic() {
+        // Фитнес −|θ − цель|²: ES должна подвести θ к цели
+        let target = [0.5, -0.3, 0.8, 0.0];
+        let fitness = |genome: &[f32]| -genome.iter().zip(&target).map(|(g, t)| (g - t).powi(2)).sum::<f32>();
+        let settings = EsSettings {
+            weight_decay: 0.0,
+            ..EsSettings::default()
+        };
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        let mut es = EsState::new(vec![0.0; 4]);
+        let start = fitness(&es.mean);
+        for _ in 0..200 {
+            let genomes = es.sample(10, settings.sigma, &mut rng);
+            let scores: Vec<(f32, f32)> = genomes.chunks(2).map(|p| (fitness(&p[0]), fitness(&p[1]))).collect();
+            let gradient = es.gradient(&scores, settings.sigma);
+            es.adam_step(&gradient, &settings);
+        }
+        assert!(fitness(&es.mean) > start * 0.05, "θ = {:?}", es.mean);
+        assert_eq!(centered_ranks(&[3.0, 1.0, 2.0]), vec![0.5, -0.5, 0.0]);
+    }
+}
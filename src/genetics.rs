@@ -1,7 +1,166 @@
 use bevy::prelude::*;
 use crate::components::*;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::checkpoint::*;
+use crate::curriculum::CurriculumState;
+use crate::es::{EsSettings, EsState, Trainer};
+use crate::metrics::{GenerationStats, MetricsLog};
+use crate::rating::{self, Rating};
 use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// По какому показателю сортировать и отбирать геномы
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum SelectionMetric {
+    /// Фитнес последнего боя
+    #[default]
+    Fitness,
+    /// Рейтинг Эло, накопленный за все бои
+    Rating,
+}
+
+/// Реакция на застой: лучший результат долго не растёт
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum StagnationResponse {
+    /// Ничего не делать
+    #[default]
+    Off,
+    /// Несколько поколений мутировать чаще и сильнее
+    Hypermutation,
+    /// Заменить часть потомков случайными геномами
+    RandomImmigrants,
+    /// Оставить только элиту, остальных создать заново
+    PartialRestart,
+}
+
+/// Настройки обнаружения застоя и реакции на него
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StagnationSettings {
+    pub response: StagnationResponse,
+    /// Сколько поколений без улучшения считается застоем
+    pub window: u32,
+    /// Минимальный прирост лучшего результата, который считается улучшением
+    pub min_improvement: f32,
+    /// Во сколько раз растут вероятность и сила мутации при гипермутации
+    pub hypermutation_factor: f32,
+    /// Сколько поколений длится гипермутация
+    pub hypermutation_generations: u32,
+    /// Доля популяции, заменяемая случайными иммигрантами
+    pub immigrant_fraction: f32,
+}
+
+impl Default for StagnationSettings {
+    fn default() -> Self {
+        Self {
+            response: StagnationResponse::default(),
+            window: 15,
+            min_improvement: 1.0,
+            hypermutation_factor: 3.0,
+            hypermutation_generations: 3,
+            immigrant_fraction: 0.25,
+        }
+    }
+}
+
+/// Счётчики застоя, сохраняемые вместе с популяцией
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct StagnationState {
+    /// Лучший результат за всё время (по текущему показателю отбора)
+    pub best_score: Option<f32>,
+    pub generations_without_improvement: u32,
+    /// Сколько поколений гипермутации осталось
+    pub hypermutation_left: u32,
+    /// Сколько раз срабатывала реакция на застой
+    pub interventions: u32,
+}
+
+/// Как свести фитнес нескольких боёв в один
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum FitnessAggregate {
+    #[default]
+    Mean,
+    Median,
+    /// Худший из боёв: награждает стабильность, а не удачу
+    Worst,
+}
+
+impl FitnessAggregate {
+    pub fn apply(&self, samples: &[f32]) -> f32 {
+        if samples.is_empty() {
+            return 0.0;
+        }
+        match self {
+            FitnessAggregate::Mean => samples.iter().sum::<f32>() / samples.len() as f32,
+            FitnessAggregate::Median => {
+                let mut sorted = samples.to_vec();
+                sorted.sort_by(|a, b| a.total_cmp(b));
+                let n = sorted.len();
+                (sorted[(n - 1) / 2] + sorted[n / 2]) / 2.0
+            }
+            FitnessAggregate::Worst => samples.iter().copied().fold(f32::MAX, f32::min),
+        }
+    }
+}
+
+/// Политика оценки: сколько боёв с разными картами, позициями и соперниками проводит каждый геном
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EvaluationSettings {
+    /// Число оценок K каждого генома за поколение
+    pub evaluations: u32,
+    pub aggregate: FitnessAggregate,
+}
+
+impl Default for EvaluationSettings {
+    fn default() -> Self {
+        Self {
+            evaluations: 1,
+            aggregate: FitnessAggregate::Mean,
+        }
+    }
+}
+
+/// Танков популяции в одном тренировочном бою
+pub const BATTLE_SIZE: usize = 10;
+
+/// Настройки эволюции, сохраняемые вместе с популяцией
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct EvolutionSettings {
+    #[serde(default)]
+    pub selection: SelectionMetric,
+    #[serde(default)]
+    pub stagnation: StagnationSettings,
+    #[serde(default)]
+    pub evaluation: EvaluationSettings,
+    #[serde(default)]
+    pub trainer: Trainer,
+    #[serde(default)]
+    pub es: EsSettings,
+}
+
+/// Источник случайности эволюции: отбор, скрещивание, мутация, иммигранты и возмущения
+/// ES. Замер скорости задаёт зерно, чтобы прогоны повторялись целиком
+#[derive(Resource)]
+pub struct EvolutionRng(pub StdRng);
+
+impl EvolutionRng {
+    pub fn seeded(seed: u64) -> Self {
+        Self(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl Default for EvolutionRng {
+    fn default() -> Self {
+        Self(StdRng::from_entropy())
+    }
+}
+
+/// Лучший геном поколения, сохранённый для боёв между поколениями
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GenerationChampion {
+    pub generation: u32,
+    pub controller: AIController,
+}
 
 #[derive(Resource, Serialize, Deserialize)]
 pub struct Population {
@@ -9,91 +168,375 @@ pub struct Population {
     pub population_size: usize,
     pub genomes: Vec<AIController>,
     pub best_genome: Option<AIController>,
+    /// Чемпионы всех пройденных поколений
+    #[serde(default)]
+    pub champions: Vec<GenerationChampion>,
+    #[serde(default)]
+    pub settings: EvolutionSettings,
+    /// Статистика пройденных поколений
+    #[serde(default)]
+    pub history: Vec<GenerationStats>,
+    /// Последний выданный номер генома
+    #[serde(default)]
+    pub next_id: u64,
+    #[serde(default)]
+    pub stagnation: StagnationState,
+    /// Сколько тренировочных боёв текущего поколения уже сыграно
+    #[serde(default)]
+    pub evaluation_battle: u32,
+    /// Этап обучения; у старых сохранений — сразу бои популяции
+    #[serde(default)]
+    pub curriculum: CurriculumState,
+    /// Состояние OpenAI-ES, если популяция обучается им
+    #[serde(default)]
+    pub es: Option<EsState>,
 }
 
 impl Population {
-    pub fn new(size: usize) -> Self {
-        if let Some(loaded) = load_genomes() {
-            println!("Загружены лучшие геномы из предыдущего запуска, поколение {}", loaded.generation);
-            loaded
-        } else {
+    /// Загружает популяцию из указанного чекпоинта или best_genomes.json.
+    /// Новая популяция создаётся только если сохранения нет вовсе:
+    /// нечитаемое сохранение — ошибка, а не повод молча начать заново.
+    pub fn load_or_new(size: usize, resume: Option<&Path>) -> Result<Self, SaveError> {
+        let path = resume.unwrap_or(Path::new(BEST_GENOMES_FILE));
+        if resume.is_none() && !path.exists() {
             println!("Создание новой популяции случайных геномов");
-            Self::new_fresh(size)
+            return Ok(Self::new_fresh(size));
         }
+        let mut loaded = load_population(path)?;
+        loaded.assign_ids();
+        println!("Загружена популяция из {}, поколение {}", path.display(), loaded.generation);
+        Ok(loaded)
     }
     
     pub fn new_fresh(size: usize) -> Self {
-        let genomes = (0..size)
-            .map(|_| AIController::new_random())
-            .collect();
-        
-        Self {
+        Self::with_genomes((0..size).map(|_| AIController::new_random()).collect())
+    }
+
+    /// Новая популяция, одинаковая при одном и том же зерне
+    pub fn new_seeded(size: usize, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        Self::with_genomes((0..size).map(|_| AIController::random_with(&mut rng)).collect())
+    }
+
+    fn with_genomes(genomes: Vec<AIController>) -> Self {
+        let size = genomes.len();
+        let mut population = Self {
             generation: 0,
             population_size: size,
             genomes,
             best_genome: None,
+            champions: Vec::new(),
+            settings: EvolutionSettings::default(),
+            history: Vec::new(),
+            next_id: 0,
+            stagnation: StagnationState::default(),
+            evaluation_battle: 0,
+            curriculum: CurriculumState::first_stage(),
+            es: None,
+        };
+        population.assign_ids();
+        population
+    }
+
+    /// Выдаёт номера геномам, у которых их ещё нет (новые дети, старые сохранения)
+    pub fn assign_ids(&mut self) {
+        for genome in self.genomes.iter_mut().filter(|g| g.id == 0) {
+            self.next_id += 1;
+            genome.id = self.next_id;
         }
     }
     
-    /// Сортирует популяцию по приспособленности
+    /// Продолжает нумерацию геномов после номеров, уже занятых в базе истории прошлыми
+    /// запусками. Популяция, ни одно поколение которой ещё не записано (новая или
+    /// --fresh), перенумеровывается целиком; у продолженной новые номера идут после taken
+    pub fn continue_ids_after(&mut self, taken: u64) {
+        if !self.history.is_empty() {
+            self.next_id = self.next_id.max(taken);
+            return;
+        }
+        for genome in &mut self.genomes {
+            genome.id += taken;
+            for parent in &mut genome.parents {
+                *parent += taken;
+            }
+        }
+        if let Some(es) = self.es.as_mut() {
+            es.mean_id += taken;
+            for pair in &mut es.pairs {
+                pair.ids = (pair.ids.0 + taken, pair.ids.1 + taken);
+            }
+        }
+        self.next_id += taken;
+    }
+
+    /// На сколько боёв делится популяция, чтобы каждый геном сыграл один раз
+    fn battles_per_round(&self) -> u32 {
+        self.genomes.len().div_ceil(BATTLE_SIZE).max(1) as u32
+    }
+
+    /// Танков популяции в одном тренировочном бою (см. lineup)
+    pub fn tanks_per_battle(&self) -> usize {
+        self.genomes.len().div_ceil(self.battles_per_round() as usize).max(1)
+    }
+
+    /// Тренировочных боёв в поколении: K оценок каждого генома
+    pub fn battles_per_generation(&self) -> u32 {
+        self.battles_per_round() * self.settings.evaluation.evaluations.max(1)
+    }
+
+    /// Все оценки поколения проведены, можно эволюционировать
+    pub fn evaluation_complete(&self) -> bool {
+        self.evaluation_battle >= self.battles_per_generation()
+    }
+
+    /// Зерно текущего боя: от него зависят карта и позиции танков
+    pub fn battle_seed(&self) -> u64 {
+        ((self.generation as u64) << 32) | self.evaluation_battle as u64
+    }
+
+    /// Состав текущего тренировочного боя: (индекс генома, команда).
+    /// В каждой оценке популяция заново перемешивается, так что напарники
+    /// и соперники от боя к бою разные.
+    pub fn lineup(&self) -> Vec<(usize, u32)> {
+        use rand::seq::SliceRandom;
+
+        let per_round = self.battles_per_round();
+        let round = self.evaluation_battle / per_round;
+        let chunk = (self.evaluation_battle % per_round) as usize;
+
+        let mut order: Vec<usize> = (0..self.genomes.len()).collect();
+        let mut rng = StdRng::seed_from_u64(((self.generation as u64) << 32) | round as u64);
+        order.shuffle(&mut rng);
+
+        order
+            .chunks(self.tanks_per_battle())
+            .nth(chunk)
+            .unwrap_or_default()
+            .iter()
+            .enumerate()
+            .map(|(i, &index)| {
+                // Против мишеней все геномы играют за синих
+                let team = if self.curriculum.stage.uses_targets() { 0 } else { (i % 2) as u32 };
+                (index, team)
+            })
+            .collect()
+    }
+
+    /// Показатель отбора генома: фитнес или рейтинг, в зависимости от настроек
+    pub fn score(&self, ai: &AIController) -> f32 {
+        match self.settings.selection {
+            SelectionMetric::Fitness => ai.fitness,
+            SelectionMetric::Rating => ai.rating.elo,
+        }
+    }
+
+    /// Сортирует популяцию по приспособленности (или рейтингу, см. EvolutionSettings)
     pub fn sort_by_fitness(&mut self) {
-        self.genomes.sort_by(|a, b| {
-            b.fitness.partial_cmp(&a.fitness).unwrap()
+        let metric = self.settings.selection;
+        self.genomes.sort_by(|a, b| match metric {
+            SelectionMetric::Fitness => b.fitness.partial_cmp(&a.fitness).unwrap(),
+            SelectionMetric::Rating => b.rating.elo.partial_cmp(&a.rating.elo).unwrap(),
         });
     }
     
     /// Выполняет селекцию, скрещивание и мутацию
-    pub fn evolve(&mut self) {
+    pub fn evolve(&mut self, rng: &mut impl Rng) {
         self.sort_by_fitness();
         self.best_genome = self.genomes.first().cloned();
+        if let Some(best) = &self.best_genome {
+            self.champions.push(GenerationChampion {
+                generation: self.generation,
+                controller: best.clone(),
+            });
+        }
         
         info!("Поколение {}: Лучший фитнес = {:.2}", 
               self.generation, 
               self.genomes[0].fitness);
+        let stagnating = self.update_stagnation();
+        if stagnating && self.settings.stagnation.response == StagnationResponse::Hypermutation {
+            self.stagnation.hypermutation_left = self.settings.stagnation.hypermutation_generations;
+        }
         
         // Оставляем лучшие 20% (элитизм)
         let elite_count = self.population_size / 5;
         let mut new_genomes = Vec::new();
         
-        // Копируем элиту
+        // Копируем элиту: в новом поколении она оценивается заново наравне со всеми
         for i in 0..elite_count {
-            let mut elite = self.genomes[i].clone();
-            elite.fitness = 0.0;
-            elite.kills = 0;
-            elite.survival_time = 0.0;
+            let mut elite = self.genomes[i].battle_copy();
+            elite.fitness_history.push(self.genomes[i].fitness);
             new_genomes.push(elite);
         }
         
-        // Восстанавливаем лучший геном с правильным фитнесом
-        if let Some(best) = &self.best_genome {
-            new_genomes[0] = best.clone();
-        }
-        
         // Создаем остальных через скрещивание и мутацию
         while new_genomes.len() < self.population_size {
-            let parent1 = self.select_parent();
-            let parent2 = self.select_parent();
+            let parent1 = self.select_parent(rng);
+            let parent2 = self.select_parent(rng);
             
-            let mut child = self.crossover(&parent1, &parent2);
-            self.mutate(&mut child);
+            let mut child = self.crossover(&parent1, &parent2, rng);
+            self.mutate(&mut child, rng);
+            child.origin_generation = self.generation + 1;
+            child.parents = vec![parent1.id, parent2.id];
             
             new_genomes.push(child);
         }
         
+        if stagnating {
+            self.respond_to_stagnation(&mut new_genomes, elite_count, rng);
+        }
+        self.stagnation.hypermutation_left = self.stagnation.hypermutation_left.saturating_sub(1);
+        
         self.genomes = new_genomes;
+        self.assign_ids();
         self.generation += 1;
+        self.evaluation_battle = 0;
     }
     
+    /// Переключает популяцию на ES: среднее — лучший геном, популяция — пары возмущений вокруг него
+    pub fn start_es(&mut self, rng: &mut impl Rng) {
+        if self.es.is_some() {
+            return;
+        }
+        let mean = self
+            .best_genome
+            .as_ref()
+            .or_else(|| self.genomes.first())
+            .map(|g| g.genome.clone())
+            .unwrap_or_else(|| AIController::random_with(rng).genome);
+        self.es = Some(EsState::new(mean));
+        self.resample_es(rng);
+    }
+
+    /// Шаг OpenAI-ES вместо evolve: градиент по фитнесу пар возмущений, шаг Adam
+    /// и новые возмущения. Чемпион поколения — само θ, а best_genome — лучшее θ за всё
+    /// обучение. Реакция на застой к ES не применяется.
+    pub fn es_step(&mut self, rng: &mut impl Rng) {
+        let Some(mut es) = self.es.take() else {
+            self.start_es(rng);
+            return;
+        };
+        self.sort_by_fitness();
+        // В старых чекпоинтах θ в популяции нет: тогда оно выходит чемпионом без оценки
+        let mean = self
+            .genomes
+            .iter()
+            .find(|g| g.id == es.mean_id)
+            .cloned()
+            .unwrap_or_else(|| AIController::with_genome(es.mean.clone()));
+        if self.best_genome.as_ref().is_none_or(|best| self.score(&mean) > self.score(best)) {
+            self.best_genome = Some(mean.clone());
+        }
+        self.champions.push(GenerationChampion {
+            generation: self.generation,
+            controller: mean,
+        });
+
+        let fitness_of = |id: u64| {
+            self.genomes
+                .iter()
+                .find(|g| g.id == id)
+                .map(|g| self.score(g))
+                .unwrap_or(0.0)
+        };
+        let scores: Vec<(f32, f32)> = es.pairs.iter().map(|p| (fitness_of(p.ids.0), fitness_of(p.ids.1))).collect();
+        let gradient = es.gradient(&scores, self.settings.es.sigma);
+        es.adam_step(&gradient, &self.settings.es);
+        info!(
+            "ES, поколение {}: шаг {}, норма градиента {:.4}",
+            self.generation,
+            es.step,
+            gradient.iter().map(|g| g * g).sum::<f32>().sqrt()
+        );
+
+        self.es = Some(es);
+        self.generation += 1;
+        self.resample_es(rng);
+    }
+
+    /// Заменяет популяцию самим средним и новыми парами его возмущений
+    fn resample_es(&mut self, rng: &mut impl Rng) {
+        let Some(mut es) = self.es.take() else {
+            return;
+        };
+        let pairs = (self.population_size.saturating_sub(1) / 2).max(1);
+        let perturbations = es.sample(pairs, self.settings.es.sigma, rng);
+        self.genomes = std::iter::once(es.mean.clone())
+            .chain(perturbations)
+            .map(|genome| AIController {
+                origin_generation: self.generation,
+                ..AIController::with_genome(genome)
+            })
+            .collect();
+        self.assign_ids();
+        es.mean_id = self.genomes[0].id;
+        for (pair, genomes) in es.pairs.iter_mut().zip(self.genomes[1..].chunks(2)) {
+            pair.ids = (genomes[0].id, genomes[1].id);
+        }
+        self.es = Some(es);
+        self.evaluation_battle = 0;
+    }
+
+    /// Обновляет счётчик поколений без улучшения; true — наступил застой и пора вмешаться
+    fn update_stagnation(&mut self) -> bool {
+        let Some(best) = self.genomes.first().map(|g| self.score(g)) else {
+            return false;
+        };
+        let settings = &self.settings.stagnation;
+        let state = &mut self.stagnation;
+        match state.best_score {
+            Some(record) if best < record + settings.min_improvement => {
+                state.generations_without_improvement += 1;
+            }
+            _ => {
+                state.best_score = Some(best);
+                state.generations_without_improvement = 0;
+            }
+        }
+        if settings.response == StagnationResponse::Off
+            || state.generations_without_improvement < settings.window.max(1)
+        {
+            return false;
+        }
+        state.generations_without_improvement = 0;
+        state.interventions += 1;
+        true
+    }
+
+    /// Заменяет часть нового поколения случайными геномами, если так велит реакция на застой
+    /// (элита не трогается; гипермутация включается до создания потомков)
+    fn respond_to_stagnation(&self, new_genomes: &mut [AIController], elite_count: usize, rng: &mut impl Rng) {
+        let settings = &self.settings.stagnation;
+        let replace = match settings.response {
+            StagnationResponse::Off | StagnationResponse::Hypermutation => 0,
+            StagnationResponse::RandomImmigrants => {
+                (self.population_size as f32 * settings.immigrant_fraction).round() as usize
+            }
+            StagnationResponse::PartialRestart => self.population_size,
+        };
+        let replace = replace.min(new_genomes.len().saturating_sub(elite_count));
+        warn!(
+            "Застой: {} поколений без улучшения, реакция {:?}, заменено геномов: {}",
+            settings.window, settings.response, replace
+        );
+
+        let start = new_genomes.len() - replace;
+        for genome in &mut new_genomes[start..] {
+            let mut immigrant = AIController::random_with(rng);
+            immigrant.origin_generation = self.generation + 1;
+            *genome = immigrant;
+        }
+    }
+
     /// Выбор родителя с помощью турнирной селекции
-    fn select_parent(&self) -> AIController {
-        let mut rng = rand::thread_rng();
+    fn select_parent(&self, rng: &mut impl Rng) -> AIController {
         let tournament_size = 3;
         
         let mut best = self.genomes[rng.gen_range(0..self.genomes.len())].clone();
         
         for _ in 1..tournament_size {
             let competitor = &self.genomes[rng.gen_range(0..self.genomes.len())];
-            if competitor.fitness > best.fitness {
+            if self.score(competitor) > self.score(&best) {
                 best = competitor.clone();
             }
         }
@@ -102,8 +545,7 @@ impl Population {
     }
     
     /// Одноточечное скрещивание
-    fn crossover(&self, parent1: &AIController, parent2: &AIController) -> AIController {
-        let mut rng = rand::thread_rng();
+    fn crossover(&self, parent1: &AIController, parent2: &AIController, rng: &mut impl Rng) -> AIController {
         let crossover_point = rng.gen_range(0..parent1.genome.len());
         
         let mut child_genome = Vec::new();
@@ -116,19 +558,18 @@ impl Population {
             }
         }
         
-        AIController {
-            genome: child_genome,
-            fitness: 0.0,
-            kills: 0,
-            survival_time: 0.0,
-        }
+        AIController::with_genome(child_genome)
     }
     
     /// Мутация генома
-    fn mutate(&self, individual: &mut AIController) {
-        let mut rng = rand::thread_rng();
-        let mutation_rate = 0.1;
-        let mutation_strength = 0.3;
+    fn mutate(&self, individual: &mut AIController, rng: &mut impl Rng) {
+        let boost = if self.stagnation.hypermutation_left > 0 {
+            self.settings.stagnation.hypermutation_factor
+        } else {
+            1.0
+        };
+        let mutation_rate = (0.1 * boost).min(1.0);
+        let mutation_strength = 0.3 * boost;
         
         for gene in individual.genome.iter_mut() {
             if rng.gen::<f32>() < mutation_rate {
@@ -138,31 +579,180 @@ impl Population {
         }
     }
     
-    /// Вычисляет фитнес для индивида
+    /// Чемпион указанного поколения
+    pub fn champion_of(&self, generation: u32) -> Option<&AIController> {
+        self.champions
+            .iter()
+            .find(|c| c.generation == generation)
+            .map(|c| &c.controller)
+    }
+
+    /// Добавляет чемпиона в популяцию вместо самого слабого генома. При ES популяция —
+    /// это θ и пары его возмущений, и замена разорвала бы пару, поэтому импорт запрещён
+    /// (готовый геном становится средним через seed_genome)
+    pub fn import_champion(&mut self, mut champion: AIController) -> Result<(), String> {
+        if self.es.is_some() {
+            return Err("при --trainer es чемпиона нельзя добавить в популяцию пар возмущений".to_string());
+        }
+        self.next_id += 1;
+        champion.id = self.next_id;
+        if self.genomes.len() < self.population_size {
+            self.genomes.push(champion);
+            return Ok(());
+        }
+        // Порядок геномов не меняется: по нему составляются оставшиеся бои поколения (lineup)
+        let weakest = (0..self.genomes.len())
+            .min_by(|&a, &b| self.score(&self.genomes[a]).total_cmp(&self.score(&self.genomes[b])));
+        if let Some(weakest) = weakest {
+            self.genomes[weakest] = champion;
+        }
+        Ok(())
+    }
+
+    /// Засевает популяцию готовым геномом (например, обученным на демонстрациях):
+    /// при ES он становится средним, иначе заменяет самый слабый геном
+    pub fn seed_genome(&mut self, genome: Vec<f32>, rng: &mut impl Rng) {
+        if let Some(es) = self.es.as_mut() {
+            *es = EsState::new(genome);
+            self.resample_es(rng);
+            return;
+        }
+        let mut seed = AIController::with_genome(genome);
+        seed.origin_generation = self.generation;
+        // Без ES импорт всегда удаётся
+        let _ = self.import_champion(seed);
+    }
+
+    /// Обновляет рейтинги геномов популяции по итогу боя двух команд
+    pub fn record_battle_outcome(&mut self, teams: [&[&AIController]; 2], score_a: f32) {
+        let find = |ai: &AIController| self.genomes.iter().position(|g| g.id == ai.id);
+        let mut indices = [Vec::new(), Vec::new()];
+        for (team, members) in teams.iter().enumerate() {
+            for ai in members.iter() {
+                if let Some(index) = find(ai) {
+                    if !indices[0].contains(&index) && !indices[1].contains(&index) {
+                        indices[team].push(index);
+                    }
+                }
+            }
+        }
+
+        let mut team_a: Vec<Rating> = indices[0].iter().map(|&i| self.genomes[i].rating).collect();
+        let mut team_b: Vec<Rating> = indices[1].iter().map(|&i| self.genomes[i].rating).collect();
+        rating::update_teams(&mut team_a, &mut team_b, score_a);
+        for (&i, rating) in indices[0].iter().zip(team_a).chain(indices[1].iter().zip(team_b)) {
+            self.genomes[i].rating = rating;
+        }
+    }
+
+    /// Обновляет рейтинги чемпионов двух поколений по итогу боя между ними
+    pub fn record_match_outcome(&mut self, generation_a: u32, generation_b: u32, score_a: f32) {
+        let rating_of = |generation| {
+            self.champions
+                .iter()
+                .find(|c| c.generation == generation)
+                .map(|c| c.controller.rating)
+        };
+        let (Some(rating_a), Some(rating_b)) = (rating_of(generation_a), rating_of(generation_b)) else {
+            return;
+        };
+        let mut team_a = [rating_a];
+        let mut team_b = [rating_b];
+        rating::update_teams(&mut team_a, &mut team_b, score_a);
+        let ([rating_a], [rating_b]) = (team_a, team_b);
+        for champion in self.champions.iter_mut() {
+            if champion.generation == generation_a {
+                champion.controller.rating = rating_a;
+            } else if champion.generation == generation_b {
+                champion.controller.rating = rating_b;
+            }
+        }
+    }
+
+    /// Вычисляет фитнес для индивида по итогу одного боя и сводит его с прошлыми оценками
     #[allow(dead_code)]
     pub fn calculate_fitness(&mut self, _entity: Entity, ai: &AIController) {
         // Фитнес = kills * 100 + survival_time * 2 - deaths * 50
         let fitness = (ai.kills as f32 * 100.0) + (ai.survival_time * 2.0);
+        let aggregate = self.settings.evaluation.aggregate;
         
-        // Обновляем фитнес в популяции
+        // Обновляем фитнес в популяции; убийства, время и поведение суммируются по боям.
+        // Ищем по номеру: у клонов одинаковые геномы, но оценки у каждого свои
         if let Some(genome) = self.genomes.iter_mut()
-            .find(|g| g.genome == ai.genome) {
-            genome.fitness = fitness;
-            genome.kills = ai.kills;
-            genome.survival_time = ai.survival_time;
+            .find(|g| g.id == ai.id) {
+            genome.fitness_samples.push(fitness);
+            genome.fitness = aggregate.apply(&genome.fitness_samples);
+            genome.kills += ai.kills;
+            genome.survival_time += ai.survival_time;
+            genome.behavior.accumulate(&ai.behavior);
         }
     }
 }
 
 /// Система эволюции популяции
+#[allow(clippy::too_many_arguments)]
 pub fn evolve_population(
     mut population: ResMut<Population>,
+    mut archive: ResMut<crate::elites::EliteArchive>,
+    checkpoints: Res<CheckpointConfig>,
+    mut metrics: ResMut<MetricsLog>,
+    history: Res<crate::history::HistoryDb>,
+    bench: Option<Res<crate::throughput::ThroughputBench>>,
+    mut rng: ResMut<EvolutionRng>,
     mut next_state: ResMut<NextState<crate::GameState>>,
 ) {
-    population.evolve();
+    // Пока не все геномы прошли K оценок — следующий бой того же поколения
+    if !population.evaluation_complete() {
+        next_state.set(crate::GameState::Battle);
+        return;
+    }
+
+    // Статистика поколения, пока фитнес ещё не сброшен эволюцией
+    let stats = GenerationStats::from_population(&population, metrics.take_generation_time());
+    if let Err(e) = metrics.record_generation(&stats) {
+        error!("Не удалось записать метрики поколения: {}", e);
+    }
+    if let Err(e) = history.record_generation(&population, &stats) {
+        error!("Не удалось записать поколение в базу истории: {}", e);
+    }
+    info!(
+        "Разнообразие: расстояние {:.3}, энтропия генов {:.3}, поведение {:.3}",
+        stats.diversity, stats.gene_entropy, stats.behavior_diversity
+    );
+    population.history.push(stats);
+
+    let tanks = population.tanks_per_battle();
+    let Population { curriculum, history, .. } = &mut *population;
+    if let Some(stage) = curriculum.update(history, tanks) {
+        println!("Поколение {}: переход на этап обучения «{}»", population.generation, stage.name());
+        // Фитнес на новом этапе в другом масштабе: застой считаем заново
+        population.stagnation = StagnationState::default();
+    }
+
+    // Пополняем архив стратегий, пока фитнес и поведение текущего поколения известны
+    let added = archive.insert_population(&population);
+    if added > 0 {
+        info!("Архив элит: обновлено ячеек {}, заполнено {:.0}%", added, archive.coverage() * 100.0);
+    }
+
+    match population.settings.trainer {
+        Trainer::Genetic => population.evolve(&mut rng.0),
+        Trainer::Es => population.es_step(&mut rng.0),
+    }
     
-    // Сохраняем лучшие геномы
-    save_best_genomes(&population);
+    // Сохраняем лучшие геномы, чекпоинт поколения и архив элит. Замер скорости
+    // не затирает настоящие сохранения: его чекпоинты уходят в свой каталог
+    if bench.is_none() {
+        if let Err(e) = save_population(Path::new(BEST_GENOMES_FILE), &population) {
+            error!("Не удалось сохранить популяцию: {}", e);
+        }
+        if let Err(e) = archive.save() {
+            error!("Не удалось сохранить архив элит: {}", e);
+        }
+    }
+    if let Err(e) = save_checkpoint(&checkpoints, &population) {
+        error!("Не удалось сохранить чекпоинт: {}", e);
+    }
     
     info!("Эволюция завершена, поколение {}", population.generation);
     
@@ -170,31 +760,122 @@ pub fn evolve_population(
     next_state.set(crate::GameState::Battle);
 }
 
-/// Сохранение лучших геномов в файл
-fn save_best_genomes(population: &Population) {
-    if let Ok(json) = serde_json::to_string_pretty(population) {
-        std::fs::write("best_genomes.json", json).ok();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_genome_is_evaluated_k_times() {
+        let mut population = Population::new_fresh(20);
+        population.settings.evaluation.evaluations = 3;
+        let mut appearances = [0; 20];
+        let mut lineups = Vec::new();
+        while !population.evaluation_complete() {
+            let lineup = population.lineup();
+            assert_eq!(lineup.len(), BATTLE_SIZE);
+            for &(index, _) in &lineup {
+                appearances[index] += 1;
+            }
+            lineups.push(lineup);
+            population.evaluation_battle += 1;
+        }
+        assert_eq!(lineups.len(), 6);
+        assert!(appearances.iter().all(|&n| n == 3));
+        assert_ne!(lineups[0], lineups[2]);
+
+        // Два клона одного генома получают каждый свою оценку
+        population.genomes[1] = AIController { id: population.genomes[1].id, ..population.genomes[0].battle_copy() };
+        let clone = AIController { kills: 1, ..population.genomes[1].battle_copy() };
+        population.calculate_fitness(Entity::PLACEHOLDER, &clone);
+        assert_eq!((population.genomes[0].fitness_samples.len(), population.genomes[1].fitness), (0, 100.0));
+
+        assert_eq!(FitnessAggregate::Median.apply(&[1.0, 9.0, 3.0]), 3.0);
+        assert_eq!(FitnessAggregate::Worst.apply(&[1.0, 9.0, 3.0]), 1.0);
+    }
+
+    #[test]
+    fn imported_champion_takes_the_weakest_slot() {
+        let mut population = Population::new_fresh(8);
+        for (i, genome) in population.genomes.iter_mut().enumerate() {
+            genome.fitness = if i == 5 { -10.0 } else { i as f32 };
+        }
+        population.evaluation_battle = 1;
+        let roster = population.lineup();
+        let ids: Vec<u64> = population.genomes.iter().map(|g| g.id).collect();
+
+        population.import_champion(AIController::with_genome(vec![1.0; 4])).unwrap();
+        // Остальные геномы на своих местах: состав оставшихся боёв поколения тот же
+        assert_eq!(population.lineup(), roster);
+        assert_eq!(population.genomes[5].genome, vec![1.0; 4]);
+        assert_eq!(population.genomes[5].id, population.next_id);
+        for (i, genome) in population.genomes.iter().enumerate().filter(|&(i, _)| i != 5) {
+            assert_eq!(genome.id, ids[i]);
+        }
     }
-}
 
-/// Загрузка геномов из файла
-pub fn load_genomes() -> Option<Population> {
-    match std::fs::read_to_string("best_genomes.json") {
-        Ok(json) => {
-            match serde_json::from_str::<Population>(&json) {
-                Ok(pop) => {
-                    println!("Successfully loaded population with generation {}", pop.generation);
-                    Some(pop)
-                },
-                Err(e) => {
-                    println!("Failed to parse JSON: {}", e);
-                    None
+    #[test]
+    fn immigrants_arrive_after_stagnation_window() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut population = Population::new_fresh(10);
+        population.settings.stagnation.response = StagnationResponse::RandomImmigrants;
+        population.settings.stagnation.window = 2;
+        population.settings.stagnation.immigrant_fraction = 0.5;
+
+        for _ in 0..3 {
+            population.evolve(&mut rng);
+        }
+        assert_eq!(population.stagnation.interventions, 1);
+        // У элиты нет родителей, но есть история фитнеса; у иммигрантов нет ни того, ни другого
+        let elites = population.genomes.iter().filter(|g| !g.fitness_history.is_empty()).count();
+        let immigrants = population
+            .genomes
+            .iter()
+            .filter(|g| g.parents.is_empty() && g.fitness_history.is_empty())
+            .count();
+        assert_eq!((elites, immigrants), (2, 5));
+    }
+
+    #[test]
+    fn seeded_evolution_repeats_exactly() {
+        let run = || {
+            let mut rng = EvolutionRng::seeded(7);
+            let mut population = Population::new_seeded(10, 7);
+            for generation in 0..3 {
+                for (i, genome) in population.genomes.iter_mut().enumerate() {
+                    genome.fitness = ((i + generation) % 4) as f32;
                 }
+                population.evolve(&mut rng.0);
             }
-        },
-        Err(e) => {
-            println!("Failed to read best_genomes.json: {}", e);
-            None
-        }
+            population.genomes.into_iter().map(|g| g.genome).collect::<Vec<_>>()
+        };
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn es_exports_the_best_mean_and_keeps_pairs_intact() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut population = Population::new_fresh(20);
+        population.start_es(&mut rng);
+        assert_eq!(population.genomes.len(), 19);
+        let mean_id = population.es.as_ref().unwrap().mean_id;
+        assert_eq!(population.genomes[0].genome, population.es.as_ref().unwrap().mean);
+
+        // Возмущение выиграло поколение, но чемпион и лучший геном — само θ
+        population.genomes[0].fitness = 50.0;
+        population.genomes[1].fitness = 200.0;
+        population.es_step(&mut rng);
+        assert_eq!(population.champions[0].controller.id, mean_id);
+        assert_eq!(population.best_genome.as_ref().map(|g| g.fitness), Some(50.0));
+
+        // Более слабое θ следующего поколения не вытесняет лучшее
+        population.genomes[0].fitness = 10.0;
+        population.es_step(&mut rng);
+        assert_eq!(population.champions.len(), 2);
+        assert_eq!(population.best_genome.as_ref().map(|g| g.id), Some(mean_id));
+
+        // Импорт не трогает пары возмущений
+        let before: Vec<u64> = population.genomes.iter().map(|g| g.id).collect();
+        assert!(population.import_champion(AIController::new_random()).is_err());
+        assert_eq!(population.genomes.iter().map(|g| g.id).collect::<Vec<_>>(), before);
     }
 }
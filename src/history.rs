@@ -0,0 +1,397 @@
+use bevy::prelude::*;
+use crate::genetics::Population;
+use crate::metrics::{BattleSummary, GenerationStats};
+use rusqlite::{params, Connection};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Файл базы истории в каталоге запуска
+pub const HISTORY_DB_FILE: &str = "history.sqlite";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS generations (
+    generation     INTEGER PRIMARY KEY,
+    best_fitness   REAL NOT NULL,
+    mean_fitness   REAL NOT NULL,
+    median_fitness REAL NOT NULL,
+    worst_fitness  REAL NOT NULL,
+    total_kills    INTEGER NOT NULL,
+    mean_kills     REAL NOT NULL,
+    mean_survival  REAL NOT NULL,
+    diversity      REAL NOT NULL,
+    wall_time      REAL NOT NULL
+);
+CREATE TABLE IF NOT EXISTS genomes (
+    generation         INTEGER NOT NULL,
+    id                 INTEGER NOT NULL,
+    origin_generation  INTEGER NOT NULL,
+    fitness            REAL NOT NULL,
+    kills              INTEGER NOT NULL,
+    survival_time      REAL NOT NULL,
+    shots_fired        INTEGER NOT NULL,
+    hits               INTEGER NOT NULL,
+    distance_travelled REAL NOT NULL,
+    engaged_time       REAL NOT NULL,
+    elo                REAL NOT NULL,
+    genome             TEXT NOT NULL,
+    PRIMARY KEY (generation, id)
+);
+CREATE TABLE IF NOT EXISTS lineage (
+    child_id  INTEGER NOT NULL,
+    parent_id INTEGER NOT NULL,
+    PRIMARY KEY (child_id, parent_id)
+);
+CREATE TABLE IF NOT EXISTS battles (
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    generation INTEGER NOT NULL,
+    kind       TEXT NOT NULL,
+    ticks      INTEGER NOT NULL,
+    duration   REAL NOT NULL,
+    alive_0    INTEGER NOT NULL,
+    alive_1    INTEGER NOT NULL,
+    health_0   REAL NOT NULL,
+    health_1   REAL NOT NULL,
+    deaths_0   INTEGER NOT NULL,
+    deaths_1   INTEGER NOT NULL,
+    score_a    REAL NOT NULL
+);
+";
+
+/// Изменения схемы после первой версии; PRAGMA user_version хранит число применённых
+const MIGRATIONS: &[&str] = &["
+ALTER TABLE generations ADD COLUMN gene_entropy REAL NOT NULL DEFAULT 0;
+ALTER TABLE generations ADD COLUMN behavior_diversity REAL NOT NULL DEFAULT 0;
+"];
+
+/// Запрос к базе истории из командной строки
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HistoryQuery {
+    /// Лучший геном каждого поколения
+    Best,
+    /// Динамика фитнеса по поколениям
+    Trend,
+    /// Предки генома с указанным номером
+    Ancestors(u64),
+}
+
+/// Геном из базы истории
+#[derive(Clone, Debug)]
+pub struct GenomeRecord {
+    pub generation: u32,
+    pub id: u64,
+    pub origin_generation: u32,
+    pub fitness: f32,
+    pub kills: u32,
+    pub survival_time: f32,
+    pub elo: f32,
+}
+
+/// Предок генома и расстояние до него в поколениях родства
+#[derive(Clone, Debug)]
+pub struct Ancestor {
+    pub id: u64,
+    pub depth: u32,
+    pub origin_generation: Option<u32>,
+    pub best_fitness: Option<f32>,
+}
+
+/// SQLite-база истории эволюции: геномы всех поколений, родословная и бои
+#[derive(Resource)]
+pub struct HistoryDb {
+    pub path: PathBuf,
+    /// Соединение открывается при первом обращении и живёт до конца программы
+    connection: Mutex<Option<Connection>>,
+}
+
+impl HistoryDb {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            connection: Mutex::new(None),
+        }
+    }
+
+    /// Открывает базу, создавая файл и таблицы
+    fn open(&self) -> rusqlite::Result<Connection> {
+        if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        let conn = Connection::open(&self.path)?;
+        conn.execute_batch(SCHEMA)?;
+        let applied: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        for (version, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+            conn.execute_batch(migration)?;
+            conn.pragma_update(None, "user_version", version + 1)?;
+        }
+        Ok(conn)
+    }
+
+    /// Выполняет f на общем соединении, открывая базу при первом обращении
+    fn with_connection<T>(&self, f: impl FnOnce(&mut Connection) -> rusqlite::Result<T>) -> rusqlite::Result<T> {
+        let mut connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        if connection.is_none() {
+            *connection = Some(self.open()?);
+        }
+        f(connection.as_mut().expect("соединение только что открыто"))
+    }
+
+    /// Наибольший номер генома, уже записанный в базу (0 — база пуста), чтобы новый
+    /// запуск продолжал нумерацию, а не перезаписывал геномы и родословную прошлых
+    pub fn max_genome_id(&self) -> rusqlite::Result<u64> {
+        self.with_connection(|conn| {
+            let max: Option<i64> = conn.query_row("SELECT MAX(id) FROM genomes", [], |row| row.get(0))?;
+            Ok(max.unwrap_or(0) as u64)
+        })
+    }
+
+    /// Записывает поколение целиком: статистику, геномы с компонентами фитнеса и родителей
+    pub fn record_generation(&self, population: &Population, stats: &GenerationStats) -> rusqlite::Result<()> {
+        self.with_connection(|conn| Self::write_generation(conn, population, stats))
+    }
+
+    fn write_generation(conn: &mut Connection, population: &Population, stats: &GenerationStats) -> rusqlite::Result<()> {
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO generations (generation, best_fitness, mean_fitness, median_fitness,
+                 worst_fitness, total_kills, mean_kills, mean_survival, diversity, wall_time,
+                 gene_entropy, behavior_diversity)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                stats.generation,
+                stats.best_fitness,
+                stats.mean_fitness,
+                stats.median_fitness,
+                stats.worst_fitness,
+                stats.total_kills,
+                stats.mean_kills,
+                stats.mean_survival,
+                stats.diversity,
+                stats.wall_time,
+                stats.gene_entropy,
+                stats.behavior_diversity
+            ],
+        )?;
+        for ai in &population.genomes {
+            let genome = serde_json::to_string(&ai.genome).unwrap_or_default();
+            tx.execute(
+                "INSERT OR REPLACE INTO genomes VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                params![
+                    population.generation,
+                    ai.id as i64,
+                    ai.origin_generation,
+                    ai.fitness,
+                    ai.kills,
+                    ai.survival_time,
+                    ai.behavior.shots_fired,
+                    ai.behavior.hits,
+                    ai.behavior.distance_travelled,
+                    ai.behavior.engaged_time,
+                    ai.rating.elo,
+                    genome
+                ],
+            )?;
+            for parent in &ai.parents {
+                tx.execute(
+                    "INSERT OR IGNORE INTO lineage VALUES (?1, ?2)",
+                    params![ai.id as i64, *parent as i64],
+                )?;
+            }
+        }
+        tx.commit()
+    }
+
+    pub fn record_battle(&self, summary: &BattleSummary) -> rusqlite::Result<()> {
+        self.with_connection(|conn| {
+            conn.execute(
+                "INSERT INTO battles (generation, kind, ticks, duration, alive_0, alive_1, health_0, health_1, deaths_0, deaths_1, score_a)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                params![
+                    summary.generation,
+                    summary.kind,
+                    summary.ticks,
+                    summary.duration,
+                    summary.alive[0],
+                    summary.alive[1],
+                    summary.health[0],
+                    summary.health[1],
+                    summary.deaths[0],
+                    summary.deaths[1],
+                    summary.score_a
+                ],
+            )?;
+            Ok(())
+        })
+    }
+
+    /// Лучший по фитнесу геном каждого поколения
+    pub fn best_by_generation(&self) -> rusqlite::Result<Vec<GenomeRecord>> {
+        self.with_connection(|conn| {
+            // SQLite берёт остальные столбцы из строки, на которой достигнут MAX
+            let mut stmt = conn.prepare(
+                "SELECT generation, id, origin_generation, MAX(fitness), kills, survival_time, elo
+                 FROM genomes GROUP BY generation ORDER BY generation",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok(GenomeRecord {
+                    generation: row.get(0)?,
+                    id: row.get::<_, i64>(1)? as u64,
+                    origin_generation: row.get(2)?,
+                    fitness: row.get(3)?,
+                    kills: row.get(4)?,
+                    survival_time: row.get(5)?,
+                    elo: row.get(6)?,
+                })
+            })?;
+            rows.collect()
+        })
+    }
+
+    /// Статистика поколений по порядку
+    pub fn fitness_trend(&self) -> rusqlite::Result<Vec<GenerationStats>> {
+        self.with_connection(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT generation, best_fitness, mean_fitness, median_fitness, worst_fitness,
+                        total_kills, mean_kills, mean_survival, diversity, wall_time,
+                        gene_entropy, behavior_diversity
+                 FROM generations ORDER BY generation",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok(GenerationStats {
+                    generation: row.get(0)?,
+                    best_fitness: row.get(1)?,
+                    mean_fitness: row.get(2)?,
+                    median_fitness: row.get(3)?,
+                    worst_fitness: row.get(4)?,
+                    total_kills: row.get(5)?,
+                    mean_kills: row.get(6)?,
+                    mean_survival: row.get(7)?,
+                    diversity: row.get(8)?,
+                    wall_time: row.get(9)?,
+                    gene_entropy: row.get(10)?,
+                    behavior_diversity: row.get(11)?,
+                })
+            })?;
+            rows.collect()
+        })
+    }
+
+    /// Все предки генома, от родителей вглубь
+    pub fn ancestors(&self, id: u64) -> rusqlite::Result<Vec<Ancestor>> {
+        self.with_connection(|conn| {
+            let mut stmt = conn.prepare(
+                "WITH RECURSIVE ancestry(id, depth) AS (
+                     SELECT parent_id, 1 FROM lineage WHERE child_id = ?1
+                     UNION
+                     SELECT lineage.parent_id, ancestry.depth + 1
+                     FROM lineage JOIN ancestry ON lineage.child_id = ancestry.id
+                 )
+                 SELECT ancestry.id, MIN(ancestry.depth),
+                        (SELECT MIN(origin_generation) FROM genomes WHERE genomes.id = ancestry.id),
+                        (SELECT MAX(fitness) FROM genomes WHERE genomes.id = ancestry.id)
+                 FROM ancestry GROUP BY ancestry.id ORDER BY MIN(ancestry.depth), ancestry.id",
+            )?;
+            let rows = stmt.query_map([id as i64], |row| {
+                Ok(Ancestor {
+                    id: row.get::<_, i64>(0)? as u64,
+                    depth: row.get(1)?,
+                    origin_generation: row.get(2)?,
+                    best_fitness: row.get(3)?,
+                })
+            })?;
+            rows.collect()
+        })
+    }
+
+    /// Выполняет запрос --query и печатает результат
+    pub fn print_query(&self, query: HistoryQuery) -> rusqlite::Result<()> {
+        match query {
+            HistoryQuery::Best => {
+                println!(
+                    "{:>10}  {:>8}  {:>8}  {:>10}  {:>7}  {:>9}  {:>6}",
+                    "поколение", "геном", "возник", "фитнес", "убийств", "выживание", "Эло"
+                );
+                for r in self.best_by_generation()? {
+                    println!(
+                        "{:>10}  {:>8}  {:>8}  {:>10.1}  {:>7}  {:>9.1}  {:>6.0}",
+                        r.generation, r.id, r.origin_generation, r.fitness, r.kills, r.survival_time, r.elo
+                    );
+                }
+            }
+            HistoryQuery::Trend => {
+                println!(
+                    "{:>10}  {:>10}  {:>8}  {:>10}  {:>10}  {:>12}  {:>8}",
+                    "поколение", "лучший", "Δ", "средний", "медиана", "разнообразие", "энтропия"
+                );
+                let mut previous: Option<f32> = None;
+                for s in self.fitness_trend()? {
+                    let delta = previous.map(|p| format!("{:+.1}", s.best_fitness - p)).unwrap_or_default();
+                    println!(
+                        "{:>10}  {:>10.1}  {:>8}  {:>10.1}  {:>10.1}  {:>12.3}  {:>8.3}",
+                        s.generation, s.best_fitness, delta, s.mean_fitness, s.median_fitness, s.diversity, s.gene_entropy
+                    );
+                    previous = Some(s.best_fitness);
+                }
+            }
+            HistoryQuery::Ancestors(id) => {
+                let ancestors = self.ancestors(id)?;
+                if ancestors.is_empty() {
+                    println!("У генома {} нет известных предков", id);
+                }
+                for a in ancestors {
+                    println!(
+                        "{}геном {} (появился в поколении {}, лучший фитнес {})",
+                        "  ".repeat(a.depth as usize),
+                        a.id,
+                        a.origin_generation.map(|g| g.to_string()).unwrap_or_else(|| "?".to_string()),
+                        a.best_fitness.map(|f| format!("{:.1}", f)).unwrap_or_else(|| "-".to_string())
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_lineage_and_best_genomes() {
+        let path = std::env::temp_dir().join(format!("smart_bot_history_{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let db = HistoryDb::new(path.clone());
+
+        let mut rng = rand::thread_rng();
+        let mut population = Population::new_fresh(6);
+        for (i, genome) in population.genomes.iter_mut().enumerate() {
+            genome.fitness = i as f32;
+        }
+        db.record_generation(&population, &GenerationStats::from_population(&population, 0.0)).unwrap();
+        population.evolve(&mut rng);
+        population.genomes[5].fitness = 100.0;
+        db.record_generation(&population, &GenerationStats::from_population(&population, 0.0)).unwrap();
+
+        let best = db.best_by_generation().unwrap();
+        assert_eq!(best.len(), 2);
+        assert_eq!(best[0].fitness, 5.0);
+        assert_eq!(best[1].id, population.genomes[5].id);
+
+        let child = &population.genomes[5];
+        let ancestors = db.ancestors(child.id).unwrap();
+        assert!(ancestors.iter().all(|a| a.depth == 1 && child.parents.contains(&a.id)));
+        assert!(!ancestors.is_empty());
+
+        // Новый запуск с --fresh в ту же базу не перезаписывает геномы и родословную прошлого
+        let taken = db.max_genome_id().unwrap();
+        let mut fresh = Population::new_fresh(6);
+        fresh.continue_ids_after(taken);
+        assert!(fresh.genomes.iter().all(|g| g.id > taken));
+        fresh.evolve(&mut rng);
+        db.record_generation(&fresh, &GenerationStats::from_population(&fresh, 0.0)).unwrap();
+        assert_eq!(db.ancestors(child.id).unwrap().len(), ancestors.len());
+        let rows: i64 = db.with_connection(|conn| conn.query_row("SELECT COUNT(*) FROM genomes", [], |row| row.get(0))).unwrap();
+        assert_eq!(rows, 18);
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -6,6 +6,25 @@ mod battle;
 mod map;
 mod camera;
 mod ui;
+mod elites;
+mod checkpoint;
+mod cli;
+mod champion;
+mod rating;
+mod metrics;
+mod history;
+mod curriculum;
+mod tournament;
+mod es;
+mod cloning;
+mod bots;
+mod controller;
+mod server;
+mod throughput;
+mod onnx;
+mod analysis;
+mod classes;
+mod weapons;
 
 use bevy::prelude::*;
 use bevy::time::{Fixed, TimeUpdateStrategy};
@@ -30,12 +49,339 @@ fn is_headless(headless: Res<Headless>) -> bool {
 }
 
 fn main() {
-    let fresh_start = std::env::args().any(|arg| arg == "--fresh");
-    let headless = std::env::args().any(|arg| arg == "--headless");
-    let population = if fresh_start {
+    let args = match cli::CliArgs::parse() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+
+    let mut checkpoint_config = checkpoint::CheckpointConfig::default();
+    if let Some(dir) = &args.checkpoint_dir {
+        checkpoint_config.dir = dir.clone();
+    }
+    if let Some(keep) = args.keep_checkpoints {
+        checkpoint_config.keep_last = keep;
+    }
+
+    if args.list_checkpoints {
+        if let Err(e) = checkpoint::print_checkpoints(&checkpoint_config) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Замер скорости пишет метрики, историю и чекпоинты в свой каталог рядом с отчётом,
+    // каждый раз пустой, чтобы прогоны были одинаковыми
+    let bench = args.bench().then(|| {
+        let output = args.bench_out.clone().unwrap_or_else(|| throughput::DEFAULT_REPORT.into());
+        let bench_dir = output.with_extension("run");
+        if bench_dir.exists() {
+            if let Err(e) = std::fs::remove_dir_all(&bench_dir) {
+                eprintln!("Не удалось очистить {}: {}", bench_dir.display(), e);
+                std::process::exit(1);
+            }
+        }
+        checkpoint_config.dir = bench_dir.join("checkpoints");
+        let seed = args.bench_seed.unwrap_or(throughput::DEFAULT_SEED);
+        (throughput::ThroughputBench::new(seed, args.bench_generations, args.bench_ticks, output), bench_dir)
+    });
+
+    let run_dir = match &bench {
+        Some((_, bench_dir)) => bench_dir.clone(),
+        None => args.run_dir.clone().unwrap_or_else(|| checkpoint_config.dir.clone()),
+    };
+    let history_db = history::HistoryDb::new(match &bench {
+        Some(_) => run_dir.join(history::HISTORY_DB_FILE),
+        None => args.history_db.clone().unwrap_or_else(|| run_dir.join(history::HISTORY_DB_FILE)),
+    });
+    if let Some(query) = args.query {
+        if let Err(e) = history_db.print_query(query) {
+            eprintln!("Ошибка базы истории {}: {}", history_db.path.display(), e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Оружие нужно и анализу генома, и боям
+    let weapons = match &args.weapons {
+        Some(path) => match weapons::Weapons::load(path) {
+            Ok(weapons) => weapons,
+            Err(e) => {
+                eprintln!("Не удалось загрузить оружие: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => weapons::Weapons::default(),
+    };
+
+    if let Some(path) = &args.analyze {
+        let agent = match tournament::Entrant::load(path) {
+            Ok(agent) => agent,
+            Err(e) => {
+                eprintln!("Не удалось загрузить геном для анализа: {}", e);
+                std::process::exit(1);
+            }
+        };
+        let report = analysis::analyze(&agent.name, &agent.controller, &weapons);
+        println!("{}", report.summary());
+        let prefix = args.analyze_out.clone().unwrap_or_else(|| "analysis".into());
+        match report.write(&prefix) {
+            Ok(path) => println!("Отчёт анализа сохранён в {}", path.display()),
+            Err(e) => {
+                eprintln!("Не удалось сохранить отчёт анализа: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let fresh_start = args.fresh;
+    // Замер повторяется целиком: и начальная популяция, и вся эволюция идут от его зерна
+    let mut evolution_rng = match &bench {
+        Some((bench, _)) => genetics::EvolutionRng::seeded(bench.seed),
+        None => genetics::EvolutionRng::default(),
+    };
+    let headless = args.headless || args.serve.is_some() || bench.is_some();
+    let mut population = if let Some((bench, _)) = &bench {
+        // Сценарий замера: новая популяция из зерна сразу в боях друг с другом,
+        // чтобы нагрузка не менялась от смены этапов обучения
+        let mut population = genetics::Population::new_seeded(20, bench.seed);
+        population.curriculum = curriculum::CurriculumState {
+            stage: curriculum::CurriculumStage::SelfPlay,
+            generations_in_stage: 0,
+        };
+        println!(
+            "Замер скорости: зерно {}, предел — {}",
+            bench.seed,
+            match (bench.max_generations, bench.max_ticks) {
+                (Some(generations), Some(ticks)) => format!("поколений: {}, тактов: {}", generations, ticks),
+                (Some(generations), None) => format!("поколений: {}", generations),
+                (None, Some(ticks)) => format!("тактов: {}", ticks),
+                (None, None) => unreachable!("--bench задаёт хотя бы один предел"),
+            }
+        );
+        population
+    } else if fresh_start {
         genetics::Population::new_fresh(20)
     } else {
-        genetics::Population::new(20)
+        match genetics::Population::load_or_new(20, args.resume.as_deref()) {
+            Ok(population) => population,
+            Err(e) => {
+                eprintln!("Не удалось загрузить сохранение: {}", e);
+                eprintln!("Запустите с --fresh, чтобы начать заново, или с --resume <чекпоинт> (см. --list-checkpoints)");
+                std::process::exit(1);
+            }
+        }
+    };
+    match history_db.max_genome_id() {
+        Ok(taken) => population.continue_ids_after(taken),
+        Err(e) => eprintln!("Ошибка базы истории {}: {}", history_db.path.display(), e),
+    }
+    if let Some(selection) = args.selection {
+        population.settings.selection = selection;
+    }
+    if let Some(stage) = args.curriculum {
+        population.curriculum = curriculum::CurriculumState {
+            stage,
+            generations_in_stage: 0,
+        };
+    }
+    if let Some(evaluations) = args.evaluations {
+        population.settings.evaluation.evaluations = evaluations.max(1);
+    }
+    if let Some(aggregate) = args.aggregate {
+        population.settings.evaluation.aggregate = aggregate;
+    }
+    if let Some(response) = args.stagnation {
+        population.settings.stagnation.response = response;
+    }
+    if let Some(window) = args.stagnation_window {
+        population.settings.stagnation.window = window;
+    }
+    if let Some(sigma) = args.es_sigma {
+        population.settings.es.sigma = sigma;
+    }
+    if let Some(learning_rate) = args.es_learning_rate {
+        population.settings.es.learning_rate = learning_rate;
+    }
+    match args.trainer {
+        Some(es::Trainer::Es) => {
+            population.settings.trainer = es::Trainer::Es;
+            population.start_es(&mut evolution_rng.0);
+        }
+        Some(es::Trainer::Genetic) => {
+            population.settings.trainer = es::Trainer::Genetic;
+            population.es = None;
+        }
+        None => {}
+    }
+    if let Some(path) = &args.clone_from {
+        let samples = match cloning::load_demonstrations(path) {
+            Ok(samples) if !samples.is_empty() => samples,
+            Ok(_) => {
+                eprintln!("В {} нет демонстраций", path.display());
+                std::process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("Не удалось прочитать демонстрации {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        };
+        let genome = cloning::train(&samples, args.clone_epochs.unwrap_or(50), 0.5, &mut rand::thread_rng());
+        println!(
+            "Сеть обучена на {} тактах игрока, ошибка {:.4}; геном добавлен в популяцию",
+            samples.len(),
+            cloning::loss(&genome, &samples)
+        );
+        population.seed_genome(genome, &mut evolution_rng.0);
+    }
+    for path in &args.import_champions {
+        match champion::ChampionFile::load(path) {
+            Ok(champion) => match population.import_champion(champion.to_controller()) {
+                Ok(()) => println!("Чемпион «{}» добавлен в популяцию", champion.metadata.name),
+                Err(e) => {
+                    eprintln!("Не удалось импортировать чемпиона «{}»: {}", champion.metadata.name, e);
+                    std::process::exit(1);
+                }
+            },
+            Err(e) => {
+                eprintln!("Не удалось импортировать чемпиона: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    let mut fixed_opponents = champion::FixedOpponents::default();
+    for path in &args.opponents {
+        match champion::ChampionFile::load(path) {
+            Ok(champion) => fixed_opponents.champions.push(champion),
+            Err(e) => {
+                eprintln!("Не удалось загрузить противника: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let mut tank_classes = match &args.tank_classes {
+        Some(path) => match classes::TankClasses::load(path) {
+            Ok(tank_classes) => tank_classes,
+            Err(e) => {
+                eprintln!("Не удалось загрузить классы танков: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => classes::TankClasses::default(),
+    };
+    for (team, composition) in [&args.blue_classes, &args.red_classes].into_iter().enumerate() {
+        if let Some(composition) = composition {
+            tank_classes.teams[team] = composition.clone();
+        }
+    }
+    if tank_classes != classes::TankClasses::default() {
+        println!("Классы танков — {}", tank_classes.describe());
+    }
+
+    if let Some(path) = &args.export_champion {
+        let Some(best) = population.best_genome.as_ref().or_else(|| population.genomes.first()) else {
+            eprintln!("В популяции нет геномов для экспорта");
+            std::process::exit(1);
+        };
+        let champion = champion::ChampionFile::from_controller(best, "best", "population", population.generation);
+        match champion.save(path) {
+            Ok(()) => println!("Чемпион поколения {} сохранён в {}", population.generation, path.display()),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(path) = &args.export_onnx {
+        let champion = match &args.onnx_from {
+            Some(source) => match champion::ChampionFile::load(source) {
+                Ok(champion) => champion,
+                Err(e) => {
+                    eprintln!("Не удалось загрузить чемпиона: {}", e);
+                    std::process::exit(1);
+                }
+            },
+            None => {
+                let Some(best) = population.best_genome.as_ref().or_else(|| population.genomes.first()) else {
+                    eprintln!("В популяции нет геномов для экспорта");
+                    std::process::exit(1);
+                };
+                champion::ChampionFile::from_controller(best, "best", "population", population.generation)
+            }
+        };
+        match onnx::save(&champion, path) {
+            Ok(()) => println!("Сеть «{}» экспортирована в ONNX: {}", champion.metadata.name, path.display()),
+            Err(e) => {
+                eprintln!("Не удалось экспортировать в ONNX: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let match_queue = battle::MatchQueue {
+        pending: args.generation_battles.iter().copied().collect(),
+        exit_when_done: headless && !args.generation_battles.is_empty(),
+    };
+
+    let tournament = match &args.tournament {
+        Some(format) => match load_tournament(&args, *format, &checkpoint_config, headless) {
+            Ok(tournament) => Some(tournament),
+            Err(e) => {
+                eprintln!("Не удалось подготовить турнир: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let benchmark = match &args.benchmark {
+        Some(path) => match tournament::Entrant::load(path) {
+            Ok(agent) => {
+                let output = args.benchmark_out.clone().unwrap_or_else(|| "benchmark".into());
+                let mut benchmark = bots::Benchmark::new(
+                    agent.name,
+                    agent.controller,
+                    args.team_size.unwrap_or(1),
+                    args.benchmark_matches.unwrap_or(10),
+                    output,
+                );
+                benchmark.exit_when_done = headless;
+                println!("Бенчмарк «{}» против {} стратегий ботов", benchmark.agent_name, bots::BotStrategy::ALL.len());
+                Some(benchmark)
+            }
+            Err(e) => {
+                eprintln!("Не удалось загрузить агента для бенчмарка: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let metrics_log = metrics::MetricsLog::new(run_dir);
+
+    // Замер начинает с пустого архива и не читает элиты текущего обучения
+    let elite_archive = if bench.is_some() {
+        elites::EliteArchive::default()
+    } else {
+        match elites::EliteArchive::load_or_default() {
+            Ok(archive) => archive,
+            Err(e) => {
+                eprintln!("Не удалось загрузить архив элит: {}", e);
+                std::process::exit(1);
+            }
+        }
     };
     
     println!("Population initialized: generation {}, fresh_start: {}", population.generation, fresh_start);
@@ -60,6 +406,26 @@ fn main() {
 
     app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(BASE_MANUAL_DT)))
         .insert_resource(population)
+        .insert_resource(evolution_rng)
+        .insert_resource(elite_archive)
+        .insert_resource(checkpoint_config)
+        .insert_resource(metrics_log)
+        .insert_resource(history_db)
+        .insert_resource(fixed_opponents)
+        .insert_resource(bots::SparringBots { strategies: args.sparring.clone() })
+        .insert_resource(tank_classes)
+        .insert_resource(weapons)
+        .insert_resource(cloning::DemonstrationRecorder::new(
+            args.demos.clone().unwrap_or_else(|| cloning::DEMONSTRATIONS_FILE.into()),
+        ))
+        .insert_resource(ChampionUiState::default())
+        .insert_resource(match_queue)
+        .insert_resource(battle::BattleKind::default())
+        .insert_resource(battle::MatchHistory::default())
+        .insert_resource(GenerationBattleUiState::default())
+        .insert_resource(EliteBrowserState::default())
+        .insert_resource(StatsPanelState::default())
+        .insert_resource(NetworkViewState::default())
         .insert_resource(TimeMultiplier::default())
         .insert_resource(TimeMultiplierUiState::default())
         .insert_resource(crate::ui::StartupChoiceMade::default())
@@ -69,14 +435,22 @@ fn main() {
         .add_systems(Startup, (setup, load_ui_assets).run_if(not_headless))
         .add_systems(Startup, headless_setup.run_if(is_headless))
         .add_systems(Startup, init_fixed_timestep)
+        .configure_sets(FixedUpdate, controller::PhysicsSet.after(controller::ControllerSet))
         .add_systems(First, refresh_time_update_strategy)
         .add_systems(FixedUpdate, (
-            tank_movement_system,
-            tank_shooting_system.run_if(not_headless),
-            projectile_movement_system,
-            collision_system,
-            ai_control_system,
-            player_control_system.run_if(not_headless),
+            (
+                ai_control_system,
+                curriculum::scripted_target_system,
+                bots::scripted_bot_system,
+                player_control_system.run_if(not_headless),
+            ).in_set(controller::ControllerSet),
+            (
+                controller::apply_tank_actions,
+                curriculum::confine_moving_targets.after(controller::apply_tank_actions),
+                projectile_movement_system,
+                collision_system,
+            ).in_set(controller::PhysicsSet),
+            cloning::record_demonstrations.after(controller::ControllerSet).before(controller::PhysicsSet).run_if(not_headless),
             health_display_system,
             battle::check_battle_end,
         ).run_if(in_state(GameState::Battle)))
@@ -86,16 +460,56 @@ fn main() {
             camera_control_system.run_if(not_headless),
             tank_selection_system.after(time_multiplier_ui_system).run_if(not_headless),
             ui_system.run_if(not_headless),
+            elite_browser_ui_system.run_if(not_headless),
+            champion_ui_system.run_if(not_headless),
+            generation_battle_ui_system.run_if(not_headless),
             update_stats_ui.run_if(not_headless),
+            network_view_ui_system.run_if(not_headless),
         ))
         .add_systems(Update, log_progress)
-        .add_systems(Update, setup_ui_system.run_if(in_state(GameState::Setup)).run_if(not_headless))
-        .add_systems(OnEnter(GameState::Battle), (battle::start_battle, battle::spawn_tanks_from_population).run_if(not_headless))
+        .add_systems(Update, cloning::take_control_system.run_if(not_headless).run_if(in_state(GameState::Battle)))
+        .add_systems(Update, setup_ui_system.run_if(in_state(GameState::Setup)).run_if(not_headless).run_if(not(tournament::tournament_active)).run_if(not(bots::benchmark_active)))
+        .add_systems(Update, tournament::begin_tournament.run_if(in_state(GameState::Setup)).run_if(tournament::tournament_active.or_else(bots::benchmark_active)))
+        .add_systems(Update, tournament_ui_system.run_if(not_headless).run_if(tournament::tournament_active))
+        .add_systems(Update, benchmark_ui_system.run_if(not_headless).run_if(bots::benchmark_active))
+        .add_systems(OnEnter(GameState::Tournament), tournament::run_tournament.run_if(tournament::tournament_active))
+        .add_systems(OnEnter(GameState::Tournament), bots::run_benchmark.run_if(bots::benchmark_active))
+        .add_systems(OnEnter(GameState::Battle), battle::start_battle)
+        .add_systems(OnEnter(GameState::Battle), map::regenerate_obstacles)
+        .add_systems(OnEnter(GameState::Battle), battle::spawn_tanks_from_population.run_if(not_headless))
         .add_systems(OnEnter(GameState::Battle), battle::spawn_tanks_headless.run_if(is_headless))
         .add_systems(OnEnter(GameState::Battle), create_stats_ui.run_if(not_headless))
         .add_systems(OnExit(GameState::Battle), battle::end_battle)
         .add_systems(OnExit(GameState::Battle), despawn_stats_ui.run_if(not_headless))
-        .add_systems(OnEnter(GameState::Evolution), genetics::evolve_population);
+        .add_systems(OnExit(GameState::Battle), cloning::flush_demonstrations.run_if(not_headless))
+        .add_systems(OnEnter(GameState::Evolution), (
+            genetics::evolve_population.run_if(battle::is_training_battle),
+            battle::finish_match.run_if(not(battle::is_training_battle)),
+        ).chain());
+
+    if let Some(tournament) = tournament {
+        app.insert_resource(tournament);
+    }
+    if let Some(benchmark) = benchmark {
+        app.insert_resource(benchmark);
+    }
+    if let Some((bench, _)) = bench {
+        app.insert_resource(bench)
+            .add_systems(FixedUpdate, (
+                throughput::tick_started.before(controller::ControllerSet),
+                throughput::ai_finished.after(controller::ControllerSet).before(controller::PhysicsSet),
+                throughput::physics_finished.after(controller::PhysicsSet),
+            ).run_if(in_state(GameState::Battle)))
+            .add_systems(OnExit(GameState::Battle), throughput::battle_finished)
+            .add_systems(OnEnter(GameState::Evolution), (
+                throughput::evolution_started.before(genetics::evolve_population),
+                throughput::evolution_finished.after(genetics::evolve_population),
+            ));
+    }
+    if let Some(addr) = &args.serve {
+        app.insert_resource(server::ExternalServer { addr: addr.clone() })
+            .set_runner(server::run);
+    }
 
     {
         // Настраиваем редкую отрисовку: RenderApp обновляет фазы только на выбранных кадрах
@@ -154,7 +568,7 @@ fn setup(
     commands.insert_resource(camera::CameraState::default());
     
     // Генерируем карту
-    let game_map = map::GameMap::new(100.0);
+    let game_map = map::GameMap::new(map::MAP_SIZE);
     game_map.spawn(&mut commands, &mut meshes, &mut materials);
     
     // Создаем начальную популяцию танков
@@ -167,10 +581,50 @@ fn setup(
 fn headless_setup(
     mut commands: Commands,
     mut next_state: ResMut<NextState<GameState>>,
+    tournament: Option<Res<tournament::Tournament>>,
+    benchmark: Option<Res<bots::Benchmark>>,
+    server: Option<Res<server::ExternalServer>>,
 ) {
     commands.insert_resource(BattleState::default());
     commands.insert_resource(CameraState::default());
-    next_state.set(GameState::Battle);
+    if server.is_some() {
+        // Бои начинает внешний агент командой reset
+        return;
+    }
+    if tournament.is_some() || benchmark.is_some() {
+        next_state.set(GameState::Tournament);
+    } else {
+        next_state.set(GameState::Battle);
+    }
+}
+
+/// Собирает участников турнира из файлов и чекпоинтов
+fn load_tournament(
+    args: &cli::CliArgs,
+    format: tournament::TournamentFormat,
+    checkpoint_config: &checkpoint::CheckpointConfig,
+    headless: bool,
+) -> Result<tournament::Tournament, String> {
+    let mut paths = args.entrants.clone();
+    if args.tournament_checkpoints {
+        let checkpoints = checkpoint::list_checkpoints(&checkpoint_config.dir)
+            .map_err(|e| format!("{}: {}", checkpoint_config.dir.display(), e))?;
+        paths.extend(checkpoints.into_iter().map(|c| c.path));
+    }
+
+    let entrants = paths
+        .iter()
+        .map(|path| tournament::Entrant::load(path).map_err(|e| e.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    if entrants.len() < 2 {
+        return Err(format!("нужно хотя бы два участника, найдено {}", entrants.len()));
+    }
+
+    println!("Турнир {:?}: {} участников", format, entrants.len());
+    let output = args.tournament_out.clone().unwrap_or_else(|| "tournament".into());
+    let mut tournament = tournament::Tournament::new(format, args.team_size.unwrap_or(1), entrants, output);
+    tournament.exit_when_done = headless;
+    Ok(tournament)
 }
 
 fn init_fixed_timestep(
@@ -1,11 +1,19 @@
 use bevy::prelude::*;
 use bevy::prelude::shape;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 /// Компонент препятствия
 #[derive(Component)]
 pub struct ObstacleComponent;
 
+/// Случайное препятствие карты (не стена): пересоздаётся перед каждым боем
+#[derive(Component)]
+pub struct MapObstacle;
+
+/// Размер карты
+pub const MAP_SIZE: f32 = 100.0;
+
 /// Генерация игровой карты
 pub struct GameMap {
     pub size: f32,
@@ -19,14 +27,22 @@ pub struct Obstacle {
 
 impl Default for GameMap {
     fn default() -> Self {
-        Self::new(100.0)
+        Self::new(MAP_SIZE)
     }
 }
 
 impl GameMap {
     pub fn new(size: f32) -> Self {
+        Self::generate(size, &mut rand::thread_rng())
+    }
+
+    /// Воспроизводимая карта: одно и то же зерно даёт те же препятствия
+    pub fn from_seed(size: f32, seed: u64) -> Self {
+        Self::generate(size, &mut StdRng::seed_from_u64(seed))
+    }
+
+    fn generate(size: f32, rng: &mut impl Rng) -> Self {
         let mut obstacles = Vec::new();
-        let mut rng = rand::thread_rng();
         
         // Создаем случайные препятствия
         for _ in 0..15 {
@@ -67,26 +83,7 @@ impl GameMap {
         });
         
         // Препятствия
-        let obstacle_material = materials.add(StandardMaterial {
-            base_color: Color::rgb(0.5, 0.5, 0.5),
-            ..default()
-        });
-        
-        for obstacle in &self.obstacles {
-            commands.spawn((
-                PbrBundle {
-                    mesh: meshes.add(Mesh::from(shape::Box::new(
-                        obstacle.size.x,
-                        obstacle.size.y,
-                        obstacle.size.z,
-                    ))),
-                    material: obstacle_material.clone(),
-                    transform: Transform::from_translation(obstacle.position),
-                    ..default()
-                },
-                ObstacleComponent,
-            ));
-        }
+        self.spawn_obstacles(commands, Some(meshes), Some(materials));
         
         // Границы карты
         let wall_material = materials.add(StandardMaterial {
@@ -142,4 +139,69 @@ impl GameMap {
             ObstacleComponent,
         ));
     }
+
+    /// Создаёт случайные препятствия; без ассетов (headless) — только положение для столкновений
+    pub fn spawn_obstacles(
+        &self,
+        commands: &mut Commands,
+        meshes: Option<&mut ResMut<Assets<Mesh>>>,
+        materials: Option<&mut ResMut<Assets<StandardMaterial>>>,
+    ) {
+        let (Some(meshes), Some(materials)) = (meshes, materials) else {
+            for obstacle in &self.obstacles {
+                commands.spawn((
+                    Transform::from_translation(obstacle.position),
+                    GlobalTransform::default(),
+                    ObstacleComponent,
+                    MapObstacle,
+                ));
+            }
+            return;
+        };
+
+        let obstacle_material = materials.add(StandardMaterial {
+            base_color: Color::rgb(0.5, 0.5, 0.5),
+            ..default()
+        });
+        for obstacle in &self.obstacles {
+            commands.spawn((
+                PbrBundle {
+                    mesh: meshes.add(Mesh::from(shape::Box::new(
+                        obstacle.size.x,
+                        obstacle.size.y,
+                        obstacle.size.z,
+                    ))),
+                    material: obstacle_material.clone(),
+                    transform: Transform::from_translation(obstacle.position),
+                    ..default()
+                },
+                ObstacleComponent,
+                MapObstacle,
+            ));
+        }
+    }
+}
+
+/// Перед боем пересоздаёт препятствия по зерну текущего боя популяции
+/// (или эпизода внешнего агента)
+pub fn regenerate_obstacles(
+    mut commands: Commands,
+    population: Res<crate::genetics::Population>,
+    battle_kind: Res<crate::battle::BattleKind>,
+    obstacles: Query<Entity, With<MapObstacle>>,
+    mut meshes: Option<ResMut<Assets<Mesh>>>,
+    mut materials: Option<ResMut<Assets<StandardMaterial>>>,
+) {
+    for entity in obstacles.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let seed = match *battle_kind {
+        crate::battle::BattleKind::External { seed, .. } => seed,
+        _ => population.battle_seed(),
+    };
+    GameMap::from_seed(MAP_SIZE, seed).spawn_obstacles(
+        &mut commands,
+        meshes.as_mut(),
+        materials.as_mut(),
+    );
 }
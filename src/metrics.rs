@@ -0,0 +1,294 @@
+use bevy::prelude::*;
+use crate::components::AIController;
+use crate::elites::EliteArchive;
+use crate::genetics::Population;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// Файлы метрик в каталоге запуска
+pub const GENERATIONS_CSV: &str = "generations.csv";
+pub const GENERATIONS_JSONL: &str = "generations.jsonl";
+pub const BATTLES_CSV: &str = "battles.csv";
+pub const BATTLES_JSONL: &str = "battles.jsonl";
+
+/// Статистика одного поколения на момент перед эволюцией
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GenerationStats {
+    pub generation: u32,
+    pub best_fitness: f32,
+    pub mean_fitness: f32,
+    pub median_fitness: f32,
+    pub worst_fitness: f32,
+    pub total_kills: u32,
+    /// Убийств за бой в среднем по геномам
+    pub mean_kills: f32,
+    /// Время выживания за бой в среднем по геномам
+    pub mean_survival: f32,
+    /// Среднее попарное евклидово расстояние между геномами
+    pub diversity: f32,
+    /// Средняя по генам нормированная энтропия значений (0 — клоны, 1 — равномерный разброс)
+    #[serde(default)]
+    pub gene_entropy: f32,
+    /// Среднее попарное расстояние между поведенческими дескрипторами
+    #[serde(default)]
+    pub behavior_diversity: f32,
+    /// Реальное время на поколение, секунды
+    pub wall_time: f32,
+}
+
+impl GenerationStats {
+    pub const CSV_HEADER: &'static str = "generation,best_fitness,mean_fitness,median_fitness,worst_fitness,total_kills,mean_kills,mean_survival,diversity,gene_entropy,behavior_diversity,wall_time";
+
+    pub fn from_population(population: &Population, wall_time: f32) -> Self {
+        let genomes = &population.genomes;
+        if genomes.is_empty() {
+            return Self {
+                generation: population.generation,
+                wall_time,
+                ..default()
+            };
+        }
+        let count = genomes.len() as f32;
+        let mut fitness: Vec<f32> = genomes.iter().map(|g| g.fitness).collect();
+        fitness.sort_by(|a, b| a.total_cmp(b));
+        let total_kills = genomes.iter().map(|g| g.kills).sum::<u32>();
+
+        Self {
+            generation: population.generation,
+            best_fitness: fitness[fitness.len() - 1],
+            mean_fitness: fitness.iter().sum::<f32>() / count,
+            median_fitness: median(&fitness),
+            worst_fitness: fitness[0],
+            total_kills,
+            mean_kills: genomes.iter().map(|g| g.kills as f32 / g.battles_played()).sum::<f32>() / count,
+            mean_survival: genomes.iter().map(|g| g.survival_time / g.battles_played()).sum::<f32>() / count,
+            diversity: mean_pairwise_distance(genomes),
+            gene_entropy: gene_entropy(genomes),
+            behavior_diversity: behavior_diversity(genomes),
+            wall_time,
+        }
+    }
+
+    pub fn csv_row(&self) -> String {
+        format!(
+            "{},{:.3},{:.3},{:.3},{:.3},{},{:.3},{:.3},{:.4},{:.4},{:.4},{:.3}",
+            self.generation,
+            self.best_fitness,
+            self.mean_fitness,
+            self.median_fitness,
+            self.worst_fitness,
+            self.total_kills,
+            self.mean_kills,
+            self.mean_survival,
+            self.diversity,
+            self.gene_entropy,
+            self.behavior_diversity,
+            self.wall_time
+        )
+    }
+}
+
+/// Итог одного боя любого вида
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BattleSummary {
+    pub generation: u32,
+    /// training, generation_match или tournament
+    pub kind: String,
+    pub ticks: u32,
+    /// Симулированная длительность, секунды
+    pub duration: f32,
+    pub alive: [u32; 2],
+    pub health: [f32; 2],
+    /// Потери команд
+    pub deaths: [u32; 2],
+    /// Очки синих: 1 — победа, 0.5 — ничья, 0 — поражение
+    pub score_a: f32,
+}
+
+impl BattleSummary {
+    pub const CSV_HEADER: &'static str = "generation,kind,ticks,duration,alive_0,alive_1,health_0,health_1,deaths_0,deaths_1,score_a";
+
+    pub fn csv_row(&self) -> String {
+        format!(
+            "{},{},{},{:.2},{},{},{:.1},{:.1},{},{},{}",
+            self.generation,
+            self.kind,
+            self.ticks,
+            self.duration,
+            self.alive[0],
+            self.alive[1],
+            self.health[0],
+            self.health[1],
+            self.deaths[0],
+            self.deaths[1],
+            self.score_a
+        )
+    }
+}
+
+/// Запись метрик запуска в CSV и JSON Lines
+#[derive(Resource)]
+pub struct MetricsLog {
+    pub dir: PathBuf,
+    generation_started: Instant,
+}
+
+impl MetricsLog {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            generation_started: Instant::now(),
+        }
+    }
+
+    /// Секунды с начала текущего поколения; отсчёт начинается заново
+    pub fn take_generation_time(&mut self) -> f32 {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.generation_started).as_secs_f32();
+        self.generation_started = now;
+        elapsed
+    }
+
+    pub fn record_generation(&self, stats: &GenerationStats) -> std::io::Result<()> {
+        self.append(GENERATIONS_CSV, GenerationStats::CSV_HEADER, &stats.csv_row())?;
+        self.append_json(GENERATIONS_JSONL, stats)
+    }
+
+    pub fn record_battle(&self, summary: &BattleSummary) -> std::io::Result<()> {
+        self.append(BATTLES_CSV, BattleSummary::CSV_HEADER, &summary.csv_row())?;
+        self.append_json(BATTLES_JSONL, summary)
+    }
+
+    fn append_json<T: Serialize>(&self, file: &str, value: &T) -> std::io::Result<()> {
+        let line = serde_json::to_string(value)?;
+        self.append(file, "", &line)
+    }
+
+    /// Дописывает строку в файл; заголовок пишется только в новый файл
+    fn append(&self, file: &str, header: &str, line: &str) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(file);
+        let is_new = !path.exists() || std::fs::metadata(&path)?.len() == 0;
+        let mut out = std::fs::OpenOptions::new().create(true).append(true).open(&path)?;
+        if is_new && !header.is_empty() {
+            writeln!(out, "{}", header)?;
+        }
+        writeln!(out, "{}", line)
+    }
+}
+
+/// Медиана отсортированного по возрастанию среза
+fn median(sorted: &[f32]) -> f32 {
+    let n = sorted.len();
+    (sorted[(n - 1) / 2] + sorted[n / 2]) / 2.0
+}
+
+/// Евклидово расстояние между геномами
+pub fn genome_distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum::<f32>().sqrt()
+}
+
+/// Среднее попарное расстояние между геномами популяции
+pub fn mean_pairwise_distance(genomes: &[AIController]) -> f32 {
+    let mut total = 0.0;
+    let mut pairs = 0;
+    for (i, a) in genomes.iter().enumerate() {
+        for b in &genomes[i + 1..] {
+            total += genome_distance(&a.genome, &b.genome);
+            pairs += 1;
+        }
+    }
+    if pairs == 0 {
+        0.0
+    } else {
+        total / pairs as f32
+    }
+}
+
+/// Число корзин для энтропии гена на отрезке [-1, 1]
+const ENTROPY_BINS: usize = 10;
+
+/// Средняя по генам энтропия Шеннона, нормированная на log(ENTROPY_BINS)
+pub fn gene_entropy(genomes: &[AIController]) -> f32 {
+    let Some(first) = genomes.first() else {
+        return 0.0;
+    };
+    let count = genomes.len() as f32;
+    let mut total = 0.0;
+    for gene in 0..first.genome.len() {
+        let mut bins = [0u32; ENTROPY_BINS];
+        for ai in genomes {
+            let value = ai.genome.get(gene).copied().unwrap_or(0.0).clamp(-1.0, 1.0);
+            let bin = (((value + 1.0) / 2.0) * ENTROPY_BINS as f32) as usize;
+            bins[bin.min(ENTROPY_BINS - 1)] += 1;
+        }
+        let entropy: f32 = bins
+            .iter()
+            .filter(|&&n| n > 0)
+            .map(|&n| {
+                let p = n as f32 / count;
+                -p * p.ln()
+            })
+            .sum();
+        total += entropy / (ENTROPY_BINS as f32).ln();
+    }
+    total / first.genome.len().max(1) as f32
+}
+
+/// Среднее попарное расстояние между поведенческими дескрипторами (см. архив элит)
+pub fn behavior_diversity(genomes: &[AIController]) -> f32 {
+    let descriptors: Vec<[f32; 3]> = genomes.iter().filter_map(EliteArchive::descriptor).collect();
+    let mut total = 0.0;
+    let mut pairs = 0;
+    for (i, a) in descriptors.iter().enumerate() {
+        for b in &descriptors[i + 1..] {
+            total += genome_distance(a, b);
+            pairs += 1;
+        }
+    }
+    if pairs == 0 {
+        0.0
+    } else {
+        total / pairs as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generation_stats_and_files() {
+        let mut population = Population::new_fresh(4);
+        for (i, genome) in population.genomes.iter_mut().enumerate() {
+            genome.fitness = i as f32 * 10.0;
+            genome.kills = i as u32;
+        }
+        let stats = GenerationStats::from_population(&population, 1.5);
+        assert_eq!(stats.best_fitness, 30.0);
+        assert_eq!(stats.worst_fitness, 0.0);
+        assert_eq!(stats.median_fitness, 15.0);
+        assert_eq!(stats.total_kills, 6);
+        assert!(stats.diversity > 0.0);
+        assert!(stats.gene_entropy > 0.0 && stats.gene_entropy <= 1.0);
+
+        let clones = vec![population.genomes[0].clone(); 4];
+        assert_eq!(mean_pairwise_distance(&clones), 0.0);
+        assert_eq!(gene_entropy(&clones), 0.0);
+
+        let dir = std::env::temp_dir().join(format!("smart_bot_metrics_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let log = MetricsLog::new(dir.clone());
+        log.record_generation(&stats).unwrap();
+        log.record_generation(&stats).unwrap();
+
+        let csv = std::fs::read_to_string(dir.join(GENERATIONS_CSV)).unwrap();
+        assert_eq!(csv.lines().count(), 3);
+        assert_eq!(csv.lines().next(), Some(GenerationStats::CSV_HEADER));
+        let jsonl = std::fs::read_to_string(dir.join(GENERATIONS_JSONL)).unwrap();
+        let parsed: GenerationStats = serde_json::from_str(jsonl.lines().next().unwrap()).unwrap();
+        assert_eq!(parsed.total_kills, 6);
+    }
+}
@@ -0,0 +1,369 @@
+use crate::ai::{NetworkSpec, INPUT_NAMES, OUTPUT_NAMES};
+use crate::champion::ChampionFile;
+use std::path::Path;
+
+/// Версия набора операторов ONNX; все используемые узлы есть в opset 13
+const OPSET_VERSION: u64 = 13;
+/// Версия формата IR, соответствующая opset 13
+const IR_VERSION: u64 = 7;
+/// TensorProto.DataType.FLOAT
+const FLOAT: u64 = 1;
+/// AttributeProto.AttributeType.INT
+const ATTRIBUTE_INT: u64 = 2;
+
+/// Модель ONNX из генома чемпиона и архитектуры его сети.
+///
+/// Каждый вход get_inputs — отдельный тензор [batch, 1] с именем из INPUT_NAMES;
+/// граф склеивает их в матрицу наблюдений, дважды умножает на веса со сигмоидой
+/// (как NeuralNetwork::forward) и разбивает результат на выходы OUTPUT_NAMES.
+pub fn export(champion: &ChampionFile) -> Result<Vec<u8>, String> {
+    let NetworkSpec { inputs, hidden, outputs } = champion.network;
+    if inputs != INPUT_NAMES.len() || outputs != OUTPUT_NAMES.len() {
+        return Err(format!("сеть {:?} не совпадает с именами входов и выходов", champion.network));
+    }
+    if champion.genome.len() != champion.network.genome_len() {
+        return Err(format!(
+            "в геноме {} весов, сети {:?} нужно {}",
+            champion.genome.len(),
+            champion.network,
+            champion.network.genome_len()
+        ));
+    }
+    let (input_hidden, hidden_output) = champion.genome.split_at(inputs * hidden);
+
+    let mut graph = Message::default();
+    graph.message(1, node("Concat", &INPUT_NAMES, &["observation"], Some(("axis", 1))));
+    graph.message(1, node("MatMul", &["observation", "input_hidden_weights"], &["hidden_sum"], None));
+    graph.message(1, node("Sigmoid", &["hidden_sum"], &["hidden"], None));
+    graph.message(1, node("MatMul", &["hidden", "hidden_output_weights"], &["output_sum"], None));
+    graph.message(1, node("Sigmoid", &["output_sum"], &["outputs"], None));
+    graph.message(1, node("Split", &["outputs"], &OUTPUT_NAMES, Some(("axis", 1))));
+    graph.string(2, &champion.metadata.name);
+    graph.message(5, tensor("input_hidden_weights", &[inputs, hidden], input_hidden));
+    graph.message(5, tensor("hidden_output_weights", &[hidden, outputs], hidden_output));
+    for name in INPUT_NAMES {
+        graph.message(11, column(name));
+    }
+    for name in OUTPUT_NAMES {
+        graph.message(12, column(name));
+    }
+
+    let mut opset = Message::default();
+    opset.string(1, "");
+    opset.varint(2, OPSET_VERSION);
+
+    let mut model = Message::default();
+    model.varint(1, IR_VERSION);
+    model.string(2, "smart_bot");
+    model.string(3, env!("CARGO_PKG_VERSION"));
+    model.message(7, graph);
+    model.message(8, opset);
+    for (key, value) in [
+        ("name", champion.metadata.name.clone()),
+        ("source", champion.metadata.source.clone()),
+        ("generation", champion.metadata.exported_generation.to_string()),
+        ("origin_generation", champion.origin_generation.to_string()),
+        ("fitness", champion.fitness.to_string()),
+        ("network", format!("{}-{}-{}", inputs, hidden, outputs)),
+    ] {
+        let mut entry = Message::default();
+        entry.string(1, key);
+        entry.string(2, &value);
+        model.message(14, entry);
+    }
+    Ok(model.0)
+}
+
+/// Экспортирует чемпиона в файл .onnx
+pub fn save(champion: &ChampionFile, path: &Path) -> Result<(), String> {
+    let bytes = export(champion)?;
+    std::fs::write(path, bytes).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// NodeProto с необязательным целочисленным атрибутом
+fn node(op_type: &str, inputs: &[&str], outputs: &[&str], attribute: Option<(&str, i64)>) -> Message {
+    let mut node = Message::default();
+    for input in inputs {
+        node.string(1, input);
+    }
+    for output in outputs {
+        node.string(2, output);
+    }
+    node.string(3, &format!("{}_{}", op_type.to_lowercase(), outputs[0]));
+    node.string(4, op_type);
+    if let Some((name, value)) = attribute {
+        let mut attr = Message::default();
+        attr.string(1, name);
+        attr.varint(3, value as u64);
+        attr.varint(20, ATTRIBUTE_INT);
+        node.message(5, attr);
+    }
+    node
+}
+
+/// TensorProto float с данными в raw_data (little-endian)
+fn tensor(name: &str, dims: &[usize], values: &[f32]) -> Message {
+    let mut tensor = Message::default();
+    for &dim in dims {
+        tensor.varint(1, dim as u64);
+    }
+    tensor.varint(2, FLOAT);
+    tensor.string(8, name);
+    let raw: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+    tensor.bytes(9, &raw);
+    tensor
+}
+
+/// ValueInfoProto входа или выхода формы [batch, 1]
+fn column(name: &str) -> Message {
+    let mut batch = Message::default();
+    batch.string(2, "batch");
+    let mut width = Message::default();
+    width.varint(1, 1);
+    let mut shape = Message::default();
+    shape.message(1, batch);
+    shape.message(1, width);
+
+    let mut tensor_type = Message::default();
+    tensor_type.varint(1, FLOAT);
+    tensor_type.message(2, shape);
+    let mut type_proto = Message::default();
+    type_proto.message(1, tensor_type);
+
+    let mut value_info = Message::default();
+    value_info.string(1, name);
+    value_info.message(2, type_proto);
+    value_info
+}
+
+/// Запись сообщения protobuf: только нужные ONNX типы полей
+#[derive(Default)]
+struct Message(Vec<u8>);
+
+impl Message {
+    fn key(&mut self, field: u32, wire_type: u8) {
+        self.raw_varint(((field as u64) << 3) | wire_type as u64);
+    }
+
+    fn raw_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+
+    fn varint(&mut self, field: u32, value: u64) {
+        self.key(field, 0);
+        self.raw_varint(value);
+    }
+
+    fn bytes(&mut self, field: u32, data: &[u8]) {
+        self.key(field, 2);
+        self.raw_varint(data.len() as u64);
+        self.0.extend_from_slice(data);
+    }
+
+    fn string(&mut self, field: u32, value: &str) {
+        self.bytes(field, value.as_bytes());
+    }
+
+    fn message(&mut self, field: u32, message: Message) {
+        self.bytes(field, &message.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::NeuralNetwork;
+    use crate::components::AIController;
+    use std::collections::HashMap;
+
+    /// Поля сообщения protobuf: номер поля -> значения (varint или байты)
+    #[derive(Default)]
+    struct Decoded(HashMap<u32, Vec<Field>>);
+
+    #[derive(Clone)]
+    enum Field {
+        Varint(u64),
+        Bytes(Vec<u8>),
+    }
+
+    fn read_varint(data: &[u8], pos: &mut usize) -> u64 {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = data[*pos];
+            *pos += 1;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte < 0x80 {
+                return value;
+            }
+            shift += 7;
+        }
+    }
+
+    fn decode(data: &[u8]) -> Decoded {
+        let mut decoded = Decoded::default();
+        let mut pos = 0;
+        while pos < data.len() {
+            let key = read_varint(data, &mut pos);
+            let field = match key & 7 {
+                0 => Field::Varint(read_varint(data, &mut pos)),
+                2 => {
+                    let len = read_varint(data, &mut pos) as usize;
+                    pos += len;
+                    Field::Bytes(data[pos - len..pos].to_vec())
+                }
+                other => panic!("неожиданный тип поля {}", other),
+            };
+            decoded.0.entry((key >> 3) as u32).or_default().push(field);
+        }
+        decoded
+    }
+
+    impl Decoded {
+        fn messages(&self, field: u32) -> Vec<Decoded> {
+            self.bytes(field).iter().map(|b| decode(b)).collect()
+        }
+
+        fn bytes(&self, field: u32) -> Vec<Vec<u8>> {
+            self.0.get(&field).into_iter().flatten().filter_map(|f| match f {
+                Field::Bytes(b) => Some(b.clone()),
+                Field::Varint(_) => None,
+            }).collect()
+        }
+
+        fn strings(&self, field: u32) -> Vec<String> {
+            self.bytes(field).into_iter().map(|b| String::from_utf8(b).unwrap()).collect()
+        }
+
+        fn varints(&self, field: u32) -> Vec<u64> {
+            self.0.get(&field).into_iter().flatten().filter_map(|f| match f {
+                Field::Varint(v) => Some(*v),
+                Field::Bytes(_) => None,
+            }).collect()
+        }
+    }
+
+    /// Матрица значений построчно с формой [строки, столбцы]
+    type Matrix = (Vec<f32>, usize, usize);
+
+    /// Ось из атрибута axis узла; для матриц -1 означает столбцы
+    fn axis(node: &Decoded) -> i64 {
+        let attribute = node
+            .messages(5)
+            .into_iter()
+            .find(|attr| attr.strings(1) == ["axis"])
+            .expect("у узла нет атрибута axis");
+        match attribute.varints(3)[0] as i64 {
+            -1 => 1,
+            axis => axis,
+        }
+    }
+
+    /// Минимальный интерпретатор графа: ровно те операторы, что пишет export, со строгой
+    /// проверкой форм и осей, чтобы граф с ошибкой раскладки не прошёл тест случайно
+    fn run(model: &[u8], feeds: &[(&str, Vec<f32>)]) -> HashMap<String, Vec<f32>> {
+        let model = decode(model);
+        let graph = &model.messages(7)[0];
+        let mut values: HashMap<String, Matrix> = HashMap::new();
+        for tensor in graph.messages(5) {
+            let dims = tensor.varints(1);
+            assert_eq!(dims.len(), 2, "веса должны быть матрицей");
+            let raw = &tensor.bytes(9)[0];
+            let data: Vec<f32> = raw.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+            assert_eq!(data.len() as u64, dims[0] * dims[1], "размер данных не совпадает с формой");
+            values.insert(tensor.strings(8)[0].clone(), (data, dims[0] as usize, dims[1] as usize));
+        }
+        for (name, column) in feeds {
+            values.insert(name.to_string(), (column.clone(), column.len(), 1));
+        }
+
+        for node in graph.messages(1) {
+            let inputs: Vec<Matrix> = node.strings(1).iter().map(|name| values[name].clone()).collect();
+            let outputs = node.strings(2);
+            match node.strings(4)[0].as_str() {
+                "Concat" => {
+                    assert_eq!(axis(&node), 1, "Concat должен склеивать столбцы");
+                    let rows = inputs[0].1;
+                    assert!(inputs.iter().all(|m| m.1 == rows), "Concat: разное число строк");
+                    let width = inputs.iter().map(|m| m.2).sum();
+                    let data = (0..rows)
+                        .flat_map(|r| inputs.iter().flat_map(move |(d, _, cols)| d[r * cols..(r + 1) * cols].to_vec()))
+                        .collect();
+                    values.insert(outputs[0].clone(), (data, rows, width));
+                }
+                "MatMul" => {
+                    let ((a, rows, inner), (b, b_rows, cols)) = (&inputs[0], &inputs[1]);
+                    assert_eq!(inner, b_rows, "MatMul: формы {}x{} и {}x{} не сходятся", rows, inner, b_rows, cols);
+                    let data = (0..*rows)
+                        .flat_map(|r| (0..*cols).map(move |j| (0..*inner).map(|k| a[r * inner + k] * b[k * cols + j]).sum()))
+                        .collect();
+                    values.insert(outputs[0].clone(), (data, *rows, *cols));
+                }
+                "Sigmoid" => {
+                    let (data, rows, cols) = &inputs[0];
+                    let data = data.iter().map(|x| 1.0 / (1.0 + (-x).exp())).collect();
+                    values.insert(outputs[0].clone(), (data, *rows, *cols));
+                }
+                "Split" => {
+                    assert_eq!(axis(&node), 1, "Split должен делить столбцы");
+                    assert_eq!(inputs.len(), 1, "Split без размеров частей делит поровну");
+                    let (data, rows, cols) = &inputs[0];
+                    assert_eq!(cols % outputs.len(), 0, "Split: {} столбцов на {} частей", cols, outputs.len());
+                    let part = cols / outputs.len();
+                    for (i, output) in outputs.iter().enumerate() {
+                        let column = (0..*rows).flat_map(|r| data[r * cols + i * part..r * cols + (i + 1) * part].to_vec()).collect();
+                        values.insert(output.clone(), (column, *rows, part));
+                    }
+                }
+                other => panic!("неизвестный оператор {}", other),
+            }
+        }
+        graph
+            .messages(12)
+            .iter()
+            .map(|output| {
+                let name = output.strings(1)[0].clone();
+                let (data, _, cols) = &values[&name];
+                assert_eq!(*cols, 1, "выход {} должен быть столбцом [batch, 1]", name);
+                (name, data.clone())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn exported_graph_matches_forward() {
+        let ai = AIController::new_random();
+        let champion = ChampionFile::from_controller(&ai, "onnx", "unit", 3);
+        let model = export(&champion).unwrap();
+
+        // Оба наблюдения идут одной пачкой [2, 1] на каждый вход
+        let network = NeuralNetwork::from_genome(&ai.genome);
+        let batch = [
+            [0.0, 0.5, 0.3, -0.2, 0.1, 0.9, 0.7, -0.1, 1.0, 0.0, 0.0, 0.6, 0.2],
+            [1.0, 0.2, -0.6, 0.8, -0.9, 0.4, 0.0, 0.5, 0.0, 0.0, 1.0, 0.0, 1.0],
+        ];
+        let feeds: Vec<(&str, Vec<f32>)> = INPUT_NAMES
+            .iter()
+            .enumerate()
+            .map(|(i, &name)| (name, batch.iter().map(|inputs| inputs[i]).collect()))
+            .collect();
+        let outputs = run(&model, &feeds);
+        for (row, inputs) in batch.iter().enumerate() {
+            for (name, expected) in OUTPUT_NAMES.iter().zip(network.forward(inputs)) {
+                let actual = outputs[*name][row];
+                assert!((actual - expected).abs() < 1e-6, "{}[{}]: {} vs {}", name, row, actual, expected);
+            }
+        }
+
+        // Имена входов и метаданные доступны внешним программам
+        let decoded = decode(&model);
+        let graph = &decoded.messages(7)[0];
+        let input_names: Vec<String> = graph.messages(11).iter().map(|v| v.strings(1)[0].clone()).collect();
+        assert_eq!(input_names, INPUT_NAMES);
+        assert_eq!(decoded.messages(8)[0].varints(2), vec![OPSET_VERSION]);
+    }
+}
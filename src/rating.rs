@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+
+/// Стартовый рейтинг нового генома
+pub const DEFAULT_RATING: f32 = 1500.0;
+/// Максимальное изменение рейтинга за один бой
+pub const K_FACTOR: f32 = 32.0;
+
+/// Рейтинг Эло генома или чемпиона
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Rating {
+    pub elo: f32,
+    pub matches: u32,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Self {
+            elo: DEFAULT_RATING,
+            matches: 0,
+        }
+    }
+}
+
+impl Rating {
+    fn apply(&mut self, delta: f32) {
+        self.elo += delta;
+        self.matches += 1;
+    }
+}
+
+/// Ожидаемый результат игрока с рейтингом a против игрока с рейтингом b
+pub fn expected_score(a: f32, b: f32) -> f32 {
+    1.0 / (1.0 + 10f32.powf((b - a) / 400.0))
+}
+
+/// Очки команды 0 по итогу боя: 1 — победа, 0.5 — ничья, 0 — поражение.
+/// Побеждает команда с большим числом выживших, при равенстве — с большим суммарным здоровьем.
+pub fn team_score(alive: [u32; 2], health: [f32; 2]) -> f32 {
+    match alive[0].cmp(&alive[1]).then(health[0].total_cmp(&health[1])) {
+        std::cmp::Ordering::Greater => 1.0,
+        std::cmp::Ordering::Less => 0.0,
+        std::cmp::Ordering::Equal => 0.5,
+    }
+}
+
+/// Обновляет рейтинги по итогу командного боя: каждый участник
+/// играет против среднего рейтинга команды соперника
+pub fn update_teams(team_a: &mut [Rating], team_b: &mut [Rating], score_a: f32) {
+    if team_a.is_empty() || team_b.is_empty() {
+        return;
+    }
+    let average = |team: &[Rating]| team.iter().map(|r| r.elo).sum::<f32>() / team.len() as f32;
+    let expected_a = expected_score(average(team_a), average(team_b));
+
+    let delta_a = K_FACTOR * (score_a - expected_a);
+    for rating in team_a.iter_mut() {
+        rating.apply(delta_a);
+    }
+    for rating in team_b.iter_mut() {
+        rating.apply(-delta_a);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn winner_gains_what_loser_loses() {
+        let mut a = [Rating::default()];
+        let mut b = [Rating::default()];
+        update_teams(&mut a, &mut b, 1.0);
+        let [a] = a;
+        let [b] = b;
+        assert!((a.elo - (DEFAULT_RATING + K_FACTOR / 2.0)).abs() < 1e-3);
+        assert!((a.elo + b.elo - 2.0 * DEFAULT_RATING).abs() < 1e-3);
+        assert_eq!(b.matches, 1);
+    }
+
+    #[test]
+    fn upset_moves_ratings_more() {
+        let mut strong = [Rating { elo: 1800.0, matches: 0 }];
+        let mut weak = [Rating { elo: 1400.0, matches: 0 }];
+        update_teams(&mut weak, &mut strong, 1.0);
+        assert!(weak[0].elo - 1400.0 > K_FACTOR * 0.9);
+        assert_eq!(team_score([2, 2], [150.0, 150.0]), 0.5);
+    }
+}
@@ -0,0 +1,389 @@
+use bevy::app::PluginsState;
+use bevy::prelude::*;
+use crate::battle::{spawn_tank_any, BattleKind, BattleState};
+use crate::ai::Observation;
+use crate::bots::{self, BotStrategy};
+use crate::classes::ClassPicker;
+use crate::components::*;
+use crate::controller::{observe, TankAction, TankSnapshot};
+use crate::genetics::Population;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+
+/// Больше кадров за один шаг не даём: ответ должен приходить быстро
+const MAX_FRAMES_PER_STEP: u32 = 500;
+
+/// Сервер для внешнего агента: пока он есть, бои запускает клиент, а не популяция
+#[derive(Resource, Clone, Debug)]
+pub struct ExternalServer {
+    pub addr: String,
+}
+
+/// Танк, которым управляет внешний агент
+#[derive(Component)]
+pub struct ExternalAgent;
+
+/// Против кого играет внешний агент
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ExternalOpponent {
+    /// Лучший геном популяции
+    #[default]
+    Population,
+    Bot(BotStrategy),
+}
+
+impl ExternalOpponent {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "population" => Some(ExternalOpponent::Population),
+            other => BotStrategy::parse(other).map(ExternalOpponent::Bot),
+        }
+    }
+}
+
+/// Запрос клиента — одна строка JSON
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+enum Request {
+    /// Новый эпизод: карта и позиции задаются зерном
+    Reset {
+        #[serde(default)]
+        seed: u64,
+        #[serde(default)]
+        opponent: Option<String>,
+        #[serde(default = "one")]
+        opponents: usize,
+    },
+    /// Действие в тех же единицах, что выходы сети в ai_control_system: четыре числа 0..1
+    /// (газ, поворот, выстрел при > 0.5, оружие); держится frames тактов
+    Step {
+        action: [f32; 4],
+        #[serde(default = "one")]
+        frames: u32,
+    },
+    Observe,
+    /// Отключить клиента; сервер ждёт следующего
+    Close,
+    /// Завершить программу
+    Shutdown,
+}
+
+fn one<T: From<u8>>() -> T {
+    T::from(1)
+}
+
+/// Ответ на reset, step и observe
+#[derive(Debug, Serialize)]
+struct Response {
+    observation: Observation,
+    /// Урон врагам минус полученный урон за шаг, в долях полного здоровья
+    reward: f32,
+    done: bool,
+    tick: u32,
+    health: f32,
+    enemies_alive: u32,
+    kills: u32,
+}
+
+/// Состояние эпизода между запросами
+#[derive(Default)]
+struct Episode {
+    active: bool,
+    observation: Observation,
+}
+
+/// Заменяет стандартный цикл Bevy: мир продвигается только по запросам клиента,
+/// ровно на один такт FixedUpdate за кадр (lockstep)
+pub fn run(mut app: App) {
+    while app.plugins_state() == PluginsState::Adding {
+        bevy::tasks::tick_global_task_pools_on_main_thread();
+    }
+    app.finish();
+    app.cleanup();
+    app.update();
+
+    let addr = app.world.resource::<ExternalServer>().addr.clone();
+    let listener = match TcpListener::bind(&addr) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Не удалось открыть сокет {}: {}", addr, e);
+            std::process::exit(1);
+        }
+    };
+    println!("Сервер ждёт агента на {}", addr);
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                error!("Ошибка подключения: {}", e);
+                continue;
+            }
+        };
+        println!("Агент подключился: {:?}", stream.peer_addr().ok());
+        match serve_client(&mut app, stream) {
+            Ok(true) => break,
+            Ok(false) => println!("Агент отключился"),
+            Err(e) => println!("Связь с агентом прервана: {}", e),
+        }
+    }
+}
+
+/// Обслуживает одного клиента; Ok(true) — клиент попросил завершить сервер
+fn serve_client(app: &mut App, stream: TcpStream) -> std::io::Result<bool> {
+    let mut writer = stream.try_clone()?;
+    let mut episode = Episode::default();
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match serde_json::from_str::<Request>(&line) {
+            Ok(Request::Close) => return Ok(false),
+            Ok(Request::Shutdown) => return Ok(true),
+            Ok(request) => handle(app, &mut episode, request),
+            Err(e) => Err(format!("неверный запрос: {}", e)),
+        };
+        let reply = match reply {
+            Ok(response) => serde_json::to_string(&response)?,
+            Err(message) => serde_json::json!({ "error": message }).to_string(),
+        };
+        writeln!(writer, "{}", reply)?;
+    }
+    Ok(false)
+}
+
+fn handle(app: &mut App, episode: &mut Episode, request: Request) -> Result<Response, String> {
+    match request {
+        Request::Reset { seed, opponent, opponents } => {
+            let opponent = match opponent {
+                Some(name) => ExternalOpponent::parse(&name)
+                    .ok_or_else(|| format!("неизвестный противник {:?}", name))?,
+                None => ExternalOpponent::Population,
+            };
+            reset(app, seed, opponent, opponents.max(1));
+            episode.active = true;
+            Ok(respond(app, episode, 0.0))
+        }
+        Request::Step { action, frames } => {
+            if !episode.active {
+                return Err("эпизод не начат или завершён, нужен reset".to_string());
+            }
+            let action = TankAction::from_network(&action);
+            let before = team_health(&mut app.world);
+            for _ in 0..frames.clamp(1, MAX_FRAMES_PER_STEP) {
+                if is_done(&mut app.world) {
+                    break;
+                }
+                set_agent_action(&mut app.world, action);
+                advance_one_tick(app);
+            }
+            let after = team_health(&mut app.world);
+            let reward = (before[1] - after[1]) - (before[0] - after[0]);
+            Ok(respond(app, episode, reward))
+        }
+        Request::Observe => {
+            if !episode.active {
+                return Err("эпизод не начат, нужен reset".to_string());
+            }
+            Ok(respond(app, episode, 0.0))
+        }
+        Request::Close | Request::Shutdown => unreachable!("обрабатываются в serve_client"),
+    }
+}
+
+/// Завершает текущий бой и начинает новый с заданным зерном
+fn reset(app: &mut App, seed: u64, opponent: ExternalOpponent, opponents: usize) {
+    *app.world.resource_mut::<BattleKind>() = BattleKind::External { seed, opponent, opponents };
+    if *app.world.resource::<State<GameState>>().get() == GameState::Battle {
+        app.world.resource_mut::<NextState<GameState>>().set(GameState::Setup);
+        app.update();
+    }
+    app.world.resource_mut::<NextState<GameState>>().set(GameState::Battle);
+    app.update();
+}
+
+/// Кадр за кадром, пока FixedUpdate не сделает ровно один такт
+fn advance_one_tick(app: &mut App) {
+    let tick = app.world.resource::<BattleState>().tick_count;
+    for _ in 0..10 {
+        app.update();
+        if app.world.resource::<BattleState>().tick_count != tick {
+            return;
+        }
+    }
+}
+
+fn set_agent_action(world: &mut World, action: TankAction) {
+    let mut query = world.query_filtered::<&mut TankAction, With<ExternalAgent>>();
+    for mut agent_action in query.iter_mut(world) {
+        *agent_action = action;
+    }
+}
+
+/// Суммарное здоровье живых танков команд в долях полного здоровья каждого танка:
+/// у классов разный запас, а награда считается в тех же долях
+fn team_health(world: &mut World) -> [f32; 2] {
+    let mut health = [0.0; 2];
+    for tank in world.query::<&Tank>().iter(world).filter(|t| t.health > 0.0) {
+        health[(tank.team as usize).min(1)] += tank.health / tank.max_health;
+    }
+    health
+}
+
+fn is_done(world: &mut World) -> bool {
+    let agent_alive = world
+        .query_filtered::<&Tank, With<ExternalAgent>>()
+        .iter(world)
+        .any(|tank| tank.health > 0.0);
+    let out_of_time = {
+        let state = world.resource::<BattleState>();
+        state.tick_count >= state.max_ticks
+    };
+    !agent_alive
+        || team_health(world)[1] <= 0.0
+        || out_of_time
+        || *world.resource::<State<GameState>>().get() != GameState::Battle
+}
+
+fn respond(app: &mut App, episode: &mut Episode, reward: f32) -> Response {
+    let world = &mut app.world;
+    let snapshots = TankSnapshot::collect(world.query::<(&Transform, &Tank)>().iter(world));
+    let mut agent = world.query_filtered::<(&Transform, &Tank), With<ExternalAgent>>();
+    let health = match agent.iter(world).next() {
+        Some((transform, tank)) => {
+            episode.observation = observe(transform, tank, &snapshots);
+            tank.health
+        }
+        None => 0.0,
+    };
+    let done = is_done(world);
+    episode.active = !done;
+    let state = world.resource::<BattleState>();
+    Response {
+        observation: episode.observation,
+        reward,
+        done,
+        tick: state.tick_count,
+        health,
+        enemies_alive: snapshots.iter().filter(|t| t.team == 1 && t.health > 0.0).count() as u32,
+        kills: state.deaths[1],
+    }
+}
+
+/// Внешний агент за синих слева, противники за красных справа; всё по зерну эпизода
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_external_match(
+    commands: &mut Commands,
+    mut meshes: Option<&mut ResMut<Assets<Mesh>>>,
+    mut materials: Option<&mut ResMut<Assets<StandardMaterial>>>,
+    picker: &mut ClassPicker,
+    population: &Population,
+    seed: u64,
+    opponent: ExternalOpponent,
+    opponents: usize,
+) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let position = Vec3::new(-rng.gen_range(10.0..40.0), 0.5, rng.gen_range(-40.0..40.0));
+    let agent = spawn_tank_any(commands, meshes.as_deref_mut(), materials.as_deref_mut(), position, 0, picker.next(0), None);
+    commands.entity(agent).insert(ExternalAgent);
+
+    match opponent {
+        ExternalOpponent::Population => {
+            let best = population.best_genome.as_ref().or_else(|| population.genomes.first());
+            for _ in 0..opponents {
+                let position = Vec3::new(rng.gen_range(10.0..40.0), 0.5, rng.gen_range(-40.0..40.0));
+                let entity = spawn_tank_any(
+                    commands,
+                    meshes.as_deref_mut(),
+                    materials.as_deref_mut(),
+                    position,
+                    1,
+                    picker.next(1),
+                    best.map(AIController::battle_copy),
+                );
+                commands.entity(entity).insert(ExhibitionTank);
+            }
+        }
+        ExternalOpponent::Bot(strategy) => {
+            bots::spawn_bots(commands, meshes, materials, picker, &vec![strategy; opponents], 1, 1.0, &mut rng);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_protocol_requests() {
+        let reset: Request = serde_json::from_str(r#"{"cmd":"reset","seed":7,"opponent":"kite"}"#).unwrap();
+        assert_eq!(reset, Request::Reset { seed: 7, opponent: Some("kite".into()), opponents: 1 });
+        let step: Request = serde_json::from_str(r#"{"cmd":"step","action":[1,0.5,0.9,0]}"#).unwrap();
+        assert_eq!(step, Request::Step { action: [1.0, 0.5, 0.9, 0.0], frames: 1 });
+        assert!(serde_json::from_str::<Request>(r#"{"cmd":"jump"}"#).is_err());
+        assert_eq!(ExternalOpponent::parse("wall"), Some(ExternalOpponent::Bot(BotStrategy::WallHugger)));
+    }
+
+    /// Тот же конвейер такта, что в main, без окна, эволюции и записи результатов
+    fn lockstep_app() -> App {
+        use crate::battle::MatchQueue;
+        use crate::champion::FixedOpponents;
+        use crate::controller::{ControllerSet, PhysicsSet};
+        use crate::systems::{collision_system, projectile_movement_system, BASE_SIM_DT, BASE_SIM_HZ};
+        use bevy::time::TimeUpdateStrategy;
+        use std::time::Duration;
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(BASE_SIM_DT)))
+            .insert_resource(Time::<Fixed>::from_hz(BASE_SIM_HZ))
+            .insert_resource(crate::Headless(true))
+            .insert_resource(Population::new_fresh(4))
+            .insert_resource(FixedOpponents::default())
+            .insert_resource(MatchQueue::default())
+            .insert_resource(BattleKind::default())
+            .insert_resource(BattleState::default())
+            .insert_resource(bots::SparringBots::default())
+            .insert_resource(crate::classes::TankClasses::default())
+            .insert_resource(crate::weapons::Weapons::default())
+            .add_state::<GameState>()
+            .configure_sets(FixedUpdate, PhysicsSet.after(ControllerSet))
+            .add_systems(FixedUpdate, (
+                bots::scripted_bot_system.in_set(ControllerSet),
+                (crate::controller::apply_tank_actions, projectile_movement_system, collision_system).in_set(PhysicsSet),
+                crate::battle::check_battle_end,
+            ).run_if(in_state(GameState::Battle)))
+            .add_systems(OnEnter(GameState::Battle), (crate::battle::start_battle, crate::battle::spawn_tanks_headless));
+        app.update();
+        app
+    }
+
+    #[test]
+    fn reset_and_step_round_trip() {
+        let mut app = lockstep_app();
+        let mut episode = Episode::default();
+        let reset = |app: &mut App, episode: &mut Episode| {
+            let request = Request::Reset { seed: 7, opponent: Some("camper".into()), opponents: 1 };
+            handle(app, episode, request).unwrap()
+        };
+        let first = reset(&mut app, &mut episode);
+        assert!(!first.done);
+        assert_eq!(first.enemies_alive, 1);
+
+        // Шаг держит действие frames тактов и двигает мир ровно на столько же
+        let step = Request::Step { action: [1.0, 0.5, 0.0, 0.0], frames: 5 };
+        let stepped = handle(&mut app, &mut episode, step).unwrap();
+        assert_eq!(stepped.tick, first.tick + 5);
+        assert_ne!(stepped.observation, first.observation);
+        assert!(stepped.reward.abs() <= 2.0);
+
+        // Тот же seed повторяет эпизод с начала
+        let again = reset(&mut app, &mut episode);
+        assert_eq!(again.tick, first.tick);
+        assert_eq!(again.observation, first.observation);
+    }
+}
@@ -1,11 +1,12 @@
 use bevy::prelude::*;
-use bevy::prelude::shape;
 use bevy::time::{Fixed, TimeUpdateStrategy};
 use bevy_egui::{egui, EguiContexts};
 use crate::components::*;
 use crate::battle::BattleState;
 use crate::ai::*;
+use crate::controller::{observe, TankAction, TankSnapshot};
 use crate::map::ObstacleComponent;
+use crate::weapons::{splash_damage, Flight, WeaponDef, GRAVITY};
 use std::time::Duration;
 use std::time::Instant;
 use crate::Headless;
@@ -14,6 +15,8 @@ use crate::Headless;
 pub const BASE_SIM_HZ: f64 = 50.0;
 /// Базовый шаг времени симуляции в секундах
 pub const BASE_SIM_DT: f64 = 1.0 / BASE_SIM_HZ;
+/// Дистанция, на которой танк считается вступившим в бой
+pub const ENGAGEMENT_RANGE: f32 = 25.0;
 
 /// Ресурс для редкого логирования прогресса
 #[derive(Resource)]
@@ -170,8 +173,9 @@ pub fn time_multiplier_ui_system(
         .resizable(false)
         .show(ctx, |ui| {
             ui.label("Управление:");
-            ui.label("WASD — движение танка");
-            ui.label("Space — выстрел");
+            ui.label("P — взять управление выбранным танком / вернуть ИИ");
+            ui.label("WASD — движение танка, Q/E — башня, 1–4 — оружие");
+            ui.label("Space (удерживать) — стрельба");
             ui.label("Tab — смена вида камеры");
             ui.label("Стрелки — движение камеры");
             ui.label("+/- — zoom камеры");
@@ -180,30 +184,15 @@ pub fn time_multiplier_ui_system(
             ui.label("[ / ] — замедлить / ускорить время");
             ui.label("\\ — сбросить скорость времени");
             ui.label("F1 — показать/скрыть этот UI");
+            ui.label("F2 — архив элит (MAP-Elites)");
+            ui.label("F3 — экспорт/импорт чемпионов");
+            ui.label("F4 — бой между поколениями");
+            ui.label("F5 — графики эволюции и статистика");
+            ui.label("F6 — нейросеть выбранного танка");
         });
 }
 
-/// Система движения танков
-pub fn tank_movement_system(
-    _time: Res<Time>,
-    mut query: Query<(&Tank, &mut Transform), Without<Projectile>>,
-) {
-    for (_tank, _transform) in query.iter_mut() {
-        // Движение обрабатывается в системах AI или игрока
-    }
-}
-
-/// Система стрельбы танков
-pub fn tank_shooting_system(
-    _commands: Commands,
-    _meshes: Option<ResMut<Assets<Mesh>>>,
-    _materials: Option<ResMut<Assets<StandardMaterial>>>,
-    _query: Query<(Entity, &Transform, &Tank)>,
-) {
-    // Логика стрельбы добавляется через события
-}
-
-/// Система движения снарядов
+/// Система движения снарядов: прямой полёт, дуга под действием тяжести; мины лежат на месте
 pub fn projectile_movement_system(
     mut commands: Commands,
     time: Res<Time<Fixed>>,
@@ -211,11 +200,13 @@ pub fn projectile_movement_system(
 ) {
     for (entity, mut transform, mut projectile) in query.iter_mut() {
         let dt = time.delta_seconds();
-        // Двигаем снаряд вперед
-        let forward = transform.back();
-        transform.translation += forward * projectile.speed * dt;
+        if projectile.flight == Flight::Ballistic {
+            projectile.velocity.y -= GRAVITY * dt;
+        }
+        transform.translation += projectile.velocity * dt;
         
-        // Обновляем таймер жизни
+        // Обновляем таймеры взведения и жизни
+        projectile.armed.tick(time.delta());
         projectile.lifetime.tick(time.delta());
         if projectile.lifetime.finished() {
             commands.entity(entity).despawn();
@@ -223,266 +214,181 @@ pub fn projectile_movement_system(
     }
 }
 
-/// Система обнаружения столкновений
+/// Система обнаружения столкновений: снаряд прямого полёта рвётся о первый танк или
+/// препятствие, баллистический — о землю, мина — когда рядом чужой танк. Урон получает
+/// танк, в который попали, а при разрыве с осколками — все танки в радиусе, кроме стрелявшего
 pub fn collision_system(
     mut commands: Commands,
+    mut battle_state: ResMut<BattleState>,
     projectile_query: Query<(Entity, &Transform, &Projectile)>,
     mut tank_query: Query<(Entity, &Transform, &mut Tank), Without<Projectile>>,
     obstacle_query: Query<&Transform, With<ObstacleComponent>>,
     mut ai_query: Query<&mut AIController>,
+    exhibition_query: Query<(), With<ExhibitionTank>>,
 ) {
     for (proj_entity, proj_transform, projectile) in projectile_query.iter() {
-        for (tank_entity, tank_transform, mut tank) in tank_query.iter_mut() {
-            // Не попадаем в себя
-            if tank_entity == projectile.owner {
-                continue;
+        let position = proj_transform.translation;
+        // Не попадаем в себя; мину подрывают только противники
+        let hit = tank_query
+            .iter()
+            .find(|(entity, transform, tank)| {
+                *entity != projectile.owner
+                    && (projectile.flight != Flight::Mine || tank.team != projectile.team)
+                    && position.distance(transform.translation) < tank.hit_distance(projectile.hit_radius)
+            })
+            .map(|(entity, ..)| entity);
+        let detonated = match projectile.flight {
+            // Увеличенный радиус для стен и препятствий
+            Flight::Direct => {
+                hit.is_some() || obstacle_query.iter().any(|obstacle| position.distance(obstacle.translation) < 5.0)
             }
-            
-            let distance = proj_transform.translation.distance(tank_transform.translation);
-            if distance < 1.5 { // Радиус попадания
-                tank.health -= projectile.damage;
-                commands.entity(proj_entity).despawn();
-                
-                if tank.health <= 0.0 {
-                    // Увеличиваем счётчик убийств у владельца снаряда
-                    if let Ok(mut ai) = ai_query.get_mut(projectile.owner) {
-                        ai.kills += 1;
-                    }
-                    commands.entity(tank_entity).despawn_recursive();
-                }
-                break;
+            Flight::Ballistic => position.y <= 0.0,
+            Flight::Mine => projectile.armed.finished() && hit.is_some(),
+        };
+        if !detonated {
+            continue;
+        }
+        commands.entity(proj_entity).despawn();
+
+        // Осколки задевают только противников, прямое попадание — любого
+        let victims: Vec<(Entity, f32)> = if projectile.splash_radius > 0.0 {
+            tank_query
+                .iter()
+                .filter(|(entity, _, tank)| *entity != projectile.owner && tank.team != projectile.team)
+                .filter_map(|(entity, transform, _)| {
+                    splash_damage(projectile.damage, projectile.splash_radius, position.distance(transform.translation))
+                        .map(|damage| (entity, damage))
+                })
+                .collect()
+        } else {
+            hit.map(|entity| (entity, projectile.damage)).into_iter().collect()
+        };
+        if !victims.is_empty() {
+            if let Ok(mut ai) = ai_query.get_mut(projectile.owner) {
+                ai.behavior.hits += 1;
             }
         }
-        
-        // Проверяем столкновение с препятствиями
-        for obstacle_transform in obstacle_query.iter() {
-            let distance = proj_transform.translation.distance(obstacle_transform.translation);
-            if distance < 5.0 { // Увеличенный радиус для стен и препятствий
-                commands.entity(proj_entity).despawn();
-                break;
+
+        for (tank_entity, damage) in victims {
+            let Ok((_, _, mut tank)) = tank_query.get_mut(tank_entity) else {
+                continue;
+            };
+            let was_alive = tank.health > 0.0;
+            tank.health -= damage;
+            if was_alive && tank.health <= 0.0 {
+                // Увеличиваем счётчик убийств у владельца снаряда
+                if let Ok(mut ai) = ai_query.get_mut(projectile.owner) {
+                    ai.kills += 1;
+                }
+                battle_state.deaths[(tank.team as usize).min(1)] += 1;
+                // Запоминаем павшего, чтобы его результат попал в популяцию
+                if let Ok(ai) = ai_query.get(tank_entity) {
+                    if !exhibition_query.contains(tank_entity) {
+                        battle_state.fallen.push((tank.team, ai.clone()));
+                    }
+                }
+                commands.entity(tank_entity).despawn_recursive();
             }
         }
     }
 }
 
-/// Система управления танком игроком
+/// Управление танком игроком: W/S — газ, A/D — поворот, Q/E — башня, пробел — стрельба
+/// (пока пробел зажат, орудие стреляет по готовности, как у ИИ), 1–4 — оружие класса
 pub fn player_control_system(
     keyboard: Res<Input<KeyCode>>,
-    time: Res<Time<Fixed>>,
-    headless: Res<Headless>,
-    mut commands: Commands,
-    mut meshes: Option<ResMut<Assets<Mesh>>>,
-    mut materials: Option<ResMut<Assets<StandardMaterial>>>,
-    mut query: Query<(Entity, &mut Transform, &Tank, &mut FireCooldown, &TeamColor), With<PlayerControlled>>,
-    all_tanks: Query<(&Transform, &Tank), Without<PlayerControlled>>,
-    obstacle_query: Query<&Transform, (With<ObstacleComponent>, Without<Tank>)>,
+    mut weapon: Local<usize>,
+    mut query: Query<(&Tank, &mut TankAction), With<PlayerControlled>>,
 ) {
-    for (entity, mut transform, tank, mut cooldown, team_color) in query.iter_mut() {
-        let mut direction = Vec3::ZERO;
-        let mut rotation = 0.0;
-        let dt = time.delta_seconds();
-        cooldown.timer.tick(time.delta());
-        
-        if keyboard.pressed(KeyCode::W) {
-            direction += transform.forward();
-        }
-        if keyboard.pressed(KeyCode::S) {
-            direction -= transform.forward();
-        }
-        if keyboard.pressed(KeyCode::A) {
-            rotation += tank.rotation_speed;
-        }
-        if keyboard.pressed(KeyCode::D) {
-            rotation -= tank.rotation_speed;
-        }
-        
-        transform.translation += direction * tank.speed * dt;
-        
-        // Проверяем столкновение с препятствиями
-        let mut collided = false;
-        for obstacle_transform in obstacle_query.iter() {
-            let distance = transform.translation.distance(obstacle_transform.translation);
-            if distance < 6.0 {
-                collided = true;
-                break;
-            }
-        }
-        
-        // Проверяем столкновение с другими танками
-        if !collided {
-            for (other_transform, _other_tank) in all_tanks.iter() {
-                let distance = transform.translation.distance(other_transform.translation);
-                if distance < 3.0 {
-                    collided = true;
-                    break;
-                }
-            }
-        }
-        
-        // Если столкновение, откатываем движение
-        if collided {
-            transform.translation -= direction * tank.speed * dt;
-        }
-        
-        transform.rotate_y(rotation * dt);
-        
-        // Фиксируем высоту танка на поверхности
-        transform.translation.y = 0.5;
-        
-        // Стрельба на пробел с кулдауном
-        if keyboard.just_pressed(KeyCode::Space) && cooldown.timer.finished() {
-            spawn_projectile(&mut commands, meshes.as_mut(), materials.as_mut(), entity, &transform, team_color.0, headless.0);
-            cooldown.timer.reset();
-        }
+    let axis = |positive: KeyCode, negative: KeyCode| {
+        keyboard.pressed(positive) as i32 as f32 - keyboard.pressed(negative) as i32 as f32
+    };
+    let keys = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4];
+    if let Some(index) = keys.iter().position(|&key| keyboard.just_pressed(key)) {
+        *weapon = index;
+    }
+    for (tank, mut action) in query.iter_mut() {
+        // Середина доли выбранного оружия на шкале выхода сети
+        let count = tank.weapons.len().max(1);
+        *action = TankAction {
+            throttle: axis(KeyCode::W, KeyCode::S),
+            turn: axis(KeyCode::A, KeyCode::D),
+            turret: axis(KeyCode::Q, KeyCode::E),
+            fire: keyboard.pressed(KeyCode::Space),
+            weapon: ((*weapon).min(count - 1) as f32 + 0.5) / count as f32,
+        };
     }
 }
 
-/// Система управления танком через ИИ
+/// Управление танком нейросетью: входы собираются по ближайшему врагу среди всех танков
+#[allow(clippy::type_complexity)]
 pub fn ai_control_system(
     time: Res<Time<Fixed>>,
-    headless: Res<Headless>,
-    mut commands: Commands,
-    mut meshes: Option<ResMut<Assets<Mesh>>>,
-    mut materials: Option<ResMut<Assets<StandardMaterial>>>,
-    mut ai_tanks: Query<(Entity, &mut Transform, &Tank, &mut AIController, &mut FireCooldown, &TeamColor), Without<PlayerControlled>>,
-    all_tanks: Query<(&Transform, &Tank), Without<AIController>>,
-    obstacle_query: Query<&Transform, (With<ObstacleComponent>, Without<Tank>)>,
+    mut ai_tanks: Query<
+        (&Transform, &Tank, &NeuralNetwork, &mut NetworkActivity, &mut AIController, &mut TankAction),
+        Without<PlayerControlled>,
+    >,
+    all_tanks: Query<(&Transform, &Tank)>,
+    mut batch: Local<Vec<NetworkActivity>>,
 ) {
-    for (entity, mut transform, tank, mut ai, mut cooldown, team_color) in ai_tanks.iter_mut() {
-        let dt = time.delta_seconds();
+    let dt = time.delta_seconds();
+    let snapshots = TankSnapshot::collect(all_tanks.iter());
 
+    // Собираем входы всех танков, чтобы прогнать сети одной пачкой
+    batch.clear();
+    let mut networks = Vec::with_capacity(ai_tanks.iter().len());
+    for (transform, tank, network, _, mut ai, _) in ai_tanks.iter_mut() {
         ai.survival_time += dt;
-        cooldown.timer.tick(time.delta());
-        
-        // Находим ближайшего врага
-        let mut nearest_enemy_pos = None;
-        let mut nearest_enemy_health = None;
-        let mut min_distance = f32::MAX;
-        
-        for (other_transform, other_tank) in all_tanks.iter() {
-            if other_tank.team != tank.team {
-                let distance = transform.translation.distance(other_transform.translation);
-                if distance < min_distance {
-                    min_distance = distance;
-                    nearest_enemy_pos = Some(other_transform.translation);
-                    nearest_enemy_health = Some(other_tank.health);
-                }
-            }
-        }
-        
-        // Получаем входные данные для нейронной сети
-        let current_angle = transform.rotation.to_euler(EulerRot::YXZ).0;
-        let inputs = NeuralNetwork::get_inputs(
-            transform.translation,
-            current_angle,
-            tank.health,
-            nearest_enemy_pos,
-            nearest_enemy_health,
-        );
-        
-        // Создаем нейронную сеть и получаем выходы
-        let nn = NeuralNetwork::from_genome(&ai.genome);
-        let outputs = nn.forward(&inputs);
-        
-        // Интерпретируем выходы: [0] - движение вперед/назад (0..1 -> -1..1), [1] - поворот (0..1 -> -1..1)
-        let move_input = (outputs[0] - 0.5) * 2.0;
-        let turn_input = (outputs[1] - 0.5) * 2.0;
-        
-        // Применяем движение
-        let forward = transform.forward();
-        let old_pos = transform.translation;
-        transform.translation += forward * move_input * tank.speed * dt;
-        
-        // Проверяем столкновение с препятствиями
-        let mut collided = false;
-        for obstacle_transform in obstacle_query.iter() {
-            let distance = transform.translation.distance(obstacle_transform.translation);
-            if distance < 6.0 { // Увеличенный радиус
-                collided = true;
-                break;
-            }
-        }
-        
-        // Проверяем столкновение с другими танками
-        if !collided {
-            for (other_transform, _other_tank) in all_tanks.iter() {
-                let distance = transform.translation.distance(other_transform.translation);
-                if distance < 3.0 { // Радиус двух танков
-                    collided = true;
-                    break;
-                }
-            }
-        }
-        
-        // Если столкновение, откатываем движение
-        if collided {
-            transform.translation = old_pos;
-        }
-        
-        // Применяем поворот
-        transform.rotate_y(turn_input * tank.rotation_speed * dt);
-        
-        // Фиксируем высоту танка на поверхности
-        transform.translation.y = 0.5;
-        
-        // Логируем высоту для отладки
-        if entity.index() == 0 {
-            info!("AI Tank 0 y: {}, obstacles: {}", transform.translation.y, obstacle_query.iter().count());
-        }
-        
-        // Стрельба: если outputs[2] > 0.5 и cooldown готов
-        if outputs[2] > 0.5 && cooldown.timer.finished() {
-            spawn_projectile(&mut commands, meshes.as_mut(), materials.as_mut(), entity, &transform, team_color.0, headless.0);
-            cooldown.timer.reset();
+
+        let nearest = TankSnapshot::nearest_enemy(&snapshots, transform.translation, tank.team);
+        if nearest.is_some_and(|enemy| enemy.position.distance(transform.translation) < ENGAGEMENT_RANGE) {
+            ai.behavior.engaged_time += dt;
         }
+
+        batch.push(NetworkActivity {
+            inputs: observe(transform, tank, &snapshots),
+            ..default()
+        });
+        networks.push(network);
+    }
+
+    NeuralNetwork::forward_batch(&networks, &mut batch);
+
+    // Порядок обхода запроса между двумя проходами не меняется
+    for ((.., mut activity, _, mut action), row) in ai_tanks.iter_mut().zip(batch.iter()) {
+        *activity = *row;
+        *action = TankAction::from_network(&row.outputs);
     }
 }
 
-/// Вспомогательная функция для создания снаряда
-fn spawn_projectile(
+/// Вспомогательная функция для создания снаряда (см. WeaponDef::launch)
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_projectile(
     commands: &mut Commands,
     meshes: Option<&mut ResMut<Assets<Mesh>>>,
     materials: Option<&mut ResMut<Assets<StandardMaterial>>>,
-    owner: Entity,
-    transform: &Transform,
+    weapon: &WeaponDef,
+    transform: Transform,
+    projectile: Projectile,
     color: Color,
     headless: bool,
 ) {
-    // Позиция конца ствола: turret center + barrel offset
-    let turret_offset = Vec3::Y * 0.75;
-    let barrel_end_offset = Vec3::new(0.0, 0.0, 1.25); // конец ствола от turret center
-    let barrel_end_pos = transform.translation + transform.rotation * (turret_offset + barrel_end_offset);
-    
     if headless {
-        commands.spawn((
-            Transform::from_translation(barrel_end_pos).with_rotation(transform.rotation),
-            GlobalTransform::default(),
-            Projectile {
-                damage: 20.0,
-                speed: 30.0,
-                lifetime: Timer::from_seconds(3.0, TimerMode::Once),
-                owner,
-            },
-        ));
+        commands.spawn((transform, GlobalTransform::default(), projectile));
     } else {
         let Some(meshes) = meshes else { return; };
         let Some(materials) = materials else { return; };
         commands.spawn((
             PbrBundle {
-                mesh: meshes.add(Mesh::from(shape::UVSphere { radius: 0.3, ..default() })),
+                mesh: meshes.add(weapon.mesh()),
                 material: materials.add(StandardMaterial {
                     base_color: color,
                     ..default()
                 }),
-                transform: Transform::from_translation(barrel_end_pos).with_rotation(transform.rotation),
+                transform,
                 ..default()
             },
-            Projectile {
-                damage: 20.0,
-                speed: 30.0,
-                lifetime: Timer::from_seconds(3.0, TimerMode::Once),
-                owner,
-            },
+            projectile,
         ));
     }
 }
@@ -493,3 +399,103 @@ pub fn health_display_system(
 ) {
     // Позже добавим UI для отображения здоровья
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classes::{ClassDef, TankClass};
+    use bevy::ecs::system::RunSystemOnce;
+
+    fn tank(world: &mut World, team: u32, x: f32) -> Entity {
+        world.spawn((Transform::from_xyz(x, 0.5, 0.0), Tank { team, ..default() })).id()
+    }
+
+    fn shell(owner: Entity, flight: Flight, hit_radius: f32, splash_radius: f32) -> Projectile {
+        Projectile {
+            damage: 10.0,
+            velocity: Vec3::ZERO,
+            lifetime: Timer::from_seconds(3.0, TimerMode::Once),
+            armed: Timer::from_seconds(0.0, TimerMode::Once),
+            owner,
+            team: 0,
+            flight,
+            hit_radius,
+            splash_radius,
+        }
+    }
+
+    #[test]
+    fn larger_hulls_are_easier_to_hit() {
+        let mut world = World::new();
+        world.insert_resource(BattleState::default());
+        let owner = world.spawn(Tank::default()).id();
+        let spawn = |world: &mut World, class: TankClass, x: f32| {
+            world.spawn((Transform::from_xyz(x, 0.5, 0.0), Tank::of_class(&ClassDef::builtin(class), 1))).id()
+        };
+        let medium = spawn(&mut world, TankClass::Medium, 20.0);
+        let heavy = spawn(&mut world, TankClass::Heavy, 40.0);
+        // По снаряду пушки в 1.7 м от центра каждого: мимо среднего, в тяжёлого
+        for x in [21.7, 41.7] {
+            world.spawn((Transform::from_xyz(x, 0.5, 0.0), shell(owner, Flight::Direct, 1.5, 0.0)));
+        }
+        world.run_system_once(collision_system);
+
+        assert_eq!(world.get::<Tank>(medium).unwrap().health, 100.0);
+        assert_eq!(world.get::<Tank>(heavy).unwrap().health, 150.0);
+        assert_eq!(ClassDef::builtin(TankClass::Medium).radius(), crate::classes::MEDIUM_RADIUS);
+        assert!(ClassDef::builtin(TankClass::Light).radius() < ClassDef::builtin(TankClass::Heavy).radius());
+    }
+
+    #[test]
+    fn mines_wait_for_arming_and_spare_teammates() {
+        let mut world = World::new();
+        world.insert_resource(BattleState::default());
+        let owner = tank(&mut world, 0, -20.0);
+        let teammate = tank(&mut world, 0, 0.0);
+        let mine = Projectile {
+            armed: Timer::from_seconds(1.5, TimerMode::Once),
+            ..shell(owner, Flight::Mine, 2.5, 0.0)
+        };
+        let mine = world.spawn((Transform::from_xyz(0.0, 0.0, 0.0), mine)).id();
+        let enemy = tank(&mut world, 1, 1.0);
+
+        // Пока мина не взведена, противник проезжает над ней
+        world.run_system_once(collision_system);
+        assert!(world.get_entity(mine).is_some());
+
+        // Взведённую мину свой танк не подрывает
+        world.get_mut::<Projectile>(mine).unwrap().armed.tick(Duration::from_secs(2));
+        world.get_mut::<Transform>(enemy).unwrap().translation.x = 10.0;
+        world.run_system_once(collision_system);
+        assert!(world.get_entity(mine).is_some());
+
+        // Противник подрывает взведённую мину
+        world.get_mut::<Transform>(enemy).unwrap().translation.x = 1.0;
+        world.run_system_once(collision_system);
+        assert!(world.get_entity(mine).is_none());
+        assert_eq!(world.get::<Tank>(enemy).unwrap().health, 90.0);
+        assert_eq!(world.get::<Tank>(teammate).unwrap().health, 100.0);
+    }
+
+    #[test]
+    fn splash_hits_enemies_with_falloff() {
+        let mut world = World::new();
+        world.insert_resource(BattleState::default());
+        let owner = tank(&mut world, 0, 1.0);
+        let teammate = tank(&mut world, 0, -1.0);
+        let near = tank(&mut world, 1, 0.0);
+        let far = tank(&mut world, 1, 0.0);
+        world.get_mut::<Transform>(far).unwrap().translation.z = 2.0;
+        let outside = tank(&mut world, 1, 5.0);
+        // Снаряд артиллерии разрывается на земле между танками
+        world.spawn((Transform::from_xyz(0.0, 0.0, 0.0), shell(owner, Flight::Ballistic, 1.5, 4.0)));
+        world.run_system_once(collision_system);
+
+        let health = |world: &World, entity| world.get::<Tank>(entity).unwrap().health;
+        assert_eq!(health(&world, owner), 100.0);
+        assert_eq!(health(&world, teammate), 100.0);
+        assert_eq!(health(&world, outside), 100.0);
+        let (near, far) = (100.0 - health(&world, near), 100.0 - health(&world, far));
+        assert!(near > far && far > 5.0, "урон {} и {}", near, far);
+    }
+}
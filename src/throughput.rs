@@ -0,0 +1,208 @@
+use bevy::prelude::*;
+use crate::genetics::Population;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Зерно сценария замера скорости по умолчанию
+pub const DEFAULT_SEED: u64 = 42;
+/// Отчёт по умолчанию
+pub const DEFAULT_REPORT: &str = "bench.json";
+
+/// Замер скорости симуляции (--bench): headless-эволюция с фиксированным зерном,
+/// время тактов разложено на ИИ, физику и эволюцию
+#[derive(Resource)]
+pub struct ThroughputBench {
+    pub seed: u64,
+    /// Остановиться после стольких поколений
+    pub max_generations: Option<u32>,
+    /// Или после стольких тактов FixedUpdate
+    pub max_ticks: Option<u64>,
+    pub output: PathBuf,
+    pub start_generation: u32,
+    pub ticks: u64,
+    pub battles: u32,
+    pub generations: u32,
+    pub ai: Duration,
+    pub physics: Duration,
+    pub evolution: Duration,
+    started: Option<Instant>,
+    /// Начало текущего отрезка такта или эволюции
+    mark: Instant,
+    finished: bool,
+}
+
+/// Итог замера; по нему сравнивают версии
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ThroughputReport {
+    pub version: String,
+    pub seed: u64,
+    pub population_size: usize,
+    pub generations: u32,
+    pub battles: u32,
+    pub ticks: u64,
+    pub wall_seconds: f64,
+    pub ticks_per_second: f64,
+    pub battles_per_minute: f64,
+    pub ai_seconds: f64,
+    pub physics_seconds: f64,
+    pub evolution_seconds: f64,
+    /// Всё остальное: начало и конец боёв, состояния, планировщик
+    pub other_seconds: f64,
+}
+
+impl ThroughputBench {
+    pub fn new(seed: u64, max_generations: Option<u32>, max_ticks: Option<u64>, output: PathBuf) -> Self {
+        Self {
+            seed,
+            max_generations,
+            max_ticks,
+            output,
+            start_generation: 0,
+            ticks: 0,
+            battles: 0,
+            generations: 0,
+            ai: Duration::ZERO,
+            physics: Duration::ZERO,
+            evolution: Duration::ZERO,
+            started: None,
+            mark: Instant::now(),
+            finished: false,
+        }
+    }
+
+    /// Время от начала отрезка; следующий отрезок начинается сейчас
+    fn lap(&mut self) -> Duration {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.mark);
+        self.mark = now;
+        elapsed
+    }
+
+    pub fn report(&self, wall: Duration, population_size: usize) -> ThroughputReport {
+        let wall_seconds = wall.as_secs_f64();
+        let per_second = |count: f64| if wall_seconds > 0.0 { count / wall_seconds } else { 0.0 };
+        let measured = self.ai + self.physics + self.evolution;
+        ThroughputReport {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            seed: self.seed,
+            population_size,
+            generations: self.generations,
+            battles: self.battles,
+            ticks: self.ticks,
+            wall_seconds,
+            ticks_per_second: per_second(self.ticks as f64),
+            battles_per_minute: per_second(self.battles as f64) * 60.0,
+            ai_seconds: self.ai.as_secs_f64(),
+            physics_seconds: self.physics.as_secs_f64(),
+            evolution_seconds: self.evolution.as_secs_f64(),
+            other_seconds: wall.saturating_sub(measured).as_secs_f64(),
+        }
+    }
+
+    /// Печатает итог, пишет JSON-отчёт и завершает программу
+    fn finish(&mut self, population_size: usize, exit: &mut EventWriter<bevy::app::AppExit>) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+        let wall = self.started.map(|started| started.elapsed()).unwrap_or_default();
+        let report = self.report(wall, population_size);
+        let share = |seconds: f64| if report.wall_seconds > 0.0 { seconds / report.wall_seconds * 100.0 } else { 0.0 };
+        println!(
+            "Замер: поколений {}, боёв {}, тактов {} за {:.1} с — {:.0} тактов/с, {:.1} боёв/мин",
+            report.generations, report.battles, report.ticks, report.wall_seconds, report.ticks_per_second, report.battles_per_minute
+        );
+        println!(
+            "  ИИ {:.2} с ({:.0}%), физика {:.2} с ({:.0}%), эволюция {:.2} с ({:.0}%), прочее {:.2} с ({:.0}%)",
+            report.ai_seconds,
+            share(report.ai_seconds),
+            report.physics_seconds,
+            share(report.physics_seconds),
+            report.evolution_seconds,
+            share(report.evolution_seconds),
+            report.other_seconds,
+            share(report.other_seconds)
+        );
+        let written = serde_json::to_string_pretty(&report)
+            .map_err(std::io::Error::from)
+            .and_then(|json| std::fs::write(&self.output, json));
+        match written {
+            Ok(()) => println!("Отчёт замера сохранён в {}", self.output.display()),
+            Err(e) => eprintln!("Не удалось сохранить отчёт замера {}: {}", self.output.display(), e),
+        }
+        exit.send(bevy::app::AppExit);
+    }
+}
+
+/// Начало такта FixedUpdate (до контроллеров); первый такт запускает часы
+pub fn tick_started(mut bench: ResMut<ThroughputBench>, population: Res<Population>) {
+    if bench.started.is_none() {
+        bench.started = Some(Instant::now());
+        bench.start_generation = population.generation;
+    }
+    bench.lap();
+}
+
+/// Контроллеры отработали: время ИИ
+pub fn ai_finished(mut bench: ResMut<ThroughputBench>) {
+    let elapsed = bench.lap();
+    bench.ai += elapsed;
+}
+
+/// Физика отработала: время физики и счёт тактов
+pub fn physics_finished(
+    mut bench: ResMut<ThroughputBench>,
+    population: Res<Population>,
+    mut exit: EventWriter<bevy::app::AppExit>,
+) {
+    let elapsed = bench.lap();
+    bench.physics += elapsed;
+    bench.ticks += 1;
+    if bench.max_ticks.is_some_and(|max| bench.ticks >= max) {
+        bench.finish(population.genomes.len(), &mut exit);
+    }
+}
+
+pub fn battle_finished(mut bench: ResMut<ThroughputBench>) {
+    bench.battles += 1;
+}
+
+pub fn evolution_started(mut bench: ResMut<ThroughputBench>) {
+    bench.lap();
+}
+
+/// После evolve_population: время эволюции и проверка числа поколений
+pub fn evolution_finished(
+    mut bench: ResMut<ThroughputBench>,
+    population: Res<Population>,
+    mut exit: EventWriter<bevy::app::AppExit>,
+) {
+    let elapsed = bench.lap();
+    bench.evolution += elapsed;
+    bench.generations = population.generation.saturating_sub(bench.start_generation);
+    if bench.max_generations.is_some_and(|max| bench.generations >= max) {
+        bench.finish(population.genomes.len(), &mut exit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_rates_and_time_split() {
+        let mut bench = ThroughputBench::new(7, Some(2), None, "bench.json".into());
+        bench.ticks = 5000;
+        bench.battles = 6;
+        bench.ai = Duration::from_secs(3);
+        bench.physics = Duration::from_secs(4);
+        bench.evolution = Duration::from_secs(1);
+
+        let report = bench.report(Duration::from_secs(10), 20);
+        assert_eq!(report.ticks_per_second, 500.0);
+        assert_eq!(report.battles_per_minute, 36.0);
+        assert!((report.other_seconds - 2.0).abs() < 1e-9);
+        assert_eq!(report.seed, 7);
+    }
+}
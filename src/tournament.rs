@@ -0,0 +1,416 @@
+use bevy::prelude::*;
+use crate::battle::BattleKind;
+use crate::champion::ChampionFile;
+use crate::checkpoint::{load_population, write_atomic, SaveError};
+use crate::components::*;
+use crate::GameState;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+
+/// Формат турнира
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum TournamentFormat {
+    /// Каждый с каждым
+    RoundRobin,
+    /// Олимпийская система: выбывание после первого поражения
+    SingleElimination,
+    /// Выбывание после второго поражения (упрощённая двойная сетка)
+    DoubleElimination,
+}
+
+impl TournamentFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "round-robin" => Some(Self::RoundRobin),
+            "single" => Some(Self::SingleElimination),
+            "double" => Some(Self::DoubleElimination),
+            _ => None,
+        }
+    }
+
+    /// Сколько поражений выдерживает участник (None — не выбывает)
+    fn lives(&self) -> Option<u32> {
+        match self {
+            Self::RoundRobin => None,
+            Self::SingleElimination => Some(1),
+            Self::DoubleElimination => Some(2),
+        }
+    }
+}
+
+/// Участник турнира
+pub struct Entrant {
+    pub name: String,
+    pub controller: AIController,
+}
+
+impl Entrant {
+    /// Загружает участника из файла чемпиона или из сохранения популяции (берётся лучший геном)
+    pub fn load(path: &Path) -> Result<Self, SaveError> {
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+
+        let champion_error = match ChampionFile::load(path) {
+            Ok(champion) => {
+                return Ok(Self {
+                    name,
+                    controller: champion.to_controller(),
+                })
+            }
+            Err(e) => e,
+        };
+        let population = load_population(path).map_err(|_| champion_error)?;
+        let best = population
+            .best_genome
+            .clone()
+            .or_else(|| population.genomes.first().cloned())
+            .ok_or_else(|| SaveError::IncompatibleNetwork(path.to_path_buf(), "в сохранении нет геномов".to_string()))?;
+        Ok(Self {
+            name: format!("{} (поколение {})", name, population.generation),
+            controller: best,
+        })
+    }
+}
+
+/// Строка турнирной таблицы
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Standing {
+    pub name: String,
+    pub played: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    /// 3 за победу, 1 за ничью
+    pub points: u32,
+    /// Раунд, в котором участник выбыл (для сеток на выбывание)
+    pub eliminated_in_round: Option<u32>,
+}
+
+/// Сыгранный матч турнира
+#[derive(Clone, Debug, Serialize)]
+pub struct TournamentMatch {
+    pub round: u32,
+    pub entrant_a: usize,
+    pub entrant_b: usize,
+    /// 1 — победа A, 0.5 — ничья, 0 — победа B
+    pub score_a: f32,
+}
+
+/// Турнир чемпионов: расписание, результаты и таблица
+#[derive(Resource)]
+pub struct Tournament {
+    pub format: TournamentFormat,
+    /// Танков в каждой команде (1 — дуэль)
+    pub team_size: usize,
+    pub entrants: Vec<Entrant>,
+    pub standings: Vec<Standing>,
+    pub matches: Vec<TournamentMatch>,
+    pub round: u32,
+    pending: VecDeque<(usize, usize)>,
+    /// Куда записать таблицу: <output>.json и <output>.csv
+    pub output: PathBuf,
+    /// Завершить программу после турнира (headless)
+    pub exit_when_done: bool,
+    pub finished: bool,
+}
+
+impl Tournament {
+    pub fn new(format: TournamentFormat, team_size: usize, entrants: Vec<Entrant>, output: PathBuf) -> Self {
+        let standings = entrants
+            .iter()
+            .map(|e| Standing {
+                name: e.name.clone(),
+                ..default()
+            })
+            .collect();
+        let mut tournament = Self {
+            format,
+            team_size: team_size.max(1),
+            entrants,
+            standings,
+            matches: Vec::new(),
+            round: 0,
+            pending: VecDeque::new(),
+            output,
+            exit_when_done: false,
+            finished: false,
+        };
+        tournament.schedule_round();
+        tournament
+    }
+
+    /// Участники, ещё не выбывшие из турнира, в порядке посева
+    fn active(&self) -> Vec<usize> {
+        (0..self.entrants.len())
+            .filter(|&i| self.standings[i].eliminated_in_round.is_none())
+            .collect()
+    }
+
+    /// Составляет расписание следующего раунда
+    fn schedule_round(&mut self) {
+        self.round += 1;
+        match self.format.lives() {
+            None => {
+                // Круговой турнир расписывается целиком в первом раунде
+                if self.round == 1 {
+                    for a in 0..self.entrants.len() {
+                        for b in a + 1..self.entrants.len() {
+                            self.pending.push_back((a, b));
+                        }
+                    }
+                }
+            }
+            Some(_) => {
+                let active = self.active();
+                if active.len() < 2 {
+                    return;
+                }
+                // Сначала пары внутри сеток с равным числом поражений,
+                // оставшиеся без пары играют между сетками
+                let mut leftovers = Vec::new();
+                for losses in 0..2 {
+                    let group: Vec<usize> = active
+                        .iter()
+                        .copied()
+                        .filter(|&i| self.standings[i].losses.min(1) == losses)
+                        .collect();
+                    for pair in group.chunks(2) {
+                        match pair {
+                            [a, b] => self.pending.push_back((*a, *b)),
+                            [a] => leftovers.push(*a),
+                            _ => {}
+                        }
+                    }
+                }
+                if let [a, b] = leftovers[..] {
+                    self.pending.push_back((a, b));
+                }
+            }
+        }
+    }
+
+    /// Следующий матч; для сеток на выбывание при необходимости составляет новый раунд
+    pub fn next_match(&mut self) -> Option<(usize, usize)> {
+        if self.pending.is_empty() && self.format.lives().is_some() && self.active().len() >= 2 {
+            self.schedule_round();
+        }
+        let next = self.pending.pop_front();
+        if next.is_none() {
+            self.finished = true;
+        }
+        next
+    }
+
+    /// Записывает результат матча
+    pub fn record(&mut self, entrant_a: usize, entrant_b: usize, score_a: f32) {
+        self.matches.push(TournamentMatch {
+            round: self.round,
+            entrant_a,
+            entrant_b,
+            score_a,
+        });
+
+        // В сетках на выбывание ничьих нет: при полном равенстве проходит участник с лучшим посевом
+        let score_a = match self.format.lives() {
+            Some(_) if score_a == 0.5 => 1.0,
+            _ => score_a,
+        };
+        for (entrant, score) in [(entrant_a, score_a), (entrant_b, 1.0 - score_a)] {
+            let standing = &mut self.standings[entrant];
+            standing.played += 1;
+            if score > 0.5 {
+                standing.wins += 1;
+                standing.points += 3;
+            } else if score < 0.5 {
+                standing.losses += 1;
+            } else {
+                standing.draws += 1;
+                standing.points += 1;
+            }
+            if let Some(lives) = self.format.lives() {
+                if standing.losses >= lives && standing.eliminated_in_round.is_none() {
+                    standing.eliminated_in_round = Some(self.round);
+                }
+            }
+        }
+    }
+
+    /// Индексы участников от первого места к последнему
+    pub fn ranking(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.entrants.len()).collect();
+        order.sort_by(|&a, &b| {
+            let (sa, sb) = (&self.standings[a], &self.standings[b]);
+            let survived = |s: &Standing| s.eliminated_in_round.map_or(u32::MAX, |r| r);
+            survived(sb)
+                .cmp(&survived(sa))
+                .then(sb.points.cmp(&sa.points))
+                .then(sb.wins.cmp(&sa.wins))
+                .then(a.cmp(&b))
+        });
+        order
+    }
+
+    /// Турнирная таблица в виде текста
+    pub fn standings_table(&self) -> String {
+        let mut table = format!(
+            "{:>4}  {:<32} {:>4} {:>4} {:>4} {:>4} {:>5}\n",
+            "#", "участник", "игр", "поб", "нич", "пор", "очки"
+        );
+        for (place, &i) in self.ranking().iter().enumerate() {
+            let s = &self.standings[i];
+            table.push_str(&format!(
+                "{:>4}  {:<32} {:>4} {:>4} {:>4} {:>4} {:>5}\n",
+                place + 1,
+                s.name,
+                s.played,
+                s.wins,
+                s.draws,
+                s.losses,
+                s.points
+            ));
+        }
+        table
+    }
+
+    /// Записывает таблицу и матчи в <output>.json и таблицу в <output>.csv
+    pub fn write_reports(&self) -> std::io::Result<(PathBuf, PathBuf)> {
+        #[derive(Serialize)]
+        struct Row<'a> {
+            rank: usize,
+            #[serde(flatten)]
+            standing: &'a Standing,
+        }
+        #[derive(Serialize)]
+        struct Report<'a> {
+            format: TournamentFormat,
+            team_size: usize,
+            standings: Vec<Row<'a>>,
+            matches: &'a [TournamentMatch],
+        }
+
+        let rows: Vec<Row> = self
+            .ranking()
+            .into_iter()
+            .enumerate()
+            .map(|(place, i)| Row {
+                rank: place + 1,
+                standing: &self.standings[i],
+            })
+            .collect();
+
+        let mut csv = String::from("rank,name,played,wins,draws,losses,points\n");
+        for row in &rows {
+            let s = row.standing;
+            csv.push_str(&format!(
+                "{},\"{}\",{},{},{},{},{}\n",
+                row.rank,
+                s.name.replace('"', "\"\""),
+                s.played,
+                s.wins,
+                s.draws,
+                s.losses,
+                s.points
+            ));
+        }
+
+        let report = Report {
+            format: self.format,
+            team_size: self.team_size,
+            standings: rows,
+            matches: &self.matches,
+        };
+        let json = serde_json::to_string_pretty(&report)?;
+
+        let json_path = self.output.with_extension("json");
+        let csv_path = self.output.with_extension("csv");
+        write_atomic(&json_path, &json)?;
+        write_atomic(&csv_path, &csv)?;
+        Ok((json_path, csv_path))
+    }
+}
+
+/// Предикат для run_if: запущен турнир
+pub fn tournament_active(tournament: Option<Res<Tournament>>) -> bool {
+    tournament.is_some()
+}
+
+/// Из стартового экрана сразу переходим к турниру
+pub fn begin_tournament(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::Tournament);
+}
+
+/// Между матчами турнира: запускает следующий матч или подводит итоги
+pub fn run_tournament(
+    mut tournament: ResMut<Tournament>,
+    mut battle_kind: ResMut<BattleKind>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut exit: EventWriter<bevy::app::AppExit>,
+) {
+    if let Some((entrant_a, entrant_b)) = tournament.next_match() {
+        info!(
+            "Турнир, раунд {}: {} vs {}",
+            tournament.round, tournament.entrants[entrant_a].name, tournament.entrants[entrant_b].name
+        );
+        *battle_kind = BattleKind::Tournament { entrant_a, entrant_b };
+        next_state.set(GameState::Battle);
+        return;
+    }
+
+    *battle_kind = BattleKind::Training;
+    println!("Турнир завершён.\n{}", tournament.standings_table());
+    match tournament.write_reports() {
+        Ok((json, csv)) => println!("Таблица сохранена в {} и {}", json.display(), csv.display()),
+        Err(e) => error!("Не удалось сохранить таблицу турнира: {}", e),
+    }
+    if tournament.exit_when_done {
+        exit.send(bevy::app::AppExit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entrants(count: usize) -> Vec<Entrant> {
+        (0..count)
+            .map(|i| Entrant {
+                name: format!("e{}", i),
+                controller: AIController::new_random(),
+            })
+            .collect()
+    }
+
+    /// Играет турнир до конца: всегда побеждает участник с меньшим индексом
+    fn play_out(tournament: &mut Tournament) -> usize {
+        let mut played = 0;
+        while let Some((a, b)) = tournament.next_match() {
+            tournament.record(a, b, if a < b { 1.0 } else { 0.0 });
+            played += 1;
+        }
+        played
+    }
+
+    #[test]
+    fn round_robin_plays_every_pair() {
+        let mut tournament = Tournament::new(TournamentFormat::RoundRobin, 1, entrants(4), PathBuf::new());
+        assert_eq!(play_out(&mut tournament), 6);
+        assert_eq!(tournament.ranking(), vec![0, 1, 2, 3]);
+        assert_eq!(tournament.standings[0].points, 9);
+    }
+
+    #[test]
+    fn elimination_brackets_produce_single_winner() {
+        let mut single = Tournament::new(TournamentFormat::SingleElimination, 1, entrants(5), PathBuf::new());
+        assert_eq!(play_out(&mut single), 4);
+        assert_eq!(single.ranking()[0], 0);
+
+        let mut double = Tournament::new(TournamentFormat::DoubleElimination, 1, entrants(5), PathBuf::new());
+        play_out(&mut double);
+        assert_eq!(double.ranking()[0], 0);
+        assert_eq!(double.active(), vec![0]);
+        assert!(double.standings[1..].iter().all(|s| s.losses == 2));
+    }
+}
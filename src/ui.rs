@@ -1,10 +1,18 @@
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts};
 use crate::components::*;
+use crate::ai::{NetworkActivity, NeuralNetwork, INPUT_NAMES, OUTPUT_NAMES};
 use crate::battle::BattleState;
 use crate::genetics::Population;
 use crate::camera::CameraState;
 use crate::systems::TimeMultiplier;
+use crate::elites::{EliteArchive, DIMENSIONS};
+use crate::champion::{ChampionFile, FixedOpponents};
+use crate::classes::TankClasses;
+use crate::weapons::Weapons;
+use crate::battle::{MatchHistory, MatchQueue};
+use std::path::Path;
+use rand::Rng;
 
 /// Ресурс для отслеживания, был ли показан стартовый выбор
 #[derive(Resource, Default)]
@@ -21,15 +29,241 @@ pub struct UiAssets {
     pub font: Handle<Font>,
 }
 
-/// Система отображения UI
+/// Состояние окна архива элит
+#[derive(Resource, Default)]
+pub struct EliteBrowserState {
+    pub visible: bool,
+    /// Команда, за которую выпускается выбранная элита
+    pub team: u32,
+}
+
+/// Состояние окна экспорта/импорта чемпионов
+#[derive(Resource)]
+pub struct ChampionUiState {
+    pub visible: bool,
+    pub path: String,
+    pub status: String,
+}
+
+impl Default for ChampionUiState {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            path: "champion.json".to_string(),
+            status: String::new(),
+        }
+    }
+}
+
+/// Состояние окна боёв между поколениями
+#[derive(Resource, Default)]
+pub struct GenerationBattleUiState {
+    pub visible: bool,
+    pub generation_a: u32,
+    pub generation_b: u32,
+}
+
+/// Состояние окна графиков эволюции
+#[derive(Resource, Default)]
+pub struct StatsPanelState {
+    pub visible: bool,
+}
+
+/// Состояние окна нейросети выбранного танка; как и остальные окна, скрыто до F6
+#[derive(Resource, Default)]
+pub struct NetworkViewState {
+    pub visible: bool,
+}
+
+const BLUE: egui::Color32 = egui::Color32::from_rgb(90, 140, 255);
+const RED: egui::Color32 = egui::Color32::from_rgb(255, 100, 90);
+const GREEN: egui::Color32 = egui::Color32::from_rgb(110, 210, 110);
+const YELLOW: egui::Color32 = egui::Color32::from_rgb(230, 200, 80);
+
+/// Окно графиков: F5 — показать/скрыть. Фитнес по поколениям из истории популяции,
+/// распределения убийств и выживания текущей популяции, сила команд в текущем бою
 pub fn ui_system(
-    _tank_query: Query<(&Tank, &Transform, Option<&AIController>)>,
-    _battle_state: Res<BattleState>,
-    _population: Res<Population>,
-    _camera_state: Res<CameraState>,
+    mut contexts: EguiContexts,
+    keys: Res<Input<KeyCode>>,
+    mut panel: ResMut<StatsPanelState>,
+    battle_state: Res<BattleState>,
+    population: Res<Population>,
+    fighters: Query<&AIController, Without<ExhibitionTank>>,
 ) {
-    // Здесь можно добавить UI используя bevy_egui или встроенный UI Bevy
-    // Пока оставим заглушку
+    if keys.just_pressed(KeyCode::F5) {
+        panel.visible = !panel.visible;
+    }
+    if !panel.visible {
+        return;
+    }
+
+    egui::Window::new("Эволюция")
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::Vec2::new(-8.0, -8.0))
+        .default_width(360.0)
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let history = &population.history;
+            ui.label(format!("Фитнес по поколениям ({} записано)", history.len()));
+            line_plot(
+                ui,
+                &[
+                    ("лучший", GREEN, history.iter().map(|s| s.best_fitness).collect()),
+                    ("средний", YELLOW, history.iter().map(|s| s.mean_fitness).collect()),
+                ],
+            );
+            if let Some(last) = history.last() {
+                ui.label(format!(
+                    "Поколение {}: лучший {:.0}, средний {:.0}, медиана {:.0}",
+                    last.generation, last.best_fitness, last.mean_fitness, last.median_fitness
+                ));
+            }
+            ui.separator();
+
+            ui.label("Разнообразие популяции");
+            line_plot(
+                ui,
+                &[
+                    ("энтропия генов", GREEN, history.iter().map(|s| s.gene_entropy).collect()),
+                    ("поведение", BLUE, history.iter().map(|s| s.behavior_diversity).collect()),
+                ],
+            );
+            if let Some(last) = history.last() {
+                ui.label(format!(
+                    "Расстояние между геномами {:.2}, энтропия {:.2}, поведение {:.2}",
+                    last.diversity, last.gene_entropy, last.behavior_diversity
+                ));
+            }
+            let curriculum = &population.curriculum;
+            let promotion = match curriculum.stage.next() {
+                Some(_) => format!(
+                    ", переход при {:.2} убийства за бой {} поколения подряд",
+                    curriculum.stage.kill_threshold(population.tanks_per_battle()),
+                    crate::curriculum::PROMOTION_WINDOW
+                ),
+                None => String::new(),
+            };
+            ui.label(format!(
+                "Этап обучения: {} ({} пок.{})",
+                curriculum.stage.name(),
+                curriculum.generations_in_stage,
+                promotion
+            ));
+            let stagnation = &population.stagnation;
+            let mut status = format!(
+                "Без улучшения: {} из {} поколений ({:?})",
+                stagnation.generations_without_improvement,
+                population.settings.stagnation.window,
+                population.settings.stagnation.response
+            );
+            if stagnation.hypermutation_left > 0 {
+                status += &format!(", гипермутация ещё {} пок.", stagnation.hypermutation_left);
+            }
+            ui.label(status);
+            ui.separator();
+
+            // Распределения по танкам текущего боя, живым и павшим: статистика геномов
+            // популяции обнуляется эволюцией сразу после боя
+            let fighters: Vec<&AIController> = fighters.iter().chain(battle_state.fallen.iter().map(|(_, ai)| ai)).collect();
+            ui.columns(2, |columns| {
+                columns[0].label("Убийств в текущем бою");
+                let kills: Vec<f32> = fighters.iter().map(|ai| ai.kills as f32).collect();
+                histogram(&mut columns[0], &kills, RED);
+                columns[1].label("Выживание в текущем бою, с");
+                let survival: Vec<f32> = fighters.iter().map(|ai| ai.survival_time).collect();
+                histogram(&mut columns[1], &survival, BLUE);
+            });
+            ui.separator();
+
+            ui.label(format!("Здоровье команд в бою ({:.0} с)", battle_state.real_time));
+            line_plot(
+                ui,
+                &[
+                    ("синие", BLUE, battle_state.strength.iter().map(|(_, h)| h[0]).collect()),
+                    ("красные", RED, battle_state.strength.iter().map(|(_, h)| h[1]).collect()),
+                ],
+            );
+        });
+}
+
+/// Высота графиков в окне статистики
+const PLOT_HEIGHT: f32 = 90.0;
+
+/// Линейный график нескольких рядов с общей вертикальной шкалой и легендой
+fn line_plot(ui: &mut egui::Ui, series: &[(&str, egui::Color32, Vec<f32>)]) {
+    let (response, painter) = ui.allocate_painter(
+        egui::vec2(ui.available_width(), PLOT_HEIGHT),
+        egui::Sense::hover(),
+    );
+    let rect = response.rect;
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+    let values = series.iter().flat_map(|(_, _, v)| v.iter().copied());
+    let (min, max) = values.fold((f32::MAX, f32::MIN), |(lo, hi), v| (lo.min(v), hi.max(v)));
+    if min > max {
+        painter.text(rect.center(), egui::Align2::CENTER_CENTER, "нет данных", egui::FontId::default(), ui.visuals().weak_text_color());
+        return;
+    }
+    let span = (max - min).max(1e-3);
+
+    for (_, color, values) in series {
+        let steps = (values.len().max(2) - 1) as f32;
+        let points: Vec<egui::Pos2> = values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                egui::pos2(
+                    rect.left() + rect.width() * i as f32 / steps,
+                    rect.bottom() - rect.height() * (v - min) / span,
+                )
+            })
+            .collect();
+        painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, *color)));
+    }
+
+    let small = egui::FontId::proportional(10.0);
+    let text_color = ui.visuals().text_color();
+    painter.text(rect.left_top(), egui::Align2::LEFT_TOP, format!("{:.0}", max), small.clone(), text_color);
+    painter.text(rect.left_bottom(), egui::Align2::LEFT_BOTTOM, format!("{:.0}", min), small, text_color);
+
+    ui.horizontal(|ui| {
+        for (name, color, _) in series {
+            ui.colored_label(*color, format!("— {}", name));
+        }
+    });
+}
+
+/// Гистограмма распределения значений
+fn histogram(ui: &mut egui::Ui, values: &[f32], color: egui::Color32) {
+    const BINS: usize = 8;
+    let (response, painter) = ui.allocate_painter(
+        egui::vec2(ui.available_width(), PLOT_HEIGHT),
+        egui::Sense::hover(),
+    );
+    let rect = response.rect;
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+    if values.is_empty() {
+        return;
+    }
+
+    let min = values.iter().copied().fold(f32::MAX, f32::min);
+    let max = values.iter().copied().fold(f32::MIN, f32::max);
+    let width = ((max - min) / BINS as f32).max(1e-3);
+    let mut counts = [0u32; BINS];
+    for v in values {
+        counts[(((v - min) / width) as usize).min(BINS - 1)] += 1;
+    }
+    let tallest = counts.iter().copied().max().unwrap_or(1).max(1) as f32;
+
+    let bar_width = rect.width() / BINS as f32;
+    for (i, &count) in counts.iter().enumerate() {
+        let height = rect.height() * count as f32 / tallest;
+        let bar = egui::Rect::from_min_max(
+            egui::pos2(rect.left() + bar_width * i as f32 + 1.0, rect.bottom() - height),
+            egui::pos2(rect.left() + bar_width * (i + 1) as f32 - 1.0, rect.bottom()),
+        );
+        painter.rect_filled(bar, 0.0, color);
+    }
+    ui.label(format!("{:.1} … {:.1}", min, max));
 }
 
 /// Система UI для состояния Setup
@@ -139,9 +373,15 @@ pub fn update_stats_ui(
         
         let current_best = population.genomes.first().map(|g| g.fitness).unwrap_or(0.0);
         let max_fitness = population.best_genome.as_ref().map(|b| b.fitness).unwrap_or(0.0);
+        let best_rating = population
+            .genomes
+            .iter()
+            .map(|g| g.rating.elo)
+            .fold(f32::MIN, f32::max);
         
         text.sections[0].value = format!(
-            "Поколение: {}\n\
+            "Поколение: {} (бой {} из {})\n\
+             Этап обучения: {}\n\
              Танков живых: {}\n\
              Команда 0 (синие): {}\n\
              Команда 1 (красные): {}\n\
@@ -149,9 +389,13 @@ pub fn update_stats_ui(
              Скорость времени: {:.2}x\n\
              Лучший фитнес: {:.0}\n\
              Максимальный фитнес: {:.0}\n\
+             Лучший рейтинг Эло: {:.0}\n\
              \n\
              F1 — показать/скрыть помощь и слайдер скорости",
             population.generation,
+            population.evaluation_battle + 1,
+            population.battles_per_generation(),
+            population.curriculum.stage.name(),
             alive_tanks,
             team0_count,
             team1_count,
@@ -160,6 +404,441 @@ pub fn update_stats_ui(
             time_multiplier.scale,
             current_best,
             max_fitness,
+            best_rating,
+        );
+    }
+}
+
+/// Окно архива MAP-Elites: F2 — показать/скрыть, «В бой» — выпустить элиту в текущий бой
+#[allow(clippy::too_many_arguments)]
+pub fn elite_browser_ui_system(
+    mut contexts: EguiContexts,
+    keys: Res<Input<KeyCode>>,
+    mut browser: ResMut<EliteBrowserState>,
+    archive: Res<EliteArchive>,
+    state: Res<State<GameState>>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    classes: Res<TankClasses>,
+) {
+    if keys.just_pressed(KeyCode::F2) {
+        browser.visible = !browser.visible;
+    }
+    if !browser.visible {
+        return;
+    }
+
+    let in_battle = *state.get() == GameState::Battle;
+    let mut team = browser.team;
+    let mut to_spawn = None;
+
+    // Сначала самые сильные элиты
+    let mut order: Vec<usize> = (0..archive.elites.len()).collect();
+    order.sort_by(|&a, &b| {
+        archive.elites[b].controller.fitness
+            .partial_cmp(&archive.elites[a].controller.fitness)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    egui::Window::new("Архив элит (MAP-Elites)")
+        .anchor(egui::Align2::LEFT_BOTTOM, egui::Vec2::new(8.0, -8.0))
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(format!(
+                "Заполнено ячеек: {} ({:.0}%)",
+                archive.elites.len(),
+                archive.coverage() * 100.0
+            ));
+            ui.horizontal(|ui| {
+                ui.label("Команда:");
+                ui.radio_value(&mut team, 0, "Синие");
+                ui.radio_value(&mut team, 1, "Красные");
+            });
+            ui.separator();
+
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                egui::Grid::new("elites_grid").striped(true).show(ui, |ui| {
+                    ui.label("Ячейка");
+                    ui.label(DIMENSIONS.join(" / "));
+                    ui.label("Фитнес");
+                    ui.label("Поколение");
+                    ui.label("");
+                    ui.end_row();
+
+                    for &index in &order {
+                        let elite = &archive.elites[index];
+                        ui.label(format!("{:?}", elite.cell));
+                        ui.label(format!(
+                            "{:.2} / {:.2} / {:.2}",
+                            elite.descriptor[0], elite.descriptor[1], elite.descriptor[2]
+                        ));
+                        ui.label(format!("{:.0}", elite.controller.fitness));
+                        ui.label(elite.generation.to_string());
+                        if ui.add_enabled(in_battle, egui::Button::new("В бой")).clicked() {
+                            to_spawn = Some(index);
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+
+    browser.team = team;
+
+    if let Some(index) = to_spawn {
+        let elite = &archive.elites[index];
+        let mut rng = rand::thread_rng();
+        let position = Vec3::new(rng.gen_range(-40.0..40.0), 0.5, rng.gen_range(-40.0..40.0));
+        let entity = crate::battle::spawn_tank(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            position,
+            team,
+            classes.for_slot(team, 0),
+            Some(AIController::with_genome(elite.controller.genome.clone())),
         );
+        commands.entity(entity).insert(ExhibitionTank);
+        info!("Элита из ячейки {:?} выпущена в бой за команду {}", elite.cell, team);
+    }
+}
+
+/// Окно чемпионов: F3 — показать/скрыть; экспорт лучшего или выбранного танка,
+/// импорт в популяцию или как фиксированного противника
+pub fn champion_ui_system(
+    mut contexts: EguiContexts,
+    keys: Res<Input<KeyCode>>,
+    mut champion_ui: ResMut<ChampionUiState>,
+    mut population: ResMut<Population>,
+    mut opponents: ResMut<FixedOpponents>,
+    camera_state: Res<CameraState>,
+    ai_query: Query<&AIController>,
+) {
+    if keys.just_pressed(KeyCode::F3) {
+        champion_ui.visible = !champion_ui.visible;
+    }
+    if !champion_ui.visible {
+        return;
+    }
+
+    let selected = camera_state
+        .selected_tank
+        .and_then(|entity| ai_query.get(entity).ok());
+    let best = population
+        .best_genome
+        .as_ref()
+        .or_else(|| population.genomes.first())
+        .cloned();
+
+    let mut path = std::mem::take(&mut champion_ui.path);
+    let mut status = None;
+
+    egui::Window::new("Чемпионы")
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::Vec2::new(-8.0, -8.0))
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Файл:");
+                ui.text_edit_singleline(&mut path);
+            });
+
+            ui.horizontal(|ui| {
+                if ui.add_enabled(best.is_some(), egui::Button::new("Экспорт лучшего")).clicked() {
+                    if let Some(best) = &best {
+                        let champion = ChampionFile::from_controller(best, "best", "population", population.generation);
+                        status = Some(export_status(&champion, &path));
+                    }
+                }
+                if ui.add_enabled(selected.is_some(), egui::Button::new("Экспорт выбранного")).clicked() {
+                    if let Some(ai) = selected {
+                        let champion = ChampionFile::from_controller(ai, "selected", "battle", population.generation);
+                        status = Some(export_status(&champion, &path));
+                    }
+                }
+            });
+
+            ui.horizontal(|ui| {
+                if ui.button("Импорт в популяцию").clicked() {
+                    status = Some(match ChampionFile::load(Path::new(&path)) {
+                        Ok(champion) => match population.import_champion(champion.to_controller()) {
+                            Ok(()) => format!("«{}» добавлен в популяцию", champion.metadata.name),
+                            Err(e) => e,
+                        },
+                        Err(e) => e.to_string(),
+                    });
+                }
+                if ui.button("Импорт как противник").clicked() {
+                    status = Some(match ChampionFile::load(Path::new(&path)) {
+                        Ok(champion) => {
+                            let message = format!("«{}» будет противником со следующего боя", champion.metadata.name);
+                            opponents.champions.push(champion);
+                            message
+                        }
+                        Err(e) => e.to_string(),
+                    });
+                }
+            });
+
+            ui.label(format!("Фиксированных противников: {}", opponents.champions.len()));
+            if !opponents.champions.is_empty() && ui.button("Убрать противников").clicked() {
+                opponents.champions.clear();
+            }
+            if !champion_ui.status.is_empty() {
+                ui.separator();
+                ui.label(&champion_ui.status);
+            }
+        });
+
+    champion_ui.path = path;
+    if let Some(status) = status {
+        info!("{}", status);
+        champion_ui.status = status;
+    }
+}
+
+/// Сохраняет чемпиона и возвращает сообщение для окна
+fn export_status(champion: &ChampionFile, path: &str) -> String {
+    match champion.save(Path::new(path)) {
+        Ok(()) => format!("Чемпион сохранён в {}", path),
+        Err(e) => e.to_string(),
+    }
+}
+
+/// Окно боёв между поколениями: F4 — показать/скрыть. Бой ставится в очередь
+/// и начнётся вместо следующего тренировочного боя.
+pub fn generation_battle_ui_system(
+    mut contexts: EguiContexts,
+    keys: Res<Input<KeyCode>>,
+    mut battle_ui: ResMut<GenerationBattleUiState>,
+    population: Res<Population>,
+    mut match_queue: ResMut<MatchQueue>,
+    match_history: Res<MatchHistory>,
+) {
+    if keys.just_pressed(KeyCode::F4) {
+        battle_ui.visible = !battle_ui.visible;
+    }
+    if !battle_ui.visible {
+        return;
+    }
+
+    let (Some(first), Some(last)) = (population.champions.first(), population.champions.last()) else {
+        egui::Window::new("Бой поколений").show(contexts.ctx_mut(), |ui| {
+            ui.label("Архив чемпионов пуст: дождитесь окончания первого поколения");
+        });
+        return;
+    };
+    let range = first.generation..=last.generation;
+    let mut generation_a = battle_ui.generation_a.clamp(*range.start(), *range.end());
+    let mut generation_b = battle_ui.generation_b.clamp(*range.start(), *range.end());
+
+    egui::Window::new("Бой поколений").show(contexts.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Синие — поколение");
+            ui.add(egui::DragValue::new(&mut generation_a).clamp_range(range.clone()));
+            ui.label("Красные — поколение");
+            ui.add(egui::DragValue::new(&mut generation_b).clamp_range(range.clone()));
+        });
+        if ui.button("В очередь").clicked() {
+            match_queue.pending.push_back((generation_a, generation_b));
+        }
+        if !match_queue.pending.is_empty() {
+            ui.label(format!("В очереди: {}", match_queue.pending.len()));
+        }
+
+        ui.separator();
+        egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+            for result in match_history.results.iter().rev() {
+                ui.label(result.summary());
+            }
+        });
+    });
+
+    battle_ui.generation_a = generation_a;
+    battle_ui.generation_b = generation_b;
+}
+
+/// Таблица текущего турнира
+pub fn tournament_ui_system(
+    mut contexts: EguiContexts,
+    tournament: Res<crate::tournament::Tournament>,
+) {
+    let title = if tournament.finished {
+        "Турнир завершён".to_string()
+    } else {
+        format!("Турнир: раунд {}, сыграно матчей {}", tournament.round, tournament.matches.len())
+    };
+    egui::Window::new("Турнир")
+        .anchor(egui::Align2::LEFT_BOTTOM, egui::Vec2::new(8.0, -8.0))
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(title);
+            ui.separator();
+            egui::Grid::new("tournament_grid").striped(true).show(ui, |ui| {
+                for header in ["#", "Участник", "Игр", "Поб", "Нич", "Пор", "Очки"] {
+                    ui.label(header);
+                }
+                ui.end_row();
+                for (place, &i) in tournament.ranking().iter().enumerate() {
+                    let s = &tournament.standings[i];
+                    ui.label((place + 1).to_string());
+                    if s.eliminated_in_round.is_some() {
+                        ui.weak(&s.name);
+                    } else {
+                        ui.label(&s.name);
+                    }
+                    ui.label(s.played.to_string());
+                    ui.label(s.wins.to_string());
+                    ui.label(s.draws.to_string());
+                    ui.label(s.losses.to_string());
+                    ui.label(s.points.to_string());
+                    ui.end_row();
+                }
+            });
+        });
+}
+
+/// Окно бенчмарка: доля побед агента против каждой стратегии ботов
+pub fn benchmark_ui_system(
+    mut contexts: EguiContexts,
+    benchmark: Res<crate::bots::Benchmark>,
+) {
+    egui::Window::new("Бенчмарк")
+        .anchor(egui::Align2::LEFT_BOTTOM, egui::Vec2::new(8.0, -8.0))
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(format!(
+                "{}: {} по {} матчей",
+                benchmark.agent_name,
+                if benchmark.finished { "завершён" } else { "идёт" },
+                benchmark.matches_per_strategy
+            ));
+            ui.separator();
+            egui::Grid::new("benchmark_grid").striped(true).show(ui, |ui| {
+                for header in ["Стратегия", "Поб", "Нич", "Пор", "Победы"] {
+                    ui.label(header);
+                }
+                ui.end_row();
+                for r in &benchmark.records {
+                    ui.label(r.strategy.name());
+                    ui.label(r.wins.to_string());
+                    ui.label(r.draws.to_string());
+                    ui.label(r.losses.to_string());
+                    ui.label(format!("{:.0}%", r.win_rate() * 100.0));
+                    ui.end_row();
+                }
+            });
+            ui.label(format!("Итого побед: {:.0}%", benchmark.overall_win_rate() * 100.0));
+        });
+}
+
+/// Окно нейросети выбранного танка: F6 — показать/скрыть. Магазины и нагрев его оружия,
+/// входы, скрытый слой и выходы с последнего такта ai_control_system; цвет связи — знак
+/// веса, толщина — его величина
+#[allow(clippy::type_complexity)]
+pub fn network_view_ui_system(
+    mut contexts: EguiContexts,
+    keys: Res<Input<KeyCode>>,
+    weapons: Res<Weapons>,
+    mut view: ResMut<NetworkViewState>,
+    selected: Query<(&Tank, &NeuralNetwork, &NetworkActivity, Option<&PlayerControlled>), With<Selected>>,
+) {
+    if keys.just_pressed(KeyCode::F6) {
+        view.visible = !view.visible;
+    }
+    if !view.visible {
+        return;
+    }
+    let Some((tank, network, activity, player)) = selected.iter().next() else {
+        return;
+    };
+
+    egui::Window::new("Нейросеть танка")
+        .anchor(egui::Align2::LEFT_TOP, egui::Vec2::new(8.0, 8.0))
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(format!("Класс: {}", tank.class.name()));
+            for (index, (kind, state)) in tank.weapons.iter().zip(&tank.gun.weapons).enumerate() {
+                let def = weapons.get(*kind);
+                let ammo = if state.reload > 0.0 {
+                    format!("перезарядка {:.1} с", state.reload)
+                } else if def.magazine == 0 {
+                    "без магазина".to_string()
+                } else {
+                    format!("{}/{}", def.magazine.saturating_sub(state.spent), def.magazine)
+                };
+                let marker = if index == tank.gun.selected { "▶" } else { " " };
+                let overheated = if state.overheated { ", перегрев" } else { "" };
+                ui.label(format!("{} {}: {}, нагрев {:.0}%{}", marker, kind.name(), ammo, state.heat * 100.0, overheated));
+            }
+            if player.is_some() {
+                ui.label("Танком управляет игрок: показан последний такт сети");
+            }
+            network_graph(ui, network, activity);
+        });
+}
+
+/// Граф сети: три столбца нейронов и связи между ними
+fn network_graph(ui: &mut egui::Ui, network: &NeuralNetwork, activity: &NetworkActivity) {
+    const WIDTH: f32 = 440.0;
+    const HEIGHT: f32 = 270.0;
+    const RADIUS: f32 = 7.0;
+    let (response, painter) = ui.allocate_painter(egui::vec2(WIDTH, HEIGHT), egui::Sense::hover());
+    let rect = response.rect;
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+    let column = |x: f32, count: usize| -> Vec<egui::Pos2> {
+        let step = rect.height() / count as f32;
+        (0..count)
+            .map(|i| egui::pos2(rect.left() + x, rect.top() + step * (i as f32 + 0.5)))
+            .collect()
+    };
+    let inputs = column(130.0, activity.inputs.len());
+    let hidden = column(WIDTH / 2.0 + 30.0, activity.hidden.len());
+    let outputs = column(WIDTH - 90.0, activity.outputs.len());
+
+    connections(&painter, &inputs, &hidden, |i, j| network.input_hidden_weight(i, j));
+    connections(&painter, &hidden, &outputs, |j, k| network.hidden_output_weight(j, k));
+
+    // Нейроны: яркость — значение (входы -1..1, остальные 0..1)
+    let small = egui::FontId::proportional(11.0);
+    let text_color = ui.visuals().text_color();
+    let neuron = |pos: egui::Pos2, value: f32| {
+        let level = (value.clamp(-1.0, 1.0).abs() * 255.0) as u8;
+        let fill = if value >= 0.0 {
+            egui::Color32::from_rgb(level, level, level)
+        } else {
+            egui::Color32::from_rgb(level, level / 3, level / 3)
+        };
+        painter.circle(pos, RADIUS, fill, egui::Stroke::new(1.0, text_color));
+    };
+    for ((&pos, &value), name) in inputs.iter().zip(&activity.inputs).zip(INPUT_NAMES) {
+        neuron(pos, value);
+        painter.text(pos - egui::vec2(RADIUS + 4.0, 0.0), egui::Align2::RIGHT_CENTER, format!("{} {:+.2}", name, value), small.clone(), text_color);
+    }
+    for (&pos, &value) in hidden.iter().zip(&activity.hidden) {
+        neuron(pos, value);
+    }
+    for ((&pos, &value), name) in outputs.iter().zip(&activity.outputs).zip(OUTPUT_NAMES) {
+        neuron(pos, value);
+        painter.text(pos + egui::vec2(RADIUS + 4.0, 0.0), egui::Align2::LEFT_CENTER, format!("{:.2} {}", value, name), small.clone(), text_color);
+    }
+}
+
+/// Связи слоя: зелёные — положительный вес, красные — отрицательный; яркость и толщина
+/// по величине веса относительно самого сильного в слое
+fn connections(painter: &egui::Painter, from: &[egui::Pos2], to: &[egui::Pos2], weight: impl Fn(usize, usize) -> f32) {
+    let scale = (0..from.len())
+        .flat_map(|i| (0..to.len()).map(move |j| (i, j)))
+        .map(|(i, j)| weight(i, j).abs())
+        .fold(1e-3, f32::max);
+    for (i, &a) in from.iter().enumerate() {
+        for (j, &b) in to.iter().enumerate() {
+            let w = weight(i, j) / scale;
+            let color = if w >= 0.0 { GREEN } else { RED };
+            let alpha = (40.0 + 200.0 * w.abs()) as u8;
+            let color = egui::Color32::from_rgba_unmultiplied(color.r(), color.g(), color.b(), alpha);
+            painter.line_segment([a, b], egui::Stroke::new(0.5 + 2.5 * w.abs(), color));
+        }
     }
 }
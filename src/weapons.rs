@@ -0,0 +1,446 @@
+use bevy::prelude::*;
+use crate::components::Projectile;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Ускорение свободного падения для баллистических снарядов, м/с²
+pub const GRAVITY: f32 = 9.81;
+/// Перегретый ствол снова стреляет, когда нагрев опустится до этой доли
+pub const COOLED_HEAT: f32 = 0.3;
+
+/// Вид оружия
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WeaponKind {
+    #[default]
+    Cannon,
+    MachineGun,
+    Artillery,
+    Mine,
+}
+
+impl WeaponKind {
+    pub const ALL: [WeaponKind; 4] = [WeaponKind::Cannon, WeaponKind::MachineGun, WeaponKind::Artillery, WeaponKind::Mine];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WeaponKind::Cannon => "пушка",
+            WeaponKind::MachineGun => "пулемёт",
+            WeaponKind::Artillery => "гаубица",
+            WeaponKind::Mine => "мины",
+        }
+    }
+
+    /// Оружие из набора танка по выходу сети «оружие» (0..1 делится поровну между видами)
+    pub fn select(weapons: &[WeaponKind], selector: f32) -> WeaponKind {
+        weapons.get(Self::select_index(weapons.len(), selector)).copied().unwrap_or_default()
+    }
+
+    /// Номер выбранного оружия в наборе из count видов
+    pub fn select_index(count: usize, selector: f32) -> usize {
+        ((selector.clamp(0.0, 1.0) * count as f32) as usize).min(count.saturating_sub(1))
+    }
+}
+
+/// Как летит снаряд (см. projectile_movement_system и collision_system)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Flight {
+    /// По прямой от ствола до первого танка или препятствия
+    Direct,
+    /// По дуге под действием тяжести над препятствиями; рвётся при падении на землю
+    Ballistic,
+    /// Лежит на месте под танком и рвётся, когда рядом оказывается другой танк
+    Mine,
+}
+
+/// Оружие. Урон и перезарядка — множители к орудию класса танка (ClassDef::damage, cooldown),
+/// так что тяжёлый пулемёт бьёт сильнее лёгкого
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WeaponDef {
+    pub kind: WeaponKind,
+    pub flight: Flight,
+    pub damage_scale: f32,
+    pub cooldown_scale: f32,
+    /// Начальная скорость снаряда, м/с
+    pub speed: f32,
+    /// Угол возвышения ствола для баллистики, градусы
+    #[serde(default)]
+    pub elevation: f32,
+    /// Сколько живёт снаряд или мина, с
+    pub lifetime: f32,
+    /// Снаряд попадает в средний танк (мина срабатывает), если центр танка ближе этого;
+    /// для других корпусов дистанция масштабируется (см. Tank::hit_distance)
+    pub hit_radius: f32,
+    /// Радиус осколков при разрыве; 0 — урон только тому, в кого попали
+    #[serde(default)]
+    pub splash_radius: f32,
+    /// Через сколько секунд после выстрела снаряд начинает реагировать на танки
+    #[serde(default)]
+    pub arm_time: f32,
+    /// Выстрелов в магазине; 0 — стреляет без перезарядки магазина
+    #[serde(default)]
+    pub magazine: u32,
+    /// Перезарядка опустевшего магазина, с
+    #[serde(default)]
+    pub reload_time: f32,
+    /// Нагрев ствола за выстрел, доля от перегрева
+    #[serde(default)]
+    pub heat_per_shot: f32,
+    /// Остывание ствола, доля в секунду
+    #[serde(default)]
+    pub cooling: f32,
+}
+
+impl WeaponDef {
+    /// Встроенное оружие; пушка совпадает с единственным орудием ранних версий:
+    /// без магазина и без нагрева ствола
+    pub fn builtin(kind: WeaponKind) -> Self {
+        let base = Self {
+            kind,
+            flight: Flight::Direct,
+            damage_scale: 1.0,
+            cooldown_scale: 1.0,
+            speed: 30.0,
+            elevation: 0.0,
+            lifetime: 3.0,
+            hit_radius: 1.5,
+            splash_radius: 0.0,
+            arm_time: 0.0,
+            magazine: 0,
+            reload_time: 0.0,
+            heat_per_shot: 0.0,
+            cooling: 0.0,
+        };
+        match kind {
+            WeaponKind::Cannon => base,
+            WeaponKind::MachineGun => Self {
+                damage_scale: 0.3,
+                cooldown_scale: 0.15,
+                speed: 45.0,
+                lifetime: 1.2,
+                magazine: 30,
+                reload_time: 4.0,
+                heat_per_shot: 0.06,
+                cooling: 0.2,
+                ..base
+            },
+            WeaponKind::Artillery => Self {
+                flight: Flight::Ballistic,
+                damage_scale: 1.6,
+                cooldown_scale: 2.5,
+                speed: 22.0,
+                elevation: 40.0,
+                lifetime: 5.0,
+                splash_radius: 4.0,
+                magazine: 3,
+                reload_time: 6.0,
+                heat_per_shot: 0.3,
+                cooling: 0.1,
+                ..base
+            },
+            WeaponKind::Mine => Self {
+                flight: Flight::Mine,
+                damage_scale: 2.0,
+                cooldown_scale: 5.0,
+                speed: 0.0,
+                lifetime: 30.0,
+                hit_radius: 2.5,
+                splash_radius: 4.0,
+                arm_time: 1.5,
+                magazine: 3,
+                reload_time: 20.0,
+                heat_per_shot: 0.0,
+                ..base
+            },
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let valid = self.damage_scale >= 0.0
+            && self.cooldown_scale > 0.0
+            && self.speed >= 0.0
+            && self.lifetime > 0.0
+            && self.hit_radius > 0.0
+            && self.splash_radius >= 0.0
+            && self.arm_time >= 0.0
+            && self.reload_time >= 0.0
+            && self.heat_per_shot >= 0.0
+            && self.cooling >= 0.0;
+        if valid {
+            Ok(())
+        } else {
+            Err(format!("оружие {:?}: перезарядка, время жизни и радиус попадания должны быть больше нуля, остальное — не меньше", self.kind))
+        }
+    }
+
+    /// Выстрел танка: положение и снаряд. tank — трансформ корпуса, повёрнутый на угол башни;
+    /// орудие смотрит по его локальной оси +Z
+    pub fn launch(&self, owner: Entity, team: u32, tank: &Transform, class_damage: f32) -> (Transform, Projectile) {
+        let aim = tank.back();
+        let (position, velocity) = match self.flight {
+            Flight::Direct | Flight::Ballistic => {
+                // Позиция конца ствола: turret center + barrel offset
+                let turret_offset = Vec3::Y * 0.75;
+                let barrel_end_offset = Vec3::new(0.0, 0.0, 1.25); // конец ствола от turret center
+                let elevation = self.elevation.to_radians();
+                (
+                    tank.translation + tank.rotation * (turret_offset + barrel_end_offset),
+                    (aim * elevation.cos() + Vec3::Y * elevation.sin()) * self.speed,
+                )
+            }
+            Flight::Mine => (Vec3::new(tank.translation.x, 0.0, tank.translation.z), Vec3::ZERO),
+        };
+        let projectile = Projectile {
+            damage: class_damage * self.damage_scale,
+            velocity,
+            lifetime: Timer::from_seconds(self.lifetime, TimerMode::Once),
+            armed: Timer::from_seconds(self.arm_time, TimerMode::Once),
+            owner,
+            team,
+            flight: self.flight,
+            hit_radius: self.hit_radius,
+            splash_radius: self.splash_radius,
+        };
+        (Transform::from_translation(position).with_rotation(tank.rotation), projectile)
+    }
+
+    /// Сфера или плоская мина под вид оружия
+    pub fn mesh(&self) -> Mesh {
+        match self.flight {
+            Flight::Mine => Mesh::from(shape::Cylinder { radius: 0.6, height: 0.15, ..default() }),
+            _ => Mesh::from(shape::UVSphere { radius: 0.1 + 0.2 * self.damage_scale.min(1.5), ..default() }),
+        }
+    }
+}
+
+/// Магазин и ствол одного оружия танка
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WeaponState {
+    /// Выстрелов из текущего магазина
+    pub spent: u32,
+    /// Сколько ещё перезаряжается магазин, с; 0 — не перезаряжается
+    pub reload: f32,
+    /// Нагрев ствола: 1 — перегрев
+    pub heat: f32,
+    /// Ствол перегрет и не стреляет, пока не остынет до COOLED_HEAT
+    pub overheated: bool,
+    /// Доля патронов в магазине (0 во время перезарядки) — вход сети
+    pub ammo: f32,
+}
+
+impl Default for WeaponState {
+    fn default() -> Self {
+        Self {
+            spent: 0,
+            reload: 0.0,
+            heat: 0.0,
+            overheated: false,
+            ammo: 1.0,
+        }
+    }
+}
+
+impl WeaponState {
+    /// Остывание ствола и перезарядка магазина за dt секунд
+    pub fn tick(&mut self, dt: f32, def: &WeaponDef) {
+        self.heat = (self.heat - def.cooling * dt).max(0.0);
+        if self.overheated && self.heat <= COOLED_HEAT {
+            self.overheated = false;
+        }
+        if self.reload > 0.0 {
+            self.reload -= dt;
+            if self.reload <= 0.0 {
+                self.reload = 0.0;
+                self.spent = 0;
+            }
+        }
+        self.update_ammo(def);
+    }
+
+    /// Магазин не пуст и ствол не перегрет
+    pub fn ready(&self, def: &WeaponDef) -> bool {
+        !self.overheated && self.reload <= 0.0 && (def.magazine == 0 || self.spent < def.magazine)
+    }
+
+    /// Выстрел: патрон из магазина и нагрев ствола; опустевший магазин сразу встаёт на перезарядку
+    pub fn fire(&mut self, def: &WeaponDef) {
+        if def.magazine > 0 {
+            self.spent += 1;
+        }
+        self.heat += def.heat_per_shot;
+        if self.heat >= 1.0 {
+            self.heat = 1.0;
+            self.overheated = true;
+        }
+        if def.magazine > 0 && self.spent >= def.magazine {
+            if def.reload_time > 0.0 {
+                self.reload = def.reload_time;
+            } else {
+                self.spent = 0;
+            }
+        }
+        self.update_ammo(def);
+    }
+
+    fn update_ammo(&mut self, def: &WeaponDef) {
+        self.ammo = if def.magazine == 0 {
+            1.0
+        } else if self.reload > 0.0 {
+            0.0
+        } else {
+            def.magazine.saturating_sub(self.spent) as f32 / def.magazine as f32
+        };
+    }
+}
+
+/// Орудие танка: состояние каждого оружия из Tank::weapons (в том же порядке)
+/// и какое из них выбрано
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Gun {
+    pub weapons: Vec<WeaponState>,
+    pub selected: usize,
+}
+
+impl Gun {
+    pub fn new(count: usize) -> Self {
+        Self {
+            weapons: vec![WeaponState::default(); count],
+            selected: 0,
+        }
+    }
+
+    /// Выбранное оружие; у танка без оружия — состояние по умолчанию
+    pub fn current(&self) -> WeaponState {
+        self.weapons.get(self.selected).copied().unwrap_or_default()
+    }
+}
+
+/// Урон осколков на расстоянии distance от разрыва: полный в центре, половина на краю
+pub fn splash_damage(damage: f32, splash_radius: f32, distance: f32) -> Option<f32> {
+    (distance < splash_radius).then(|| damage * (1.0 - 0.5 * distance / splash_radius))
+}
+
+/// Оружие, доступное танкам. Читается из JSON (--weapons): виды, которых в файле нет,
+/// берутся встроенные
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Weapons {
+    pub weapons: Vec<WeaponDef>,
+}
+
+impl Default for Weapons {
+    fn default() -> Self {
+        Self {
+            weapons: WeaponKind::ALL.into_iter().map(WeaponDef::builtin).collect(),
+        }
+    }
+}
+
+impl Weapons {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut loaded: Weapons = serde_json::from_str(&json).map_err(|e| format!("{}: {}", path.display(), e))?;
+        for def in &loaded.weapons {
+            def.validate().map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        for kind in WeaponKind::ALL {
+            if !loaded.weapons.iter().any(|def| def.kind == kind) {
+                loaded.weapons.push(WeaponDef::builtin(kind));
+            }
+        }
+        Ok(loaded)
+    }
+
+    pub fn get(&self, kind: WeaponKind) -> &WeaponDef {
+        self.weapons
+            .iter()
+            .find(|def| def.kind == kind)
+            .expect("все виды оружия заполняются при загрузке")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn artillery_flies_an_arc() {
+        let artillery = WeaponDef::builtin(WeaponKind::Artillery);
+        let tank = Transform::from_xyz(0.0, 0.5, 0.0);
+        let (start, shell) = artillery.launch(Entity::PLACEHOLDER, 0, &tank, 20.0);
+        assert_eq!(shell.damage, 32.0);
+        assert!(shell.velocity.y > 0.0 && shell.velocity.z > 0.0);
+
+        // Дальность по кинематике того же шага, что в projectile_movement_system
+        let (mut position, mut velocity, dt) = (start.translation, shell.velocity, 0.02);
+        while position.y > 0.0 {
+            velocity.y -= GRAVITY * dt;
+            position += velocity * dt;
+        }
+        assert!((40.0..55.0).contains(&position.z), "дальность {}", position.z);
+    }
+
+    #[test]
+    fn selection_splits_network_output() {
+        let set = [WeaponKind::MachineGun, WeaponKind::Mine];
+        assert_eq!(WeaponKind::select(&set, 0.2), WeaponKind::MachineGun);
+        assert_eq!(WeaponKind::select(&set, 0.7), WeaponKind::Mine);
+        assert_eq!(WeaponKind::select(&set, 1.0), WeaponKind::Mine);
+        assert_eq!(WeaponKind::select(&[WeaponKind::Cannon], 0.9), WeaponKind::Cannon);
+    }
+
+    #[test]
+    fn splash_fades_to_the_edge() {
+        assert_eq!(splash_damage(40.0, 4.0, 4.0), None);
+        assert_eq!(splash_damage(40.0, 4.0, 2.0), Some(30.0));
+    }
+
+    #[test]
+    fn cannon_fires_without_magazine_or_heat() {
+        let gun = WeaponDef::builtin(WeaponKind::Cannon);
+        let mut state = WeaponState::default();
+        for _ in 0..100 {
+            assert!(state.ready(&gun));
+            state.fire(&gun);
+        }
+        assert_eq!((state.spent, state.ammo, state.heat), (0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn machine_gun_overheats_before_the_magazine_ends() {
+        let gun = WeaponDef::builtin(WeaponKind::MachineGun);
+        let mut state = WeaponState::default();
+        let mut shots = 0;
+        while state.ready(&gun) {
+            state.fire(&gun);
+            shots += 1;
+        }
+        // 17 × 6% — ствол перегрелся, а в магазине ещё 13 патронов
+        assert_eq!(shots, 17);
+        assert!(state.overheated && state.reload == 0.0);
+        assert_eq!(state.ammo, 13.0 / 30.0);
+
+        state.tick(4.0, &gun);
+        assert!(state.ready(&gun) && state.heat <= COOLED_HEAT, "нагрев {}", state.heat);
+    }
+
+    #[test]
+    fn empty_magazine_reloads() {
+        let gun = WeaponDef::builtin(WeaponKind::MachineGun);
+        let mut state = WeaponState::default();
+        // Короткими очередями: ствол успевает остыть между выстрелами
+        for _ in 0..29 {
+            assert!(state.ready(&gun));
+            state.fire(&gun);
+            state.tick(0.3, &gun);
+        }
+        assert_eq!(state.ammo, 1.0 / 30.0);
+        state.fire(&gun);
+        assert!(!state.ready(&gun) && !state.overheated);
+        assert_eq!(state.ammo, 0.0);
+
+        state.tick(gun.reload_time, &gun);
+        assert_eq!((state.spent, state.ammo), (0, 1.0));
+        assert!(state.ready(&gun));
+    }
+}